@@ -84,18 +84,353 @@
     clippy::wildcard_enum_match_arm,
 )]
 
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
 use anyhow::Context;
-use log::info;
-use obmm_rs::{UbPrivData, ObmmExportFlags, MAX_NUMA_NODES, mem_export};
+use clap::{Parser, Subcommand};
+use log::{info, warn};
+use obmm_rs::{ExportProfile, MemId, MemlinkConfig, MemlinkDir, MemlinkMeta, ObmmError, ObmmImportFlags, ObmmMemDesc, UbPrivData, MAX_NUMA_NODES, find_overlaps, flags_compatible, mem_export, mem_import, run_selftest};
 
-fn main() -> anyhow::Result<()> {
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
-    let export_id = 1;
-    info!("Memory linking and analysis utilities");
+/// Default location of the export profiles file
+const DEFAULT_PROFILES_FILE: &str = "/etc/memlink/profiles.toml";
+/// Default location of the memlink configuration file, used when present
+const DEFAULT_CONFIG_FILE: &str = "/etc/memlink/config.toml";
+/// Prefix identifying a signed descriptor envelope
+const ENVELOPE_PREFIX: &str = "obmm:v1:";
+
+/// Command line interface of memlink
+#[derive(Parser, Debug)]
+#[command(name = "memlink", version, about)]
+struct Cli {
+    /// Path to the memlink configuration file
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Subcommand to run, exports the default region when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// memlink subcommands
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export a memory region
+    Export {
+        /// Name of the export profile to apply instead of the default shape
+        #[arg(long)]
+        profile: Option<String>,
+        /// Path to the export profiles file
+        #[arg(long, default_value = DEFAULT_PROFILES_FILE)]
+        profiles_file: PathBuf,
+        /// Write the descriptor to stdout, e.g. to pipe it into `memlink import -`
+        #[arg(long)]
+        stdout: bool,
+        /// Sign the descriptor written to stdout as an envelope with the key in this file
+        #[arg(long, requires = "stdout")]
+        key_file: Option<PathBuf>,
+    },
+    /// Import a descriptor, JSON or envelope, and print the resulting Memory ID
+    Import {
+        /// Descriptor file to import, `-` to read it from stdin
+        source: PathBuf,
+        /// Key file checking the MAC of an envelope descriptor
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+        /// Import flags, e.g. "ALLOWMMAP | REMOTENUMA"
+        #[arg(long, default_value = "")]
+        flags: ObmmImportFlags,
+        /// Base distribution hint
+        #[arg(long, default_value_t = 0)]
+        base_dist: i32,
+    },
+    /// Check the descriptors in the memlink directory for overlapping regions
+    Doctor,
+    /// List the descriptors in the memlink directory
+    List {
+        /// Show the age of each export, read from its meta sidecar
+        #[arg(long)]
+        show_age: bool,
+    },
+    /// Check whether a descriptor can be imported with the given flags
+    CanImport {
+        /// Descriptor JSON file to check
+        #[arg(long)]
+        desc: PathBuf,
+        /// Import flags, e.g. "ALLOWMMAP | REMOTENUMA"
+        #[arg(long, default_value = "")]
+        flags: ObmmImportFlags,
+    },
+    /// Export, round-trip and import a small region locally, reporting each step
+    Selftest,
+    /// Remove expired descriptors and those left by dead local processes
+    Gc {
+        /// Only remove descriptors whose exporting process is gone
+        #[arg(long)]
+        orphans_only: bool,
+    },
+    /// Compare the descriptors of two memlink directories
+    Compare {
+        /// First memlink directory
+        #[arg(long)]
+        a: PathBuf,
+        /// Second memlink directory
+        #[arg(long)]
+        b: PathBuf,
+    },
+}
+
+/// Load the configuration from `path`, or the default file if present, then apply environment overrides
+fn load_config(path: Option<&Path>) -> anyhow::Result<MemlinkConfig> {
+    let default_path = Path::new(DEFAULT_CONFIG_FILE);
+    match path {
+        Some(path) => MemlinkConfig::load(path),
+        None if default_path.exists() => MemlinkConfig::load(default_path),
+        None => {
+            let mut config = MemlinkConfig::default();
+            config.apply_env_overrides()?;
+            Ok(config)
+        }
+    }
+}
+
+/// Where `memlink export` writes the descriptor besides the log
+#[derive(Debug, Clone, Copy)]
+enum DescOutput<'key> {
+    /// Only log it
+    Log,
+    /// Write it to stdout as JSON
+    Json,
+    /// Write it to stdout as an envelope signed with the key
+    Envelope(&'key [u8]),
+}
+
+impl DescOutput<'_> {
+    /// Write `desc` to stdout unless only logging
+    fn write(self, desc: &ObmmMemDesc<UbPrivData>) -> anyhow::Result<()> {
+        let text = match self {
+            DescOutput::Log => return Ok(()),
+            DescOutput::Json => desc.to_json()?,
+            DescOutput::Envelope(key) => desc.to_envelope(key)?,
+        };
+        writeln!(std::io::stdout().lock(), "{text}")?;
+        Ok(())
+    }
+}
+
+/// Read the envelope key in `path`, trailing whitespace excluded
+fn read_key(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut key = std::fs::read(path).with_context(|| format!("Failed to read key file {}", path.display()))?;
+    key.truncate(key.trim_ascii_end().len());
+    Ok(key)
+}
+
+/// Export the configured size on the configured NUMA node
+fn export_default(config: &MemlinkConfig, output: DescOutput<'_>) -> anyhow::Result<()> {
+    let export_id = config.numa_node;
     let mut lens = vec![0; MAX_NUMA_NODES];
-    lens.get_mut(export_id).map(|v| *v = 1024 * 1024 * 128).with_context(|| format!("Failed to set length for NUMA node {export_id}"))?;
-    let (mem_id, desc) = mem_export::<UbPrivData>(&lens, ObmmExportFlags::ALLOWMMAP).with_context(|| "Failed to export memory")?;
+    lens.get_mut(export_id).map(|v| *v = config.size).with_context(|| format!("Failed to set length for NUMA node {export_id}"))?;
+    let (mem_id, desc) = mem_export::<UbPrivData>(&lens, config.flags).with_context(|| "Failed to export memory")?;
     info!("Exported memory with MemID: {mem_id}");
     info!("Memory Descriptor: {desc:?}");
+    output.write(&desc)
+}
+
+/// Export memory according to the profile `name` in `profiles_file`
+fn export_profile(name: &str, profiles_file: &Path, output: DescOutput<'_>) -> anyhow::Result<()> {
+    let profile = ExportProfile::load(profiles_file, name)?;
+    let (mem_id, desc) = profile.apply().with_context(|| format!("Failed to export memory with profile {name}"))?;
+    info!("Exported memory with MemID: {mem_id} using profile {name}");
+    info!("Memory Descriptor: {desc:?}");
+    output.write(&desc)
+}
+
+/// Parse a descriptor, detecting the envelope format by its prefix
+fn parse_descriptor(text: &str, key_file: Option<&Path>) -> anyhow::Result<ObmmMemDesc<UbPrivData>> {
+    let text = text.trim();
+    if text.starts_with(ENVELOPE_PREFIX) {
+        let key_file = key_file.context("An envelope descriptor needs --key-file")?;
+        ObmmMemDesc::from_envelope(text, &read_key(key_file)?)
+    } else {
+        ObmmMemDesc::from_json(text)
+    }
+}
+
+/// Import the descriptor in `source`, or stdin for `-`, and print its Memory ID
+fn import(source: &Path, key_file: Option<&Path>, flags: ObmmImportFlags, base_dist: i32) -> anyhow::Result<()> {
+    let mut text = String::new();
+    if source == Path::new("-") {
+        let _len = std::io::stdin().lock().read_to_string(&mut text).context("Failed to read descriptor from stdin")?;
+    } else {
+        text = std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    }
+    let desc = parse_descriptor(&text, key_file)?;
+    let (mem_id, numa) = mem_import(&desc, flags, base_dist).context("Failed to import memory")?;
+    info!("Imported memory with MemID: {mem_id} on NUMA node {numa}");
+    writeln!(std::io::stdout().lock(), "{mem_id}")?;
+    Ok(())
+}
+
+/// Read every descriptor in `dir`, skipping unreadable ones
+fn load_descriptors(dir: &MemlinkDir) -> anyhow::Result<Vec<(MemId, ObmmMemDesc<UbPrivData>)>> {
+    let mut descs = Vec::new();
+    for mem_id in dir.list()? {
+        match dir.load(mem_id) {
+            Ok(desc) => descs.push((mem_id, desc)),
+            Err(err) => warn!("Skipping MemID {mem_id}: {err:#}"),
+        }
+    }
+    Ok(descs)
+}
+
+/// Report descriptors in the memlink directory whose address ranges overlap
+fn doctor(config: &MemlinkConfig) -> anyhow::Result<()> {
+    let dir = MemlinkDir::open(&config.memlink_dir)?;
+    let descs = load_descriptors(&dir)?;
+    let regions: Vec<(MemId, u64, u64)> = descs.iter().map(|&(mem_id, ref desc)| (mem_id, desc.addr, desc.length)).collect();
+    let overlaps = find_overlaps(&regions);
+    for &(a, b) in &overlaps {
+        warn!("MemID {a} and MemID {b} export overlapping address ranges");
+    }
+    info!("Checked {} descriptors in {}", descs.len(), dir.path().display());
+    if overlaps.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} overlapping descriptor pairs found", overlaps.len()))
+    }
+}
+
+/// Remove orphaned and, unless `orphans_only`, expired descriptors from the memlink directory
+fn gc(config: &MemlinkConfig, orphans_only: bool) -> anyhow::Result<()> {
+    let dir = MemlinkDir::open(&config.memlink_dir)?;
+    let mut stale = dir.find_orphans()?;
+    for &mem_id in &stale {
+        warn!("MemID {mem_id}: exporting process is gone");
+    }
+    if !orphans_only {
+        for mem_id in dir.list()? {
+            let expired = dir
+                .load::<UbPrivData>(mem_id)
+                .err()
+                .is_some_and(|err| matches!(err.downcast_ref::<ObmmError>(), Some(&ObmmError::Expired(_))));
+            if expired && !stale.contains(&mem_id) {
+                warn!("MemID {mem_id}: descriptor has expired");
+                stale.push(mem_id);
+            }
+        }
+    }
+    for &mem_id in &stale {
+        dir.remove(mem_id)?;
+    }
+    info!("Removed {} stale descriptors from {}", stale.len(), dir.path().display());
+    Ok(())
+}
+
+/// Report descriptors only in `a`, only in `b` and differing between them
+fn compare(a: &Path, b: &Path) -> anyhow::Result<()> {
+    let (dir_a, dir_b) = (MemlinkDir::open(a)?, MemlinkDir::open(b)?);
+    let comparison = dir_a.compare(&dir_b)?;
+    for mem_id in &comparison.only_in_self {
+        warn!("MemID {mem_id}: only in {}", a.display());
+    }
+    for mem_id in &comparison.only_in_other {
+        warn!("MemID {mem_id}: only in {}", b.display());
+    }
+    for &(mem_id, ref fields) in &comparison.differing {
+        warn!("MemID {mem_id}: differs in {}", fields.join(", "));
+    }
+    if comparison.is_empty() {
+        info!("{} and {} hold the same descriptors", a.display(), b.display());
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} and {} diverge", a.display(), b.display()))
+    }
+}
+
+/// List the descriptors in the memlink directory
+fn list(config: &MemlinkConfig, show_age: bool) -> anyhow::Result<()> {
+    let dir = MemlinkDir::open(&config.memlink_dir)?;
+    for (mem_id, desc) in load_descriptors(&dir)? {
+        if show_age {
+            let age = MemlinkMeta::from_file(dir.path(), mem_id)
+                .and_then(|meta| meta.age())
+                .map_or_else(|_err| "unknown".to_owned(), |age| format!("{}s", age.as_secs()));
+            info!("MemID {mem_id}: addr {:#x}, length {}, age {age}", desc.addr, desc.length);
+        } else {
+            info!("MemID {mem_id}: addr {:#x}, length {}", desc.addr, desc.length);
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run the import checks on the descriptor in `path`, reporting each result
+fn can_import(path: &Path, flags: ObmmImportFlags) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let desc = ObmmMemDesc::<UbPrivData>::from_json(&json)?;
+    let checks: [(&str, Result<(), ObmmError>); 3] = [
+        ("sanity", desc.sanity_check()),
+        ("address", desc.validate_address()),
+        ("flags", flags_compatible(desc.export_flags, flags)),
+    ];
+    let mut failed = 0_usize;
+    for (name, result) in checks {
+        match result {
+            Ok(()) => info!("{name}: ok"),
+            Err(err) => {
+                warn!("{name}: {err}");
+                failed = failed.saturating_add(1);
+            }
+        }
+    }
+    if failed == 0 {
+        info!("{} can be imported with flags {flags:?}", path.display());
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{failed} import checks failed for {}", path.display()))
+    }
+}
+
+/// Run the library self-test, reporting each step with its timing
+fn selftest() -> anyhow::Result<()> {
+    let report = run_selftest()?;
+    for step in &report.steps {
+        match step.result {
+            Ok(()) => info!("{}: ok ({:?})", step.name, step.elapsed),
+            Err(ref err) => warn!("{}: {err} ({:?})", step.name, step.elapsed),
+        }
+    }
+    if report.passed() {
+        info!("Self-test passed");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Self-test failed"))
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    let cli = Cli::parse();
+    info!("Memory linking and analysis utilities");
+    let config = load_config(cli.config.as_deref())?;
+    match cli.command {
+        None => export_default(&config, DescOutput::Log),
+        Some(Command::Export { profile, profiles_file, stdout, key_file }) => {
+            let key = key_file.as_deref().map(read_key).transpose()?;
+            let output = match (stdout, key.as_deref()) {
+                (false, _) => DescOutput::Log,
+                (true, None) => DescOutput::Json,
+                (true, Some(key)) => DescOutput::Envelope(key),
+            };
+            match profile {
+                Some(name) => export_profile(&name, &profiles_file, output),
+                None => export_default(&config, output),
+            }
+        }
+        Some(Command::Import { source, key_file, flags, base_dist }) => import(&source, key_file.as_deref(), flags, base_dist),
+        Some(Command::Doctor) => doctor(&config),
+        Some(Command::List { show_age }) => list(&config, show_age),
+        Some(Command::CanImport { desc, flags }) => can_import(&desc, flags),
+        Some(Command::Selftest) => selftest(),
+        Some(Command::Gc { orphans_only }) => gc(&config, orphans_only),
+        Some(Command::Compare { a, b }) => compare(&a, &b),
+    }
+}
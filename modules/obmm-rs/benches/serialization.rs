@@ -0,0 +1,38 @@
+//! Compare allocating and buffer-reusing descriptor serialization
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use obmm_rs::{ObmmMemDesc, UbPrivData};
+use std::hint::black_box;
+
+fn descriptor() -> ObmmMemDesc<UbPrivData> {
+    ObmmMemDesc::<UbPrivData>::builder()
+        .addr(0xffff_fc00_0000)
+        .length(1024 * 1024 * 128)
+        .seid([1; 16])
+        .deid([2; 16])
+        .tokenid(42)
+        .priv_data(UbPrivData::OCHIP | UbPrivData::CACHEABLE)
+        .build()
+        .expect("valid descriptor")
+}
+
+fn bench_json(c: &mut Criterion) {
+    let desc = descriptor();
+    let mut group = c.benchmark_group("to_json");
+    group.bench_function("allocating", |b| b.iter(|| black_box(&desc).to_json()));
+    let mut buf = String::new();
+    group.bench_function("reusing", |b| b.iter(|| black_box(&desc).to_json_into(&mut buf)));
+    group.finish();
+}
+
+fn bench_bytes(c: &mut Criterion) {
+    let desc = descriptor();
+    let mut group = c.benchmark_group("to_bytes");
+    group.bench_function("allocating", |b| b.iter(|| black_box(&desc).to_bytes()));
+    let mut buf = Vec::new();
+    group.bench_function("reusing", |b| b.iter(|| black_box(&desc).to_bytes_into(&mut buf)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_json, bench_bytes);
+criterion_main!(benches);
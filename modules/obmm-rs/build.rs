@@ -24,5 +24,8 @@ fn setup_linking() {
 
 fn main() {
     println!("Hello from obmm-rs build script!");
-    setup_linking();
+    // with dlopen the library is loaded at runtime, if present
+    if std::env::var_os("CARGO_FEATURE_DLOPEN").is_none() {
+        setup_linking();
+    }
 }
\ No newline at end of file
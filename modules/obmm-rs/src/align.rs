@@ -0,0 +1,74 @@
+//! Rounding per-node export lengths up to the page size
+
+use anyhow::Context;
+
+use crate::{NumaLengths, page_size};
+
+/// Rounding export lengths up to whole pages before exporting
+///
+/// Alignment is opt-in: `mem_export` passes lengths to the driver as given.
+pub trait AlignUpToPage: Sized {
+    /// Round each non-zero node length up to the OS page size
+    /// # Returns
+    /// # Errors
+    /// Aligned lengths and the total padding added in bytes on success,
+    /// `anyhow::Error` if a rounded length overflows
+    fn align_up_to_page(&self) -> anyhow::Result<(Self, usize)>;
+
+    /// Round each non-zero node length up to a multiple of `align`
+    /// # Arguments
+    /// * `align` - Granularity in bytes, non-zero
+    /// # Returns
+    /// # Errors
+    /// Aligned lengths and the total padding added in bytes on success,
+    /// `anyhow::Error` if `align` is zero or a rounded length overflows
+    fn align_up_to(&self, align: usize) -> anyhow::Result<(Self, usize)>;
+}
+
+impl AlignUpToPage for NumaLengths {
+    #[inline]
+    fn align_up_to_page(&self) -> anyhow::Result<(Self, usize)> {
+        self.align_up_to(usize::try_from(page_size())?)
+    }
+
+    #[inline]
+    fn align_up_to(&self, align: usize) -> anyhow::Result<(Self, usize)> {
+        if align == 0 {
+            anyhow::bail!("Alignment must be greater than 0");
+        }
+        let mut aligned = *self;
+        let mut padding = 0_usize;
+        for (node, length) in aligned.iter_mut().enumerate() {
+            let rounded = length
+                .div_ceil(align)
+                .checked_mul(align)
+                .with_context(|| format!("Length {length} of NUMA node {node} overflows when aligned to {align}"))?;
+            padding = padding.checked_add(rounded.saturating_sub(*length)).context("Alignment padding overflows")?;
+            *length = rounded;
+        }
+        Ok((aligned, padding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, numa_lengths};
+
+    #[test]
+    fn test_align_up_to_page() -> anyhow::Result<()> {
+        let page = usize::try_from(page_size())?;
+        let lengths = numa_lengths! { 0 => page.saturating_mul(3), 2 => 1000 };
+        let (aligned, padding) = lengths.align_up_to_page()?;
+        assert_eq!(aligned, numa_lengths! { 0 => page.saturating_mul(3), 2 => page });
+        assert_eq!(padding, page.saturating_sub(1000));
+        assert_eq!([0; MAX_NUMA_NODES].align_up_to_page()?, ([0; MAX_NUMA_NODES], 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_align_up_to_errors() {
+        assert!(numa_lengths! { 1 => usize::MAX }.align_up_to(4096).is_err());
+        assert!(numa_lengths! { 1 => 1000 }.align_up_to(0).is_err());
+    }
+}
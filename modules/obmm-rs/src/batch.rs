@@ -0,0 +1,166 @@
+//! Unexporting and importing many regions at once
+
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{MemId, ObmmError, ObmmImportFlags, ObmmMemDesc, ObmmUnexportFlags, UbPrivData, mem_import, mem_unexport};
+
+/// Outcome of unexporting one region in a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TimedUnexport {
+    /// Memory ID that was unexported
+    pub memid: MemId,
+    /// Result of `mem_unexport`
+    pub result: Result<(), ObmmError>,
+    /// Time spent in the unexport call
+    pub elapsed: Duration,
+}
+
+/// Per-region outcomes of a batch unexport and their aggregate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnexportSummary {
+    /// Outcome of each region, in the order given
+    pub results: Vec<TimedUnexport>,
+    /// Time spent unexporting the whole batch
+    pub total: Duration,
+    /// Number of regions unexported
+    pub succeeded: usize,
+    /// Number of regions that failed to unexport
+    pub failed: usize,
+}
+
+/// Unexport several regions, continuing past failures
+/// # Arguments
+/// * `memids` - Memory IDs to unexport
+/// * `flags` - Unexport flags applied to every region
+/// # Returns
+/// Result of each unexport paired with its Memory ID, in the order given
+#[inline]
+#[must_use]
+pub fn mem_unexport_many(memids: &[MemId], flags: ObmmUnexportFlags) -> Vec<(MemId, Result<(), ObmmError>)> {
+    memids.iter().map(|&memid| (memid, mem_unexport(memid, flags))).collect()
+}
+
+/// Unexport several regions, continuing past failures, timing each call
+/// # Arguments
+/// * `memids` - Memory IDs to unexport
+/// * `flags` - Unexport flags applied to every region
+/// # Returns
+/// Timed outcome of each unexport with totals
+#[inline]
+#[must_use]
+pub fn mem_unexport_many_timed(memids: &[MemId], flags: ObmmUnexportFlags) -> UnexportSummary {
+    let start = Instant::now();
+    let results: Vec<TimedUnexport> = memids
+        .iter()
+        .map(|&memid| {
+            let call = Instant::now();
+            let result = mem_unexport(memid, flags);
+            TimedUnexport { memid, result, elapsed: call.elapsed() }
+        })
+        .collect();
+    let succeeded = results.iter().filter(|r| r.result.is_ok()).count();
+    UnexportSummary {
+        failed: results.len().saturating_sub(succeeded),
+        succeeded,
+        results,
+        total: start.elapsed(),
+    }
+}
+
+/// Import several descriptors concurrently, at most `max_concurrency` at a time
+///
+/// The calling thread imports alongside up to `max_concurrency - 1` helper
+/// threads, each taking the next pending descriptor, so the driver never sees
+/// more than `max_concurrency` imports in flight.
+/// # Arguments
+/// * `descs` - Memory Descriptors from remote
+/// * `flags` - Import flags applied to every descriptor
+/// * `base_dist` - Base distribution hint applied to every descriptor
+/// * `max_concurrency` - Maximum number of imports in flight
+/// # Returns
+/// # Errors
+/// Result of each import in the order of `descs`, `anyhow::Error` if `max_concurrency` is 0
+#[inline]
+pub fn mem_import_many(
+    descs: &[ObmmMemDesc<UbPrivData>],
+    flags: ObmmImportFlags,
+    base_dist: i32,
+    max_concurrency: usize,
+) -> anyhow::Result<Vec<Result<(MemId, i32), ObmmError>>> {
+    if max_concurrency == 0 {
+        anyhow::bail!("max_concurrency must be greater than 0");
+    }
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(desc) = descs.get(index) else {
+                return done;
+            };
+            done.push((index, mem_import(desc, flags, base_dist)));
+        }
+    };
+    let helpers = max_concurrency.saturating_sub(1).min(descs.len());
+    let mut done = thread::scope(|scope| {
+        let handles: Vec<_> = (0..helpers)
+            .filter_map(|_| thread::Builder::new().name("obmm-import".to_owned()).spawn_scoped(scope, work).ok())
+            .collect();
+        let mut done = work();
+        for handle in handles {
+            match handle.join() {
+                Ok(mut helper_done) => done.append(&mut helper_done),
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+        done
+    });
+    done.sort_unstable_by_key(|&(index, _)| index);
+    Ok(done.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, ObmmExportFlags, mem_export};
+
+    #[test]
+    fn test_unexport_many_timed() -> anyhow::Result<()> {
+        let mut memids = Vec::new();
+        for _ in 0..3 {
+            let (memid, _) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty())?;
+            memids.push(memid);
+        }
+        let summary = mem_unexport_many_timed(&memids, ObmmUnexportFlags::empty());
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.failed, 0);
+        assert_eq!(summary.results.iter().map(|r| r.memid).collect::<Vec<_>>(), memids);
+        let sum: Duration = summary.results.iter().map(|r| r.elapsed).sum();
+        assert!(sum <= summary.total);
+
+        let plain = mem_unexport_many(&memids, ObmmUnexportFlags::empty());
+        assert!(plain.iter().all(|entry| entry.1.is_ok()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_many() -> anyhow::Result<()> {
+        let descs: Vec<ObmmMemDesc<UbPrivData>> = (1..=5_u64)
+            .map(|pages| ObmmMemDesc::builder().addr(0xffff_fc00_0000).length(pages * 4096).build())
+            .collect::<anyhow::Result<_>>()?;
+        let results = mem_import_many(&descs, ObmmImportFlags::ALLOWMMAP, 0, 2)?;
+        assert_eq!(results.len(), descs.len());
+        for (desc, result) in descs.iter().zip(&results) {
+            let (memid, _) = result.clone()?;
+            let tracked = crate::lookup_descriptor(memid).ok_or_else(|| anyhow::anyhow!("MemID {memid} not tracked"))?;
+            assert_eq!(tracked.length, desc.length);
+        }
+        assert!(mem_import_many(&descs, ObmmImportFlags::ALLOWMMAP, 0, 0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! JSON schema of the descriptors emitted by the C OBMM tools
+//!
+//! The C tools print a descriptor as a flat JSON object:
+//!
+//! ```json
+//! {
+//!   "addr": "0xfffffc000000",
+//!   "length": 134217728,
+//!   "seid": "0x0000000000000000000000000000abcd",
+//!   "deid": "0x1",
+//!   "tokenid": 7,
+//!   "scna": 1,
+//!   "dcna": 2,
+//!   "priv_len": 2,
+//!   "priv_data": 96,
+//!   "flags": 2
+//! }
+//! ```
+//!
+//! * `addr` and `length` are integers or `0x`-prefixed hex strings.
+//! * `seid` and `deid` are the 128bit eid as a hex number, `0x` optional,
+//!   stored little-endian like `ObmmMemDesc::set_seid`.
+//! * `priv_data` is an integer holding the `priv_len` bytes of privilege data
+//!   little-endian, decoded with the binary codec, or the native form.
+//! * `flags`, or `export_flags`, is the integer bitmask of export flags.
+//!
+//! Unknown fields are ignored. The Rust-only fields are left at their
+//! defaults and the result is upgraded to `OBMM_DESC_SCHEMA_VERSION`.
+
+use anyhow::Context;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::{OBMM_DESC_SCHEMA_VERSION, ObmmExportFlags, ObmmMemDesc};
+
+/// Descriptor as printed by the C tools
+#[derive(Debug, Deserialize)]
+pub(crate) struct CJsonDesc {
+    /// Base address of the memory region
+    #[serde(deserialize_with = "int_or_hex")]
+    addr: u64,
+    /// Length of the memory region
+    #[serde(deserialize_with = "int_or_hex")]
+    length: u64,
+    /// 128bit eid as a hex number
+    #[serde(deserialize_with = "hex_eid")]
+    seid: u128,
+    /// 128bit deid as a hex number
+    #[serde(deserialize_with = "hex_eid")]
+    deid: u128,
+    /// Token ID
+    #[serde(default)]
+    tokenid: u32,
+    /// Source CNA
+    #[serde(default)]
+    scna: u32,
+    /// Destination CNA
+    #[serde(default)]
+    dcna: u32,
+    /// Length of privilege data
+    priv_len: u16,
+    /// Privilege data, an integer or the native form
+    priv_data: serde_json::Value,
+    /// Raw export flag bits
+    #[serde(default, alias = "export_flags")]
+    flags: u64,
+}
+
+impl CJsonDesc {
+    /// Normalize into the Rust representation
+    pub(crate) fn into_desc<T>(self) -> anyhow::Result<ObmmMemDesc<T>>
+    where
+        T: Default + for<'de> Deserialize<'de>,
+    {
+        let priv_data = if let Some(number) = self.priv_data.as_number() {
+            let raw = number.as_u64().context("priv_data must be a non-negative integer")?;
+            let bytes = raw.to_le_bytes();
+            let priv_bytes = bytes
+                .get(..usize::from(self.priv_len))
+                .with_context(|| format!("priv_len {} too large for integer priv_data", self.priv_len))?;
+            bincode::deserialize(priv_bytes).context("Invalid priv_data")?
+        } else {
+            serde_json::from_value(self.priv_data)?
+        };
+        let export_flags = ObmmExportFlags::from_bits_validated(self.flags)?;
+        let mut desc = ObmmMemDesc {
+            addr: self.addr,
+            length: self.length,
+            tokenid: self.tokenid,
+            scna: self.scna,
+            dcna: self.dcna,
+            priv_len: self.priv_len,
+            priv_data,
+            schema_version: OBMM_DESC_SCHEMA_VERSION,
+            export_flags,
+            ..ObmmMemDesc::default()
+        };
+        desc.set_seid(self.seid);
+        desc.set_deid(self.deid);
+        Ok(desc)
+    }
+}
+
+/// Integer or hex string, as the C tools print `addr` and `length`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntOrHex {
+    /// Plain integer
+    Int(u64),
+    /// `0x`-prefixed hex string
+    Hex(String),
+}
+
+/// Deserialize a `u64` from an integer or a `0x`-prefixed hex string
+fn int_or_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match IntOrHex::deserialize(deserializer)? {
+        IntOrHex::Int(value) => Ok(value),
+        IntOrHex::Hex(hex) => {
+            let digits = hex
+                .strip_prefix("0x")
+                .ok_or_else(|| D::Error::custom(format!("expected a 0x-prefixed hex string, got {hex:?}")))?;
+            u64::from_str_radix(digits, 16).map_err(D::Error::custom)
+        }
+    }
+}
+
+/// Deserialize a 128bit eid from a hex number, `0x` optional
+fn hex_eid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    u128::from_str_radix(hex.strip_prefix("0x").unwrap_or(&hex), 16).map_err(D::Error::custom)
+}
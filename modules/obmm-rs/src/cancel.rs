@@ -0,0 +1,125 @@
+//! Best-effort cancellation of in-flight exports
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+
+use crate::{MemId, ObmmError, ObmmExportFlags, ObmmMemDesc, ObmmUnexportFlags, mem_export, mem_unexport};
+
+/// Shared flag used to cancel an export from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a new, uncancelled `CancelToken`
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Request cancellation of exports using this token
+    ///
+    /// Only the token is set, the driver is not signalled: an export already
+    /// in flight runs to completion and is then unexported again. See
+    /// `cancel_in_flight_exports` to abort it in the driver.
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation has been requested
+    #[inline]
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Ask the driver to abort every export in flight
+///
+/// The request is process-wide: `obmm_cancel_export` aborts all exports of
+/// this process still running in the driver, whichever thread or token they
+/// belong to, including exports without a `CancelToken`. Aborted exports
+/// fail with the error of the driver.
+/// # Returns
+/// # Errors
+/// Ok(()), the hooked implementation never fails
+#[cfg(feature = "hook")]
+#[inline]
+pub fn cancel_in_flight_exports() -> Result<(), ObmmError> {
+    // hooked implementation, exports complete synchronously
+    Ok(())
+}
+
+/// Ask the driver to abort every export in flight
+///
+/// The request is process-wide: `obmm_cancel_export` aborts all exports of
+/// this process still running in the driver, whichever thread or token they
+/// belong to, including exports without a `CancelToken`. Aborted exports
+/// fail with the error of the driver.
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::LibraryUnavailable` if the OBMM library
+/// failed to load, `ObmmError::Ffi` if the driver rejected the request
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn cancel_in_flight_exports() -> Result<(), ObmmError> {
+    crate::ffi_ready()?;
+    // SAFETY: `obmm_cancel_export` takes no arguments and only signals the driver
+    let ret = unsafe { crate::obmm_cancel_export() };
+    if ret != 0 {
+        return Err(ObmmError::Ffi(ret));
+    }
+    Ok(())
+}
+
+/// Export memory region unless cancelled
+///
+/// Cancellation is best effort: the token is checked before the export, and
+/// if it was set while the export was in flight the region is unexported
+/// again. A cancel that arrives after this function returns has no effect.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `token` - Token checked for cancellation
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `ObmmError::Cancelled`
+/// wrapped in `anyhow::Error` when cancelled, `anyhow::Error` on other failures
+#[inline]
+pub fn mem_export_cancellable<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    token: &CancelToken,
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    if token.is_cancelled() {
+        return Err(ObmmError::Cancelled.into());
+    }
+    let (memid, desc) = mem_export::<T>(length, flags)?;
+    if token.is_cancelled() {
+        // the region is being discarded, a failed unexport can only be reported as cancellation
+        let _result = mem_unexport(memid, ObmmUnexportFlags::FORCE);
+        return Err(ObmmError::Cancelled.into());
+    }
+    Ok((memid, desc))
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, UbPrivData};
+
+    #[test]
+    fn test_cancelled_before_export() {
+        let token = CancelToken::new();
+        let lengths = vec![1024 * 1024; MAX_NUMA_NODES];
+        assert!(mem_export_cancellable::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, &token).is_ok());
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        let result = mem_export_cancellable::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, &token);
+        assert!(result.is_err_and(|e| e.downcast_ref::<ObmmError>() == Some(&ObmmError::Cancelled)));
+        assert_eq!(cancel_in_flight_exports(), Ok(()));
+    }
+}
@@ -0,0 +1,123 @@
+//! Free exportable capacity per NUMA node and spreading exports over it
+
+#[cfg(not(feature = "hook"))]
+use anyhow::Context;
+
+use crate::{MAX_NUMA_NODES, NumaLengths};
+#[cfg(not(feature = "hook"))]
+use crate::{ObmmError, obmm_capacity};
+
+/// Query how many bytes are still exportable on each NUMA node
+/// # Returns
+/// # Errors
+/// Free exportable bytes of each node, `anyhow::Error` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn exportable_capacity() -> anyhow::Result<[u64; MAX_NUMA_NODES]> {
+    // hooked implementation
+    Ok(crate::hook::capacity())
+}
+
+/// Query how many bytes are still exportable on each NUMA node
+/// # Returns
+/// # Errors
+/// Free exportable bytes of each node, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn exportable_capacity() -> anyhow::Result<[u64; MAX_NUMA_NODES]> {
+    crate::ffi_ready()?;
+    let mut capacity = [0; MAX_NUMA_NODES];
+    let ret = unsafe { obmm_capacity(capacity.as_mut_ptr(), MAX_NUMA_NODES) };
+    if ret != 0 {
+        return Err(ObmmError::Ffi(ret)).context("Failed to query exportable capacity");
+    }
+    Ok(capacity)
+}
+
+/// Spreading an export over NUMA nodes by their free capacity
+pub trait SpreadByCapacity: Sized {
+    /// Split `total` bytes over the nodes in proportion to their free exportable capacity
+    /// # Arguments
+    /// * `total` - Bytes to export in total
+    /// # Returns
+    /// # Errors
+    /// Per-node lengths summing to `total`, `anyhow::Error` if the capacity
+    /// can't be queried or is smaller than `total`
+    fn spread_by_capacity(total: usize) -> anyhow::Result<Self>;
+
+    /// Split `total` bytes over the nodes in proportion to `capacity`
+    ///
+    /// Each node gets the floor of its share; the bytes left over go one each
+    /// to the nodes with the largest remainders, so no node exceeds its
+    /// capacity.
+    /// # Arguments
+    /// * `total` - Bytes to export in total
+    /// * `capacity` - Free exportable bytes of each node
+    /// # Returns
+    /// # Errors
+    /// Per-node lengths summing to `total`, `anyhow::Error` if `capacity` is smaller than `total`
+    fn spread_over(total: usize, capacity: &[u64; MAX_NUMA_NODES]) -> anyhow::Result<Self>;
+}
+
+impl SpreadByCapacity for NumaLengths {
+    #[inline]
+    fn spread_by_capacity(total: usize) -> anyhow::Result<Self> {
+        Self::spread_over(total, &exportable_capacity()?)
+    }
+
+    #[inline]
+    fn spread_over(total: usize, capacity: &[u64; MAX_NUMA_NODES]) -> anyhow::Result<Self> {
+        let free: u128 = capacity.iter().copied().map(u128::from).sum();
+        let wanted = u128::try_from(total)?;
+        if wanted > free {
+            anyhow::bail!("Cannot spread {total} bytes over {free} free exportable bytes");
+        }
+        let mut lengths = [0; MAX_NUMA_NODES];
+        if wanted == 0 {
+            return Ok(lengths);
+        }
+        // total * capacity < 2^128 since both fit in 64 bits
+        let mut remainders = [0_u128; MAX_NUMA_NODES];
+        let mut left = wanted;
+        for ((length, remainder), &node_free) in lengths.iter_mut().zip(&mut remainders).zip(capacity) {
+            let scaled = wanted.saturating_mul(u128::from(node_free));
+            let share = scaled.checked_div(free).unwrap_or_default();
+            *remainder = scaled.checked_rem(free).unwrap_or_default();
+            *length = usize::try_from(share)?;
+            left = left.saturating_sub(share);
+        }
+        let mut nodes: Vec<usize> = (0..MAX_NUMA_NODES).collect();
+        nodes.sort_by_key(|&node| core::cmp::Reverse(remainders.get(node).copied()));
+        for node in nodes.into_iter().take(usize::try_from(left)?) {
+            if let Some(length) = lengths.get_mut(node) {
+                *length = length.saturating_add(1);
+            }
+        }
+        Ok(lengths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GB;
+
+    #[test]
+    fn test_spread_over() -> anyhow::Result<()> {
+        let mut capacity = [0; MAX_NUMA_NODES];
+        capacity[0] = 3 << 30;
+        capacity[2] = 1 << 30;
+        let lengths = NumaLengths::spread_over(2 * GB, &capacity)?;
+        assert_eq!((lengths[0], lengths[2]), (3 * GB / 2, GB / 2));
+        assert_eq!(lengths.iter().sum::<usize>(), 2 * GB);
+
+        capacity[1] = 1 << 30;
+        let uneven = NumaLengths::spread_over(10, &capacity)?;
+        assert_eq!(uneven.iter().sum::<usize>(), 10);
+        assert!(uneven.iter().zip(&capacity).all(|(&length, &free)| u64::try_from(length).is_ok_and(|length| length <= free)));
+
+        assert!(NumaLengths::spread_over(5 * GB + 1, &capacity).is_err());
+        assert_eq!(NumaLengths::spread_over(0, &[0; MAX_NUMA_NODES])?, [0; MAX_NUMA_NODES]);
+        Ok(())
+    }
+}
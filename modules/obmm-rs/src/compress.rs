@@ -0,0 +1,113 @@
+//! LZ4-compressed frames of the binary descriptor codec
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MAX_DESC_BYTES, ObmmError, ObmmMemDesc, decode_bytes};
+
+/// First byte of a frame holding the `to_bytes` form as is
+const FRAME_PLAIN: u8 = 0x00;
+
+/// First byte of a frame holding the LZ4-compressed `to_bytes` form
+const FRAME_LZ4: u8 = 0xc4;
+
+impl<T> ObmmMemDesc<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    /// Serialize the `ObmmMemDesc` to a compressed binary frame
+    ///
+    /// The frame is `FRAME_LZ4`, the uncompressed length as a little-endian
+    /// `u32`, then the LZ4 block of the `to_bytes` form. If compressing doesn't
+    /// shrink it the frame is `FRAME_PLAIN` followed by the `to_bytes` form.
+    /// Worth it for large `priv_data`; `to_bytes` stays the default.
+    /// # Returns
+    /// # Errors
+    /// Compressed frame on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_bytes_compressed(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = self.to_bytes()?;
+        let len = u32::try_from(bytes.len())?;
+        let block = lz4_flex::compress(&bytes);
+        let mut frame = Vec::with_capacity(bytes.len().saturating_add(1));
+        if block.len().saturating_add(size_of::<u32>()) < bytes.len() {
+            frame.push(FRAME_LZ4);
+            frame.extend_from_slice(&len.to_le_bytes());
+            frame.extend_from_slice(&block);
+        } else {
+            frame.push(FRAME_PLAIN);
+            frame.extend_from_slice(&bytes);
+        }
+        Ok(frame)
+    }
+
+    /// Deserialize the `ObmmMemDesc` from a frame of `to_bytes_compressed`
+    ///
+    /// The format is read from the first byte of the frame. Bare `to_bytes`
+    /// output has no such byte and is rejected, use `from_bytes` for it. The
+    /// declared uncompressed length is bounded like `from_bytes` input before
+    /// decompressing.
+    /// # Arguments
+    /// * `bytes` - Frame from `to_bytes_compressed`
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on malformed input
+    #[inline]
+    pub fn from_bytes_compressed(bytes: &[u8]) -> anyhow::Result<Self> {
+        let (&tag, rest) = bytes.split_first().ok_or_else(|| ObmmError::Codec("empty compressed frame".to_owned()))?;
+        match tag {
+            FRAME_PLAIN => return Ok(decode_bytes(rest)?),
+            FRAME_LZ4 => {}
+            _ => return Err(ObmmError::Codec(format!("unknown frame type {tag:#04x}")).into()),
+        }
+        let (len, block) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| ObmmError::Codec("truncated compressed frame".to_owned()))?;
+        let len = u32::from_le_bytes(*len);
+        if u64::from(len) > MAX_DESC_BYTES {
+            return Err(ObmmError::Codec(format!("compressed frame declares {len} bytes")).into());
+        }
+        let decompressed = lz4_flex::decompress(block, usize::try_from(len)?).map_err(|e| ObmmError::Codec(e.to_string()))?;
+        if decompressed.len() != usize::try_from(len)? {
+            return Err(ObmmError::Codec("compressed frame length mismatch".to_owned()).into());
+        }
+        Ok(decode_bytes(&decompressed)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ObmmMemDesc, UbPrivData};
+
+    #[test]
+    fn test_compressed_round_trip() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<Vec<u8>>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(4096)
+            .priv_data(b"memlink".repeat(2048))
+            .build()?;
+        let plain = desc.to_bytes()?;
+        let compressed = desc.to_bytes_compressed()?;
+        assert!(compressed.len() < plain.len() / 4);
+        assert_eq!(ObmmMemDesc::<Vec<u8>>::from_bytes_compressed(&compressed)?, desc);
+        assert!(ObmmMemDesc::<Vec<u8>>::from_bytes_compressed(&plain).is_err());
+
+        let truncated = compressed.get(..compressed.len() / 2).unwrap_or_default();
+        assert!(ObmmMemDesc::<Vec<u8>>::from_bytes_compressed(truncated).is_err());
+        let mut inflated = compressed.clone();
+        if let Some(len) = inflated.get_mut(1..5) {
+            len.copy_from_slice(&u32::MAX.to_le_bytes());
+        }
+        assert!(ObmmMemDesc::<Vec<u8>>::from_bytes_compressed(&inflated).is_err());
+        assert!(ObmmMemDesc::<Vec<u8>>::from_bytes_compressed(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unaligned_addr_round_trip() -> anyhow::Result<()> {
+        // the low byte of `addr` equals the LZ4 frame tag
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0x7f00_0000_10c4).length(4096).build()?;
+        let frame = desc.to_bytes_compressed()?;
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_bytes_compressed(&frame)?, desc);
+        Ok(())
+    }
+}
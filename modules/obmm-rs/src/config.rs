@@ -0,0 +1,150 @@
+//! memlink configuration loaded from TOML with environment overrides
+
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::{Serialize, Deserialize};
+
+use crate::ObmmExportFlags;
+
+/// Environment variable overriding `MemlinkConfig::memlink_dir`
+pub const ENV_MEMLINK_DIR: &str = "OBMM_MEMLINK_DIR";
+/// Environment variable overriding `MemlinkConfig::numa_node`
+pub const ENV_MEMLINK_NUMA_NODE: &str = "OBMM_MEMLINK_NUMA_NODE";
+/// Environment variable overriding `MemlinkConfig::size`
+pub const ENV_MEMLINK_SIZE: &str = "OBMM_MEMLINK_SIZE";
+/// Environment variable overriding `MemlinkConfig::flags`, e.g. `"ALLOWMMAP | REMOTENUMA"`
+pub const ENV_MEMLINK_FLAGS: &str = "OBMM_MEMLINK_FLAGS";
+
+/// Defaults used by memlink when not given on the command line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct MemlinkConfig {
+    /// NUMA node to export from
+    pub numa_node: usize,
+    /// Size of the exported region in bytes
+    pub size: usize,
+    /// Export flags
+    pub flags: ObmmExportFlags,
+    /// Directory holding descriptor files
+    pub memlink_dir: PathBuf,
+}
+
+impl Default for MemlinkConfig {
+    #[inline]
+    fn default() -> Self {
+        MemlinkConfig {
+            numa_node: 1,
+            size: 1024 * 1024 * 128,
+            flags: ObmmExportFlags::ALLOWMMAP,
+            memlink_dir: PathBuf::from("/tmp/memlink"),
+        }
+    }
+}
+
+impl MemlinkConfig {
+    /// Deserialize a `MemlinkConfig` from toml format, missing keys take default values
+    /// # Arguments
+    /// * `toml_str` - TOML string representation
+    /// # Returns
+    /// # Errors
+    /// `MemlinkConfig` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_toml(toml_str: &str) -> anyhow::Result<Self> {
+        let config: MemlinkConfig = toml::from_str(toml_str)?;
+        Ok(config)
+    }
+
+    /// Load a `MemlinkConfig` from a TOML file, then apply environment overrides
+    /// # Arguments
+    /// * `path` - Path to the TOML file
+    /// # Returns
+    /// # Errors
+    /// `MemlinkConfig` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let toml_str = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut config = MemlinkConfig::from_toml(&toml_str)?;
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    /// Override fields from the `OBMM_MEMLINK_*` environment variables
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` if a variable cannot be parsed
+    #[inline]
+    pub fn apply_env_overrides(&mut self) -> anyhow::Result<()> {
+        self.apply_overrides(|key| std::env::var(key).ok())
+    }
+
+    /// Override fields from variables returned by `lookup`
+    /// # Arguments
+    /// * `lookup` - Returns the value of an environment variable, if set
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` if a variable cannot be parsed
+    fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        if let Some(dir) = lookup(ENV_MEMLINK_DIR) {
+            self.memlink_dir = PathBuf::from(dir);
+        }
+        if let Some(node) = lookup(ENV_MEMLINK_NUMA_NODE) {
+            self.numa_node = node.parse().with_context(|| format!("Invalid {ENV_MEMLINK_NUMA_NODE}: {node}"))?;
+        }
+        if let Some(size) = lookup(ENV_MEMLINK_SIZE) {
+            self.size = size.parse().with_context(|| format!("Invalid {ENV_MEMLINK_SIZE}: {size}"))?;
+        }
+        if let Some(flags) = lookup(ENV_MEMLINK_FLAGS) {
+            self.flags = bitflags::parser::from_str(&flags)
+                .map_err(|e| anyhow::anyhow!("Invalid {ENV_MEMLINK_FLAGS}: {flags}: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    const CONFIG_TOML: &str = r#"
+        numa_node = 2
+        size = 67108864
+        flags = "ALLOWMMAP | REMOTENUMA"
+        memlink_dir = "/var/lib/memlink"
+    "#;
+
+    #[test]
+    fn test_config_from_toml() -> anyhow::Result<()> {
+        let config = MemlinkConfig::from_toml(CONFIG_TOML)?;
+        assert_eq!(config.numa_node, 2);
+        assert_eq!(config.size, 1024 * 1024 * 64);
+        assert_eq!(config.flags, ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::REMOTENUMA);
+        assert_eq!(config.memlink_dir, PathBuf::from("/var/lib/memlink"));
+
+        let partial = MemlinkConfig::from_toml("numa_node = 3")?;
+        assert_eq!(partial.numa_node, 3);
+        assert_eq!(partial.size, MemlinkConfig::default().size);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_env_overrides() -> anyhow::Result<()> {
+        let env = HashMap::from([
+            (ENV_MEMLINK_DIR, "/run/memlink"),
+            (ENV_MEMLINK_NUMA_NODE, "0"),
+            (ENV_MEMLINK_FLAGS, "REMOTENUMA"),
+        ]);
+        let mut config = MemlinkConfig::from_toml(CONFIG_TOML)?;
+        config.apply_overrides(|key| env.get(key).map(|value| (*value).to_owned()))?;
+        assert_eq!(config.memlink_dir, PathBuf::from("/run/memlink"));
+        assert_eq!(config.numa_node, 0);
+        assert_eq!(config.flags, ObmmExportFlags::REMOTENUMA);
+        assert_eq!(config.size, 1024 * 1024 * 64);
+
+        let bad = HashMap::from([(ENV_MEMLINK_SIZE, "lots")]);
+        assert!(config.apply_overrides(|key| bad.get(key).map(|value| (*value).to_owned())).is_err());
+        Ok(())
+    }
+}
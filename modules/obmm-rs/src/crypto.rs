@@ -0,0 +1,179 @@
+//! HMAC authentication of descriptors exchanged with untrusted peers
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::ObmmMemDesc;
+
+/// HMAC-SHA256 as used for descriptor MACs
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix of the signed envelope format
+const ENVELOPE_PREFIX: &str = "obmm:v1:";
+
+/// Keyed HMAC-SHA256 instance
+fn keyed(key: &[u8]) -> HmacSha256 {
+    let Ok(mac) = HmacSha256::new_from_slice(key) else {
+        unreachable!("HMAC accepts keys of any length")
+    };
+    mac
+}
+
+impl<T> ObmmMemDesc<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    /// Compute an HMAC-SHA256 over the binary form of the canonical descriptor
+    ///
+    /// Descriptors are canonicalized first, see `canonicalize`, so semantically
+    /// equal descriptors share a MAC. A descriptor whose `priv_data` fails to
+    /// serialize is never accepted by `verify`, whatever MAC is returned for it here.
+    /// # Arguments
+    /// * `key` - Key shared with the peer
+    #[inline]
+    #[must_use]
+    pub fn sign(&self, key: &[u8]) -> [u8; 32] {
+        let mut mac = keyed(key);
+        mac.update(&self.canonical_bytes().unwrap_or_default());
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Check a MAC produced by `sign` in constant time
+    /// # Arguments
+    /// * `key` - Key shared with the peer
+    /// * `mac` - MAC received alongside the descriptor
+    #[inline]
+    #[must_use]
+    pub fn verify(&self, key: &[u8], mac: &[u8; 32]) -> bool {
+        let Ok(bytes) = self.canonical_bytes() else {
+            return false;
+        };
+        let mut expected = keyed(key);
+        expected.update(&bytes);
+        expected.verify_slice(mac).is_ok()
+    }
+
+    /// Serialize the descriptor to the signed envelope format `obmm:v1:<b64>:<mac>`
+    ///
+    /// Both the binary canonical descriptor and the MAC are standard base64.
+    /// # Arguments
+    /// * `key` - Key shared with the peer
+    /// # Returns
+    /// # Errors
+    /// Envelope on success, `anyhow::Error` if the descriptor fails to serialize
+    #[inline]
+    pub fn to_envelope(&self, key: &[u8]) -> anyhow::Result<String> {
+        let bytes = self.canonical_bytes()?;
+        let mut mac = keyed(key);
+        mac.update(&bytes);
+        Ok(format!(
+            "{ENVELOPE_PREFIX}{}:{}",
+            STANDARD.encode(&bytes),
+            STANDARD.encode(mac.finalize().into_bytes())
+        ))
+    }
+
+    /// Deserialize a descriptor from the signed envelope format, checking its MAC
+    ///
+    /// The result is the canonical form of the descriptor that was wrapped. The
+    /// payload is decoded like `from_bytes`, so an authentic envelope carrying
+    /// unknown export flag bits or a stale `priv_len` is still rejected.
+    /// # Arguments
+    /// * `envelope` - Envelope produced by `to_envelope`
+    /// * `key` - Key shared with the peer
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` if the envelope is malformed or the MAC doesn't match
+    #[inline]
+    pub fn from_envelope(envelope: &str, key: &[u8]) -> anyhow::Result<Self> {
+        let Some((payload, mac)) = envelope.strip_prefix(ENVELOPE_PREFIX).and_then(|rest| rest.split_once(':')) else {
+            anyhow::bail!("Malformed descriptor envelope");
+        };
+        let bytes = STANDARD.decode(payload)?;
+        let mut expected = keyed(key);
+        expected.update(&bytes);
+        if expected.verify_slice(&STANDARD.decode(mac)?).is_err() {
+            anyhow::bail!("Descriptor envelope MAC mismatch");
+        }
+        Ok(crate::decode_bytes(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD;
+    use hmac::Mac as _;
+
+    use super::{ENVELOPE_PREFIX, keyed};
+    use crate::{ObmmError, ObmmMemDesc, UbPrivData};
+
+    /// Descriptor signed in the tests
+    fn desc() -> ObmmMemDesc<UbPrivData> {
+        ObmmMemDesc {
+            addr: 0xffff_fc00_0000,
+            length: 128 * 1024 * 1024,
+            tokenid: 42,
+            priv_data: UbPrivData::CACHEABLE,
+            ..ObmmMemDesc::default()
+        }
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let desc = desc();
+        let mac = desc.sign(b"shared key");
+        assert!(desc.verify(b"shared key", &mac));
+
+        let mut tampered = desc;
+        tampered.length = 256 * 1024 * 1024;
+        assert!(!tampered.verify(b"shared key", &mac));
+
+        let mut drifted = desc;
+        drifted.priv_len = 0;
+        assert_eq!(drifted.sign(b"shared key"), mac);
+
+        assert!(!desc.verify(b"wrong key", &mac));
+    }
+
+    #[test]
+    fn test_envelope() -> anyhow::Result<()> {
+        let desc = desc();
+        let envelope = desc.to_envelope(b"shared key")?;
+        assert!(envelope.starts_with("obmm:v1:"));
+        let mut canonical = desc;
+        canonical.canonicalize();
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_envelope(&envelope, b"shared key")?, canonical);
+        assert!(ObmmMemDesc::<UbPrivData>::from_envelope(&envelope, b"wrong key").is_err());
+
+        let mut tampered = desc;
+        tampered.tokenid = 7;
+        let forged_envelope = tampered.to_envelope(b"other key")?;
+        let (forged, _) = forged_envelope.rsplit_once(':').unwrap_or_default();
+        let (_, mac) = envelope.rsplit_once(':').unwrap_or_default();
+        assert!(ObmmMemDesc::<UbPrivData>::from_envelope(&format!("{forged}:{mac}"), b"shared key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_envelope_rejects_stale_priv_len() -> anyhow::Result<()> {
+        let mut stale = desc();
+        stale.priv_len = 9;
+        let bytes = bincode::serialize(&stale)?;
+        let mut mac = keyed(b"shared key");
+        mac.update(&bytes);
+        let envelope = format!(
+            "{ENVELOPE_PREFIX}{}:{}",
+            STANDARD.encode(&bytes),
+            STANDARD.encode(mac.finalize().into_bytes())
+        );
+        let err = ObmmMemDesc::<UbPrivData>::from_envelope(&envelope, b"shared key")
+            .err()
+            .and_then(|err| err.downcast::<ObmmError>().ok());
+        assert!(matches!(err, Some(ObmmError::PrivLenMismatch { declared: 9, .. })), "{err:?}");
+        Ok(())
+    }
+}
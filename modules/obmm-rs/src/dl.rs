@@ -0,0 +1,96 @@
+//! Lazy loading of the OBMM library with `dlopen`
+//!
+//! Each `obmm_*` function declared by the `extern` block of the static-link
+//! build is replaced here by a shim resolving the symbol on first use. When
+//! the library or a symbol is missing, the shims return the failure value of
+//! the C function (`OBMM_INVALID_MEMID`, `-1` or an empty mask) and the
+//! wrappers report `ObmmError::LibraryUnavailable` through `ensure_loaded`.
+
+use core::ffi::c_void;
+use std::sync::LazyLock;
+
+use libloading::Library;
+
+use crate::{MemId, OBMM_INVALID_MEMID, ObmmError};
+
+/// Environment variable overriding the path of the OBMM library
+pub const ENV_OBMM_LIBRARY: &str = "OBMM_LIBRARY";
+
+/// Library name searched on the loader path by default
+const DEFAULT_LIBRARY: &str = "libobmm.so";
+
+/// The OBMM library, loaded on first use
+static LIBRARY: LazyLock<Result<Library, ObmmError>> = LazyLock::new(|| {
+    let path = std::env::var(ENV_OBMM_LIBRARY).unwrap_or_else(|_err| DEFAULT_LIBRARY.to_owned());
+    load(&path)
+});
+
+/// Load the OBMM library from `path`
+fn load(path: &str) -> Result<Library, ObmmError> {
+    // SAFETY: the OBMM library runs no initialization routines with preconditions
+    unsafe { Library::new(path) }.map_err(|err| ObmmError::LibraryUnavailable(format!("{path}: {err}")))
+}
+
+/// Check that the OBMM library is loaded
+/// # Errors
+/// `ObmmError::LibraryUnavailable` naming the library and why it failed to load
+#[cfg(not(feature = "hook"))]
+pub(crate) fn ensure_loaded() -> Result<(), ObmmError> {
+    LIBRARY.as_ref().map(|_lib| ()).map_err(Clone::clone)
+}
+
+/// Resolve the symbol `name`, NUL-terminated, as a function pointer of type `F`
+fn symbol<F: Copy>(name: &[u8]) -> Option<F> {
+    let lib = LIBRARY.as_ref().ok()?;
+    // SAFETY: every caller names `F` after the C prototype in libobmm.h
+    unsafe { lib.get::<F>(name) }.ok().map(|sym| *sym)
+}
+
+/// Define shims with the signatures of the `extern` block, resolved lazily
+macro_rules! lazy_ffi {
+    ($($name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty = $missing:expr;)*) => {
+        $(
+            #[doc = concat!("Lazily resolved `", stringify!($name), "` of the OBMM library")]
+            ///
+            /// Returns the C failure value if the library or symbol is missing.
+            /// # Safety
+            /// Same contract as the C function of the same name.
+            #[inline]
+            pub unsafe fn $name($($arg: $ty),*) -> $ret {
+                match symbol::<unsafe extern "C" fn($($ty),*) -> $ret>(concat!(stringify!($name), "\0").as_bytes()) {
+                    // SAFETY: forwarded under the caller's contract
+                    Some(func) => unsafe { func($($arg),*) },
+                    None => $missing,
+                }
+            }
+        )*
+    };
+}
+
+lazy_ffi! {
+    obmm_export(length: *const usize, flags: u64, desc: *mut c_void) -> MemId = OBMM_INVALID_MEMID;
+    obmm_export_acl(length: *const usize, flags: u64, desc: *mut c_void, cnas: *const u32, n_cnas: usize) -> MemId = OBMM_INVALID_MEMID;
+    obmm_export_cb(
+        length: *const usize,
+        flags: u64,
+        desc: *mut c_void,
+        cb: extern "C" fn(u64, u64, *mut c_void),
+        ctx: *mut c_void,
+    ) -> MemId = OBMM_INVALID_MEMID;
+    obmm_supported_flags() -> u64 = 0;
+    obmm_export_useraddr(pid: i32, va: *mut c_void, length: usize, flags: u64, desc: *mut c_void) -> MemId = OBMM_INVALID_MEMID;
+    obmm_unexport(id: MemId, flags: u64) -> i32 = -1;
+    obmm_cancel_export() -> i32 = -1;
+    obmm_import(desc: *const c_void, flags: u64, base_dist: i32, numa: *mut i32) -> MemId = OBMM_INVALID_MEMID;
+    obmm_import_at(desc: *const c_void, flags: u64, base_dist: i32, va: *mut c_void, numa: *mut i32) -> MemId = OBMM_INVALID_MEMID;
+    obmm_unimport(id: MemId, flags: u64) -> i32 = -1;
+    obmm_query_memid_by_pa(pa: u64, id: *mut MemId, offset: *mut u64) -> i32 = -1;
+    obmm_pin(id: MemId) -> i32 = -1;
+    obmm_unpin(id: MemId) -> i32 = -1;
+    obmm_grant(id: MemId, cna: u32, desc: *mut c_void) -> i32 = -1;
+    obmm_set_cacheable(id: MemId, cacheable: i32) -> i32 = -1;
+    obmm_query_owner(id: MemId, cna: *mut u32) -> i32 = -1;
+    obmm_access_stats(id: MemId, reads: *mut u64, writes: *mut u64, last_access_ns: *mut u64) -> i32 = -1;
+    obmm_query_pa_by_memid(id: MemId, offset: u64, pa: *mut u64) -> i32 = -1;
+    obmm_capacity(capacity: *mut u64, n_nodes: usize) -> i32 = -1;
+}
@@ -0,0 +1,146 @@
+//! Typed 128bit eid, kept as raw little-endian bytes in the descriptor ABI
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ObmmError;
+
+/// 128bit endpoint ID, as carried in `seid` and `deid`
+///
+/// Descriptors store eids as little-endian bytes for the C ABI, convert with
+/// `from_le_bytes` and `to_le_bytes`. Displayed and serialized as 32 hex
+/// digits, most significant first, hyphenated like a UUID:
+/// `00000000-0000-0000-0000-00000000abcd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Eid(u128);
+
+impl Eid {
+    /// Eid with the given integer value
+    #[inline]
+    #[must_use]
+    pub const fn new(eid: u128) -> Self {
+        Eid(eid)
+    }
+
+    /// Eid decoded from the little-endian bytes of a descriptor field
+    #[inline]
+    #[must_use]
+    pub const fn from_le_bytes(bytes: [u8; 16]) -> Self {
+        Eid(u128::from_le_bytes(bytes))
+    }
+
+    /// Little-endian bytes, as stored in a descriptor field
+    #[inline]
+    #[must_use]
+    pub const fn to_le_bytes(self) -> [u8; 16] {
+        self.0.to_le_bytes()
+    }
+
+    /// Integer value of the eid
+    #[inline]
+    #[must_use]
+    pub const fn get(self) -> u128 {
+        self.0
+    }
+}
+
+impl From<u128> for Eid {
+    #[inline]
+    fn from(eid: u128) -> Self {
+        Eid(eid)
+    }
+}
+
+impl From<Eid> for u128 {
+    #[inline]
+    fn from(eid: Eid) -> Self {
+        eid.0
+    }
+}
+
+impl fmt::Display for Eid {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let eid = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            eid >> 96_u32,
+            (eid >> 80_u32) & 0xffff,
+            (eid >> 64_u32) & 0xffff,
+            (eid >> 48_u32) & 0xffff,
+            eid & 0xffff_ffff_ffff
+        )
+    }
+}
+
+impl FromStr for Eid {
+    type Err = ObmmError;
+
+    /// Parse 32 hex digits, hyphenated as `Display` writes them or not
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hyphenated = s.len() == 36 && s.char_indices().all(|(i, c)| (c == '-') == matches!(i, 8 | 13 | 18 | 23));
+        let digits: String = if hyphenated { s.chars().filter(|&c| c != '-').collect() } else { s.to_owned() };
+        if digits.len() != 32 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(ObmmError::InvalidEid(s.to_owned()));
+        }
+        u128::from_str_radix(&digits, 16).map(Eid).map_err(|_err| ObmmError::InvalidEid(s.to_owned()))
+    }
+}
+
+impl Serialize for Eid {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Eid {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let eid = String::deserialize(deserializer)?;
+        eid.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        let mut bytes = [0; 16];
+        bytes[0] = 0xcd;
+        bytes[1] = 0xab;
+        let eid = Eid::from_le_bytes(bytes);
+        assert_eq!(eid, Eid::new(0xabcd));
+        assert_eq!(eid.to_le_bytes(), bytes);
+        assert_eq!(u128::from(eid), 0xabcd);
+        assert_eq!(Eid::default().get(), 0);
+    }
+
+    #[test]
+    fn test_parse_display() -> Result<(), ObmmError> {
+        let eid = Eid::new(0x0123_4567_89ab_cdef_0011_2233_4455_6677);
+        assert_eq!(eid.to_string(), "01234567-89ab-cdef-0011-223344556677");
+        assert_eq!("01234567-89ab-cdef-0011-223344556677".parse::<Eid>()?, eid);
+        assert_eq!("0123456789ABCDEF0011223344556677".parse::<Eid>()?, eid);
+        for invalid in ["", "abcd", "0123456789abcdef00112233445566770", "01234567-89ab-cdef-0011-22334455667g", "0123456-789ab-cdef-0011-223344556677"] {
+            assert_eq!(invalid.parse::<Eid>(), Err(ObmmError::InvalidEid(invalid.to_owned())));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde_round_trip() -> anyhow::Result<()> {
+        let eid = Eid::new(0xabcd);
+        let json = serde_json::to_string(&eid)?;
+        assert_eq!(json, r#""00000000-0000-0000-0000-00000000abcd""#);
+        assert_eq!(serde_json::from_str::<Eid>(&json)?, eid);
+        assert!(serde_json::from_str::<Eid>(r#""not an eid""#).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Error type for OBMM operations
+
+use thiserror::Error;
+
+/// Errors reported by OBMM wrappers
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ObmmError {
+    /// Descriptor address is not aligned to the page size
+    #[error("address {0:#x} is not page-aligned")]
+    UnalignedAddress(u64),
+    /// Descriptor address is outside the UB address window
+    #[error("address {0:#x} is outside the UB address window")]
+    AddressOutOfWindow(u64),
+    /// Flag names that are not defined, comma-separated
+    #[error("unknown flags: {0}")]
+    UnknownFlags(String),
+    /// A read-only export cannot be mapped writable
+    #[error("MemID {0} was exported read-only")]
+    ReadOnly(u64),
+    /// A string that is not 32 hex digits, optionally hyphenated
+    #[error("invalid eid: {0:?}")]
+    InvalidEid(String),
+    /// An import placement names an invalid NUMA node set
+    #[error("invalid import placement: {0}")]
+    InvalidPlacement(String),
+    /// An import landed on a NUMA node its placement hint does not allow
+    #[error("NUMA node {node} does not satisfy placement hint {base_dist:#x}")]
+    PlacementMismatch {
+        /// Placement hint given to the import
+        base_dist: i32,
+        /// NUMA node the driver assigned
+        node: i32,
+    },
+    /// Import flags conflict with how the region was exported
+    #[error("incompatible flags: {0}")]
+    IncompatibleFlags(String),
+    /// The Memory ID is not a live export or import of this process
+    #[error("unknown MemID {0}")]
+    UnknownMemId(u64),
+    /// Unpin of a region that is not pinned
+    #[error("MemID {0} is not pinned")]
+    NotPinned(u64),
+    /// `priv_len` disagrees with the serialized length of `priv_data`
+    #[error("priv_len {declared} does not match priv_data length {actual:?}")]
+    PrivLenMismatch {
+        /// Length the descriptor claims
+        declared: u16,
+        /// Serialized length of the privilege data, `None` if it doesn't fit a `u16`
+        actual: Option<u16>,
+    },
+    /// A descriptor failed to encode or decode
+    #[error("descriptor codec error: {0}")]
+    Codec(String),
+    /// A descriptor file is past its `expires_at`
+    #[error("descriptor of MemID {0} has expired")]
+    Expired(u64),
+    /// Flags the driver does not support, by name or as hex for undefined bits
+    #[error("unsupported export flags: {0}")]
+    UnsupportedFlags(String),
+    /// The region is still imported by this process
+    #[error("MemID {0} is still imported locally")]
+    StillImported(u64),
+    /// The importing CNA is not on the allow-list of the export
+    #[error("CNA {0} is not allowed to import this region")]
+    CnaNotAllowed(u32),
+    /// The descriptor file of a Memory ID changed since it was read
+    #[error("descriptor file of MemID {0} does not match the expected contents")]
+    Conflict(u64),
+    /// `length` disagrees with the sum of the per-node breakdown
+    #[error("length {length} does not match per-node sum {per_node_sum:?}")]
+    LengthMismatch {
+        /// Aggregate length of the descriptor
+        length: u64,
+        /// Sum of the per-node lengths, `None` if it overflows
+        per_node_sum: Option<u64>,
+    },
+    /// The descriptor describes an empty region
+    #[error("descriptor length is zero")]
+    ZeroLength,
+    /// The export rate limit budget is exhausted
+    #[error("export rate limit exceeded")]
+    RateLimited,
+    /// The operation was cancelled through a `CancelToken`
+    #[error("operation cancelled")]
+    Cancelled,
+    /// The OBMM library could not be loaded at runtime, with the reason
+    #[error("OBMM library unavailable: {0}")]
+    LibraryUnavailable(String),
+    /// The OBMM C library returned an error code
+    #[error("OBMM call failed: {} ({0})", ObmmError::describe_code(*.0))]
+    Ffi(i32),
+}
+
+impl ObmmError {
+    /// Describe a return code of the OBMM C library
+    ///
+    /// Known codes are the negated errno values the library reports:
+    /// * `-1` - generic failure
+    /// * `-12` - out of memory (`ENOMEM`)
+    /// * `-16` - device or resource busy (`EBUSY`)
+    /// * `-22` - invalid argument (`EINVAL`)
+    /// # Arguments
+    /// * `code` - Return code of an OBMM call
+    /// # Returns
+    /// Human readable description, "unknown error" for other codes
+    #[inline]
+    #[must_use]
+    pub fn describe_code(code: i32) -> &'static str {
+        match code {
+            -1 => "generic failure",
+            -12 => "out of memory",
+            -16 => "device or resource busy",
+            -22 => "invalid argument",
+            _ => "unknown error",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_code() {
+        assert_eq!(ObmmError::describe_code(-1), "generic failure");
+        assert_eq!(ObmmError::describe_code(-12), "out of memory");
+        assert_eq!(ObmmError::describe_code(-22), "invalid argument");
+        assert_eq!(ObmmError::describe_code(-5), "unknown error");
+        assert_eq!(ObmmError::Ffi(-12).to_string(), "OBMM call failed: out of memory (-12)");
+        assert_eq!(ObmmError::Ffi(-5).to_string(), "OBMM call failed: unknown error (-5)");
+    }
+}
@@ -0,0 +1,126 @@
+//! Subscriptions to export, import and release events
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, PoisonError, RwLock};
+
+use crate::MemId;
+
+/// A change in the set of live regions of this process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ObmmEvent {
+    /// A region was exported
+    Exported {
+        /// Memory ID of the export
+        memid: MemId,
+        /// Length of the region
+        bytes: u64,
+    },
+    /// A region was imported
+    Imported {
+        /// Memory ID of the import
+        memid: MemId,
+        /// NUMA node the region was imported onto, `-1` if unassigned
+        node: i32,
+    },
+    /// An export was released
+    Unexported {
+        /// Memory ID of the export
+        memid: MemId,
+    },
+    /// An import was released
+    Unimported {
+        /// Memory ID of the import
+        memid: MemId,
+    },
+}
+
+/// Handle of a subscription, for `unsubscribe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// An event callback
+type Callback = Arc<dyn Fn(ObmmEvent) + Send + Sync>;
+
+/// Registered callbacks, in subscription order
+static SUBSCRIBERS: LazyLock<RwLock<Vec<(SubscriptionId, Callback)>>> = LazyLock::new(RwLock::default);
+
+/// Next subscription handle
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Call `f` with every export, import and release of this process
+///
+/// Callbacks run on the thread of the wrapper that caused the event, after
+/// the OBMM call returned and with no lock held, so they may call back into
+/// the crate. Events of one region are delivered in order.
+/// # Arguments
+/// * `f` - Callback receiving each event
+/// # Returns
+/// Handle to pass to `unsubscribe`
+#[inline]
+pub fn subscribe(f: impl Fn(ObmmEvent) + Send + Sync + 'static) -> SubscriptionId {
+    let id = SubscriptionId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    SUBSCRIBERS.write().unwrap_or_else(PoisonError::into_inner).push((id, Arc::new(f)));
+    id
+}
+
+/// Stop delivering events to a subscription
+/// # Arguments
+/// * `id` - Handle returned by `subscribe`
+/// # Returns
+/// Whether the subscription existed
+#[inline]
+pub fn unsubscribe(id: SubscriptionId) -> bool {
+    let mut subscribers = SUBSCRIBERS.write().unwrap_or_else(PoisonError::into_inner);
+    let before = subscribers.len();
+    subscribers.retain(|entry| entry.0 != id);
+    subscribers.len() != before
+}
+
+/// Deliver `event` to every subscriber
+pub(crate) fn emit(event: ObmmEvent) {
+    // snapshot so callbacks run without the lock and may (un)subscribe
+    let callbacks: Vec<Callback> = SUBSCRIBERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|entry| Arc::clone(&entry.1))
+        .collect();
+    for callback in callbacks {
+        callback(event);
+    }
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{MAX_NUMA_NODES, ObmmExportFlags, ObmmUnexportFlags, UbPrivData, mem_export, mem_unexport};
+
+    #[test]
+    fn test_events_in_order() -> anyhow::Result<()> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = subscribe(move |event| sink.lock().unwrap_or_else(PoisonError::into_inner).push(event));
+
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        if let Some(v) = lengths.get_mut(1) {
+            *v = 1024 * 1024;
+        }
+        let (memid, _) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::empty())?;
+        mem_unexport(memid, ObmmUnexportFlags::empty())?;
+        assert!(unsubscribe(id));
+        assert!(!unsubscribe(id));
+
+        let events: Vec<ObmmEvent> = seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .copied()
+            .filter(|&event| matches!(event, ObmmEvent::Exported { memid: m, .. } | ObmmEvent::Unexported { memid: m } if m == memid))
+            .collect();
+        assert_eq!(events, [ObmmEvent::Exported { memid, bytes: 1024 * 1024 }, ObmmEvent::Unexported { memid }]);
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Names of descriptor files and their sidecars
+
+use std::fmt;
+
+use crate::MemId;
+
+/// Prefix of descriptor file names written by memlink
+pub const DEFAULT_FILENAME_PREFIX: &str = "memdesc_";
+
+/// Name of a file belonging to a Memory ID, such as `memdesc_7.json`
+///
+/// The name is `<prefix><id>.<extension>`: descriptors use the extension
+/// `json`, meta sidecars `meta.json` and lock files `json.lock`. Only the
+/// canonical decimal form of the Memory ID is accepted, so parsing and
+/// `to_string` round-trip.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct MemlinkFilename {
+    /// Text before the Memory ID
+    pub prefix: String,
+    /// Memory ID the file belongs to
+    pub mem_id: MemId,
+    /// Everything after the first `.` following the Memory ID
+    pub extension: String,
+}
+
+impl MemlinkFilename {
+    /// Name with the default prefix
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// * `extension` - Extension without the leading `.`, e.g. `"json"`
+    #[inline]
+    #[must_use]
+    pub fn new(mem_id: MemId, extension: &str) -> Self {
+        Self::with_prefix(DEFAULT_FILENAME_PREFIX, mem_id, extension)
+    }
+
+    /// Name with a custom prefix
+    /// # Arguments
+    /// * `prefix` - Text before the Memory ID
+    /// * `mem_id` - Memory ID
+    /// * `extension` - Extension without the leading `.`, e.g. `"json"`
+    #[inline]
+    #[must_use]
+    pub fn with_prefix(prefix: &str, mem_id: MemId, extension: &str) -> Self {
+        MemlinkFilename { prefix: prefix.to_owned(), mem_id, extension: extension.to_owned() }
+    }
+
+    /// Parse a file name with the default prefix
+    /// # Arguments
+    /// * `name` - File name without directory
+    /// # Returns
+    /// `MemlinkFilename` if `name` is `memdesc_<id>.<extension>`, `None` otherwise
+    #[inline]
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::parse_with_prefix(name, DEFAULT_FILENAME_PREFIX)
+    }
+
+    /// Parse a file name with a custom prefix
+    /// # Arguments
+    /// * `name` - File name without directory
+    /// * `prefix` - Text expected before the Memory ID
+    /// # Returns
+    /// `MemlinkFilename` if `name` is `<prefix><id>.<extension>`, `None` otherwise
+    #[inline]
+    #[must_use]
+    pub fn parse_with_prefix(name: &str, prefix: &str) -> Option<Self> {
+        let (id, extension) = name.strip_prefix(prefix)?.split_once('.')?;
+        let mem_id = id.parse::<MemId>().ok().filter(|mem_id| mem_id.to_string() == id)?;
+        (!extension.is_empty()).then(|| Self::with_prefix(prefix, mem_id, extension))
+    }
+
+    /// Whether this names a descriptor file rather than a sidecar
+    #[inline]
+    #[must_use]
+    pub fn is_descriptor(&self) -> bool {
+        self.extension == "json"
+    }
+}
+
+impl fmt::Display for MemlinkFilename {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}.{}", self.prefix, self.mem_id, self.extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid() {
+        assert_eq!(MemlinkFilename::parse("memdesc_7.json"), Some(MemlinkFilename::new(7, "json")));
+        assert_eq!(MemlinkFilename::parse("memdesc_7.meta.json"), Some(MemlinkFilename::new(7, "meta.json")));
+        assert_eq!(MemlinkFilename::parse("memdesc_0.json.lock"), Some(MemlinkFilename::new(0, "json.lock")));
+        assert!(MemlinkFilename::new(7, "json").is_descriptor());
+        assert!(!MemlinkFilename::new(7, "meta.json").is_descriptor());
+        assert_eq!(MemlinkFilename::new(42, "json").to_string(), "memdesc_42.json");
+    }
+
+    #[test]
+    fn test_parse_rejects_unrelated() {
+        let unrelated = [
+            "idempotency.json", "other_7.json", "memdesc_.json", "memdesc_x.json", "memdesc_07.json", "memdesc_+7.json", "memdesc_7", "memdesc_7.",
+        ];
+        for name in unrelated {
+            assert_eq!(MemlinkFilename::parse(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_custom_prefix_round_trip() {
+        let name = MemlinkFilename::with_prefix("region-", 9, "json");
+        assert_eq!(name.to_string(), "region-9.json");
+        assert_eq!(MemlinkFilename::parse_with_prefix(&name.to_string(), "region-"), Some(name));
+        assert_eq!(MemlinkFilename::parse("region-9.json"), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Non-generic `#[repr(C)]` descriptor for FFI consumers such as cxx
+
+use crate::{LifetimeClass, MAX_NUMA_NODES, ObmmError, ObmmExportFlags, ObmmMemDesc, UbPrivData};
+
+/// Fully concrete flattening of `ObmmMemDesc<UbPrivData>`
+///
+/// Flags are stored as their raw bits and `per_node` as an array plus a
+/// presence flag, giving a stable ABI without generics or `Option`. The
+/// `expires_at` of file-based descriptors is not carried.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FlatMemDesc {
+    /// Base address of the memory region
+    pub addr: u64,
+    /// Length of the memory region
+    pub length: u64,
+    /// 128bit eid, ordered by little-endian
+    pub seid: [u8; 16],
+    /// 128bit deid, ordered by little-endian
+    pub deid: [u8; 16],
+    /// Token ID
+    pub tokenid: u32,
+    /// Source CNA
+    pub scna: u32,
+    /// Destination CNA
+    pub dcna: u32,
+    /// Length of privilege data
+    pub priv_len: u16,
+    /// Bits of the `UbPrivData` privilege data
+    pub priv_data: u16,
+    /// Descriptor schema version
+    pub schema_version: u32,
+    /// Whether `per_node` holds a breakdown
+    pub has_per_node: bool,
+    /// Per-NUMA-node breakdown of `length`, zeroed if `has_per_node` is false
+    pub per_node: [u64; MAX_NUMA_NODES],
+    /// Bits of the `ObmmExportFlags` the region was exported with
+    pub export_flags: u64,
+}
+
+impl From<ObmmMemDesc<UbPrivData>> for FlatMemDesc {
+    #[inline]
+    fn from(desc: ObmmMemDesc<UbPrivData>) -> Self {
+        FlatMemDesc {
+            addr: desc.addr,
+            length: desc.length,
+            seid: desc.seid,
+            deid: desc.deid,
+            tokenid: desc.tokenid,
+            scna: desc.scna,
+            dcna: desc.dcna,
+            priv_len: desc.priv_len,
+            priv_data: desc.priv_data.bits(),
+            schema_version: desc.schema_version,
+            has_per_node: desc.per_node.is_some(),
+            per_node: desc.per_node.unwrap_or_default(),
+            export_flags: desc.export_flags.bits(),
+        }
+    }
+}
+
+impl TryFrom<FlatMemDesc> for ObmmMemDesc<UbPrivData> {
+    type Error = ObmmError;
+
+    /// Fails with `ObmmError::UnknownFlags` if either flag field has undefined bits
+    #[inline]
+    fn try_from(flat: FlatMemDesc) -> Result<Self, Self::Error> {
+        let priv_data = UbPrivData::from_bits_validated(flat.priv_data)?;
+        let export_flags = ObmmExportFlags::from_bits_validated(flat.export_flags)?;
+        Ok(ObmmMemDesc {
+            addr: flat.addr,
+            length: flat.length,
+            seid: flat.seid,
+            deid: flat.deid,
+            tokenid: flat.tokenid,
+            scna: flat.scna,
+            dcna: flat.dcna,
+            priv_len: flat.priv_len,
+            priv_data,
+            schema_version: flat.schema_version,
+            per_node: flat.has_per_node.then_some(flat.per_node),
+            export_flags,
+            expires_at: None,
+            lifetime: LifetimeClass::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_round_trip() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData> {
+            addr: 0xffff_fc00_0000,
+            length: 128 * 1024 * 1024,
+            seid: [1; 16],
+            deid: [2; 16],
+            tokenid: 42,
+            scna: 3,
+            dcna: 4,
+            priv_len: 2,
+            priv_data: UbPrivData::OCHIP | UbPrivData::CACHEABLE,
+            per_node: Some([8 * 1024 * 1024; MAX_NUMA_NODES]),
+            export_flags: ObmmExportFlags::ALLOWMMAP,
+            ..ObmmMemDesc::default()
+        };
+        let flat = FlatMemDesc::from(desc);
+        assert_eq!(flat.priv_data, desc.priv_data.bits());
+        assert!(flat.has_per_node);
+        assert_eq!(ObmmMemDesc::try_from(flat)?, desc);
+
+        let empty = ObmmMemDesc::<UbPrivData>::default();
+        assert_eq!(ObmmMemDesc::try_from(FlatMemDesc::from(empty))?, empty);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flat_unknown_bits() {
+        let flat = FlatMemDesc { priv_data: 1, ..FlatMemDesc::default() };
+        assert_eq!(ObmmMemDesc::try_from(flat), Err(ObmmError::UnknownFlags("0x1".to_owned())));
+    }
+}
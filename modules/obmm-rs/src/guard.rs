@@ -0,0 +1,187 @@
+//! RAII guards that unexport memory when dropped
+
+use std::marker::PhantomData;
+
+use serde::Serialize;
+
+use crate::{MemId, ObmmExportFlags, ObmmMemDesc, ObmmUnexportFlags, USERADDR_ALIGN, UbPrivData, mem_export, mem_export_at, mem_unexport};
+
+/// An exported memory region that is unexported when dropped
+#[derive(Debug)]
+pub struct ExportedMemory<T> {
+    /// Memory ID of the export
+    memid: MemId,
+    /// Memory Descriptor of the export
+    desc: ObmmMemDesc<T>,
+}
+
+impl<T: Default + Serialize> ExportedMemory<T> {
+    /// Export memory region and wrap it in a guard
+    /// # Arguments
+    /// * `length` - Array of lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// # Returns
+    /// # Errors
+    /// `ExportedMemory` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn export(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<Self> {
+        let (memid, desc) = mem_export::<T>(length, flags)?;
+        Ok(ExportedMemory { memid, desc })
+    }
+}
+
+impl<T> ExportedMemory<T> {
+    /// Wrap an existing export in a guard
+    #[cfg(all(unix, feature = "mmap"))]
+    pub(crate) fn from_parts(memid: MemId, desc: ObmmMemDesc<T>) -> Self {
+        ExportedMemory { memid, desc }
+    }
+
+    /// Memory ID of the export
+    #[inline]
+    #[must_use]
+    pub fn memid(&self) -> MemId {
+        self.memid
+    }
+
+    /// Memory Descriptor of the export
+    #[inline]
+    #[must_use]
+    pub fn desc(&self) -> &ObmmMemDesc<T> {
+        &self.desc
+    }
+}
+
+impl<T> Drop for ExportedMemory<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // errors cannot be reported from drop
+        let _result = mem_unexport(self.memid, ObmmUnexportFlags::empty());
+    }
+}
+
+/// Export of a borrowed buffer that is unexported when dropped
+///
+/// Holds the borrow of the buffer, so it can't be freed, reallocated or moved
+/// while exported. Only dropping the guard unexports the buffer: after
+/// `mem::forget` on the guard the borrow ends but the buffer stays exported
+/// and pinned, and importers keep access to memory Rust may reuse.
+#[derive(Debug)]
+pub struct SliceExport<'buf> {
+    /// Memory ID of the export
+    memid: MemId,
+    /// Borrow of the exported buffer
+    buf: PhantomData<&'buf [u8]>,
+}
+
+impl SliceExport<'_> {
+    /// Memory ID of the export
+    #[inline]
+    #[must_use]
+    pub fn memid(&self) -> MemId {
+        self.memid
+    }
+}
+
+impl Drop for SliceExport<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        // errors cannot be reported from drop
+        let _result = mem_unexport(self.memid, ObmmUnexportFlags::empty());
+    }
+}
+
+/// Export a buffer of the calling process, such as the contents of a `Vec<u8>`
+///
+/// The address and length come from `buf`, which is exported with
+/// `mem_export_at`. The driver pins its pages, and the returned guard borrows
+/// `buf` until it is dropped, which unexports it. The buffer is shared only as
+/// `&[u8]`: importers must not write to it, as Rust assumes it doesn't change
+/// while borrowed.
+///
+/// As `obmm_export_useraddr` requires, `buf` must start and end on a
+/// `USERADDR_ALIGN` boundary and be backed by 2MB huge pages, e.g. a
+/// `MAP_HUGETLB` mapping; a plain `Vec<u8>` generally is neither. Unaligned
+/// buffers are rejected before reaching the driver; the backing page size is
+/// checked by the driver only.
+/// # Arguments
+/// * `buf` - Buffer to export, non-empty and `USERADDR_ALIGN` aligned in address and length
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Memory ID, Memory Descriptor and the guard of the export on success,
+/// `anyhow::Error` if `buf` is empty or unaligned or the export fails
+#[inline]
+pub fn mem_export_slice(buf: &[u8], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<UbPrivData>, SliceExport<'_>)> {
+    if buf.is_empty() {
+        anyhow::bail!("Cannot export an empty buffer");
+    }
+    if !buf.as_ptr().addr().is_multiple_of(USERADDR_ALIGN) || !buf.len().is_multiple_of(USERADDR_ALIGN) {
+        anyhow::bail!(
+            "Buffer at {:#x} of {} bytes is not aligned to {USERADDR_ALIGN} bytes in address and length",
+            buf.as_ptr().addr(),
+            buf.len()
+        );
+    }
+    let (memid, desc) = mem_export_at(u64::try_from(buf.as_ptr().addr())?, buf.len(), flags)?;
+    Ok((memid, desc, SliceExport { memid, buf: PhantomData }))
+}
+
+/// Lazily export each region spec, yielding a guard per export
+///
+/// Nothing is exported ahead of consumption: when iteration stops early only
+/// the guards already produced exist, so
+/// `export_each(specs).collect::<anyhow::Result<Vec<_>>>()` exports up to the
+/// first failure and unexports everything exported so far.
+/// # Arguments
+/// * `specs` - Per-node lengths and export flags of each region
+/// # Returns
+/// Iterator of `ExportedMemory` guards, or the error of each failed export
+#[inline]
+pub fn export_each<T: Default + Serialize>(
+    specs: impl IntoIterator<Item = (Vec<usize>, ObmmExportFlags)>,
+) -> impl Iterator<Item = anyhow::Result<ExportedMemory<T>>> {
+    specs
+        .into_iter()
+        .map(|(length, flags)| ExportedMemory::export(&length, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use anyhow::Context as _;
+    use super::*;
+    use crate::{MAX_NUMA_NODES, UbPrivData};
+
+    #[test]
+    fn test_export_each_is_lazy() -> anyhow::Result<()> {
+        let pulled = Cell::new(0_usize);
+        let specs = (0..5).map(|_| {
+            pulled.set(pulled.get().saturating_add(1));
+            (vec![1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::ALLOWMMAP)
+        });
+        let guards = export_each::<UbPrivData>(specs)
+            .take(2)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(guards.len(), 2);
+        assert_eq!(pulled.get(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_slice() -> anyhow::Result<()> {
+        let backing = vec![0xa5_u8; 3 * USERADDR_ALIGN];
+        let offset = backing.as_ptr().align_offset(USERADDR_ALIGN);
+        let buf = backing.get(offset..offset.saturating_add(USERADDR_ALIGN)).context("no aligned window")?;
+        let (memid, desc, guard) = mem_export_slice(buf, ObmmExportFlags::empty())?;
+        assert_eq!(usize::try_from(desc.length)?, buf.len());
+        assert_eq!(guard.memid(), memid);
+        assert!(crate::lookup_descriptor(memid).is_some());
+        drop(guard);
+        assert!(crate::lookup_descriptor(memid).is_none());
+        assert!(mem_export_slice(&[], ObmmExportFlags::empty()).is_err());
+        assert!(mem_export_slice(buf.get(1..).unwrap_or_default(), ObmmExportFlags::empty()).is_err());
+        assert!(mem_export_slice(buf.get(..4096).unwrap_or_default(), ObmmExportFlags::empty()).is_err());
+        Ok(())
+    }
+}
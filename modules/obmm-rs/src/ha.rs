@@ -0,0 +1,69 @@
+//! Mirroring exports to a backup CNA for high availability
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MemId, ObmmExportFlags, ObmmMemDesc, mem_export, mem_grant};
+
+/// Sentinel of `BACKUP_CNA` meaning no backup CNA is configured
+const NO_BACKUP: u64 = u64::MAX;
+
+/// CNA every `mem_export_ha` export is granted to, `NO_BACKUP` if none
+static BACKUP_CNA: AtomicU64 = AtomicU64::new(NO_BACKUP);
+
+/// Configure the CNA `mem_export_ha` mirrors every export to
+/// # Arguments
+/// * `cna` - Backup CNA, `None` to stop mirroring
+#[inline]
+pub fn set_backup_cna(cna: Option<u32>) {
+    BACKUP_CNA.store(cna.map_or(NO_BACKUP, u64::from), Ordering::Relaxed);
+}
+
+/// CNA configured with `set_backup_cna`, `None` if none
+fn backup_cna() -> Option<u32> {
+    u32::try_from(BACKUP_CNA.load(Ordering::Relaxed)).ok()
+}
+
+/// Export made by `mem_export_ha` with its backup grant
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HaExport<T> {
+    /// Memory ID of the export
+    pub memid: MemId,
+    /// Memory Descriptor for the primary consumer
+    pub primary: ObmmMemDesc<T>,
+    /// Memory Descriptor for the backup CNA, `None` if none is configured or the grant failed
+    pub backup: Option<ObmmMemDesc<T>>,
+    /// Why the backup grant failed, empty otherwise
+    pub warnings: Vec<String>,
+}
+
+/// Export memory region and grant the configured backup CNA access to it
+///
+/// Without a backup CNA this is `mem_export`. A failed grant does not fail
+/// the export: it is logged and reported in `HaExport::warnings`.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Export with its primary and backup descriptors on success, `anyhow::Error`
+/// if the export fails
+#[inline]
+pub fn mem_export_ha<T>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<HaExport<T>>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    let (memid, primary) = mem_export(length, flags)?;
+    let mut warnings = Vec::new();
+    let backup = backup_cna().and_then(|cna| match mem_grant(memid, cna) {
+        Ok(backup) => Some(backup),
+        Err(err) => {
+            log::warn!("Export of MemID {memid}: backup grant to CNA {cna} failed: {err:#}");
+            warnings.push(format!("backup grant to CNA {cna} failed: {err:#}"));
+            None
+        }
+    });
+    Ok(HaExport { memid, primary, backup, warnings })
+}
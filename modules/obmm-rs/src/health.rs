@@ -0,0 +1,187 @@
+//! Driver health probing and a background watchdog
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Context;
+
+/// Where the kernel exposes the version of the loaded OBMM module
+#[cfg(not(feature = "hook"))]
+const OBMM_MODULE_VERSION_PATH: &str = "/sys/module/obmm/version";
+
+/// Probe the OBMM driver for its version
+/// # Returns
+/// # Errors
+/// Driver version on success, `anyhow::Error` if the driver is not loaded or unresponsive
+#[cfg(feature = "hook")]
+#[inline]
+pub fn obmm_version() -> anyhow::Result<String> {
+    if crate::hook::healthy() {
+        Ok("hook".to_owned())
+    } else {
+        Err(anyhow::anyhow!("OBMM driver is unhealthy"))
+    }
+}
+
+/// Probe the OBMM driver for its version
+/// # Returns
+/// # Errors
+/// Driver version on success, `anyhow::Error` if the driver is not loaded or unresponsive
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn obmm_version() -> anyhow::Result<String> {
+    let version = std::fs::read_to_string(OBMM_MODULE_VERSION_PATH)
+        .with_context(|| format!("Failed to read {OBMM_MODULE_VERSION_PATH}"))?;
+    Ok(version.trim().to_owned())
+}
+
+/// Health of the OBMM driver as last observed by a `HealthWatchdog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HealthStatus {
+    /// The last probe succeeded within the timeout
+    Healthy,
+    /// The last probe failed or timed out
+    Unhealthy,
+}
+
+/// State shared between a `HealthWatchdog` and its thread
+#[derive(Debug)]
+struct Shared {
+    /// Whether the last probe succeeded
+    healthy: AtomicBool,
+    /// Set when the watchdog is asked to stop
+    stopped: Mutex<bool>,
+    /// Wakes the thread early on stop
+    wake: Condvar,
+}
+
+/// Background thread pinging the OBMM driver at a fixed interval
+///
+/// The driver starts out assumed healthy and the callback is invoked with the
+/// new status on every transition. A probe that does not answer within the
+/// timeout counts as unhealthy. Dropping the watchdog stops it.
+#[derive(Debug)]
+pub struct HealthWatchdog {
+    /// State shared with the thread
+    shared: Arc<Shared>,
+    /// Watchdog thread, taken on stop
+    handle: Option<JoinHandle<()>>,
+}
+
+impl HealthWatchdog {
+    /// Start the watchdog thread
+    /// # Arguments
+    /// * `interval` - Delay between probes
+    /// * `timeout` - Maximum time to wait for a probe to answer
+    /// * `callback` - Invoked with the new status on each transition
+    /// # Returns
+    /// # Errors
+    /// `HealthWatchdog` on success, `anyhow::Error` if the thread can't be spawned
+    #[inline]
+    pub fn start<F>(interval: Duration, timeout: Duration, mut callback: F) -> anyhow::Result<Self>
+    where
+        F: FnMut(HealthStatus) + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            healthy: AtomicBool::new(true),
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+        let thread_shared = Arc::clone(&shared);
+        let handle = thread::Builder::new()
+            .name("obmm-watchdog".to_owned())
+            .spawn(move || {
+                let mut stopped = thread_shared.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+                while !*stopped {
+                    drop(stopped);
+                    let healthy = probe(timeout);
+                    if thread_shared.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                        callback(if healthy { HealthStatus::Healthy } else { HealthStatus::Unhealthy });
+                    }
+                    stopped = thread_shared.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+                    if !*stopped {
+                        (stopped, _) = thread_shared
+                            .wake
+                            .wait_timeout(stopped, interval)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                }
+            })
+            .context("Failed to spawn watchdog thread")?;
+        Ok(HealthWatchdog { shared, handle: Some(handle) })
+    }
+
+    /// Health observed by the most recent probe
+    #[inline]
+    #[must_use]
+    pub fn status(&self) -> HealthStatus {
+        if self.shared.healthy.load(Ordering::Relaxed) {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        }
+    }
+
+    /// Stop the watchdog and join its thread
+    #[inline]
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signal the thread to stop and wait for it to exit
+    fn shutdown(&mut self) {
+        *self.shared.stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.shared.wake.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _result = handle.join();
+        }
+    }
+}
+
+impl Drop for HealthWatchdog {
+    #[inline]
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Run `obmm_version` on a helper thread, giving up after `timeout`
+///
+/// A hung probe leaves its helper thread behind, it exits if the driver ever answers.
+fn probe(timeout: Duration) -> bool {
+    let (tx, rx) = mpsc::channel();
+    let spawned = thread::Builder::new().name("obmm-probe".to_owned()).spawn(move || {
+        let _result = tx.send(obmm_version().is_ok());
+    });
+    spawned.is_ok() && rx.recv_timeout(timeout).unwrap_or(false)
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::hook::set_hook_healthy;
+
+    #[test]
+    fn test_watchdog_transitions() -> anyhow::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let watchdog = HealthWatchdog::start(Duration::from_millis(5), Duration::from_secs(1), move |status| {
+            let _result = tx.send(status);
+        })?;
+        assert_eq!(watchdog.status(), HealthStatus::Healthy);
+
+        set_hook_healthy(false);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5))?, HealthStatus::Unhealthy);
+        assert_eq!(watchdog.status(), HealthStatus::Unhealthy);
+
+        set_hook_healthy(true);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5))?, HealthStatus::Healthy);
+
+        watchdog.stop();
+        assert!(rx.try_recv().is_err());
+        Ok(())
+    }
+}
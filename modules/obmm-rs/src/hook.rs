@@ -0,0 +1,134 @@
+//! State backing the hooked implementations used without the OBMM driver
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::{MAX_NUMA_NODES, MemId};
+
+/// Next Memory ID handed out by hooked exports and imports
+static NEXT_MEMID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// NUMA node reported by hooked imports on this thread
+    static IMPORT_NODE: Cell<i32> = const { Cell::new(0) };
+}
+
+/// Allocate a fresh, unique Memory ID for a hooked export or import
+pub(crate) fn next_memid() -> MemId {
+    NEXT_MEMID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Make hooked exports and imports hand out Memory IDs from `id` onwards
+///
+/// The counter only moves forward so IDs stay unique: an `id` below the next
+/// one to be handed out is ignored.
+/// # Arguments
+/// * `id` - Next Memory ID to hand out, `OBMM_INVALID_MEMID` is skipped
+#[inline]
+pub fn set_hook_next_memid(id: MemId) {
+    let _prev = NEXT_MEMID.fetch_max(id.max(1), Ordering::Relaxed);
+}
+
+/// NUMA node hooked imports report on the calling thread
+pub(crate) fn import_node() -> i32 {
+    IMPORT_NODE.get()
+}
+
+/// Set the NUMA node hooked imports report on the calling thread
+/// # Arguments
+/// * `node` - NUMA node ID, `-1` for an unassigned node
+#[inline]
+pub fn set_hook_import_node(node: i32) {
+    IMPORT_NODE.set(node);
+}
+
+/// Whether the hooked driver health probe succeeds
+static HEALTHY: AtomicBool = AtomicBool::new(true);
+
+/// Whether the hooked driver is reported healthy
+pub(crate) fn healthy() -> bool {
+    HEALTHY.load(Ordering::Relaxed)
+}
+
+/// Set whether the hooked `obmm_version` probe succeeds
+/// # Arguments
+/// * `healthy` - `false` to make the probe fail
+#[inline]
+pub fn set_hook_healthy(healthy: bool) {
+    HEALTHY.store(healthy, Ordering::Relaxed);
+}
+
+/// Read count, write count and last access time of a hooked region
+type AccessCounters = (u64, u64, SystemTime);
+
+/// Access counters of hooked regions
+static ACCESS: LazyLock<Mutex<HashMap<MemId, AccessCounters>>> = LazyLock::new(Mutex::default);
+
+/// Stored access counters of a hooked region, `None` if it was never accessed
+pub(crate) fn access_counters(memid: MemId) -> Option<AccessCounters> {
+    ACCESS.lock().unwrap_or_else(PoisonError::into_inner).get(&memid).copied()
+}
+
+/// Count accesses to a region in the counters hooked `mem_access_stats` reports
+/// # Arguments
+/// * `memid` - Memory ID of the region
+/// * `reads` - Number of reads to add
+/// * `writes` - Number of writes to add
+#[inline]
+pub fn record_hook_access(memid: MemId, reads: u64, writes: u64) {
+    let mut access = ACCESS.lock().unwrap_or_else(PoisonError::into_inner);
+    let entry = access.entry(memid).or_insert((0, 0, SystemTime::UNIX_EPOCH));
+    *entry = (entry.0.saturating_add(reads), entry.1.saturating_add(writes), SystemTime::now());
+}
+
+/// Address and length of a region known only to the hooked driver
+type PaRange = (u64, u64);
+
+/// Regions the hooked `obmm_query_memid_by_pa` resolves, by Memory ID
+static PA_OWNERS: LazyLock<Mutex<HashMap<MemId, PaRange>>> = LazyLock::new(Mutex::default);
+
+/// Region of the hooked driver containing `pa` and the offset into it
+pub(crate) fn pa_owner(pa: u64) -> Option<(MemId, u64)> {
+    let owners = PA_OWNERS.lock().unwrap_or_else(PoisonError::into_inner);
+    owners
+        .iter()
+        .filter_map(|(&memid, &(addr, length))| pa.checked_sub(addr).filter(|&offset| offset < length).map(|offset| (memid, offset)))
+        .min()
+}
+
+/// Make the hooked driver resolve `[addr, addr + length)` to `memid`
+///
+/// Stands in for regions the driver knows of but this process does not
+/// track, such as exports made before a restart.
+/// # Arguments
+/// * `memid` - Memory ID the range resolves to
+/// * `addr` - Physical address of the range
+/// * `length` - Length of the range, 0 to forget `memid`
+#[inline]
+pub fn set_hook_pa_owner(memid: MemId, addr: u64, length: u64) {
+    let mut owners = PA_OWNERS.lock().unwrap_or_else(PoisonError::into_inner);
+    if length == 0 {
+        let _removed = owners.remove(&memid);
+    } else {
+        let _prev = owners.insert(memid, (addr, length));
+    }
+}
+
+/// Free exportable bytes the hooked `obmm_capacity` reports per node, 64 GiB each until set
+static CAPACITY: Mutex<[u64; MAX_NUMA_NODES]> = Mutex::new([64 << 30; MAX_NUMA_NODES]);
+
+/// Free exportable bytes per node of the hooked driver
+pub(crate) fn capacity() -> [u64; MAX_NUMA_NODES] {
+    *CAPACITY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Set the free exportable bytes hooked `exportable_capacity` reports
+/// # Arguments
+/// * `capacity` - Free exportable bytes of each NUMA node
+#[inline]
+pub fn set_hook_capacity(capacity: [u64; MAX_NUMA_NODES]) {
+    *CAPACITY.lock().unwrap_or_else(PoisonError::into_inner) = capacity;
+}
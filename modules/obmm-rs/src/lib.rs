@@ -80,8 +80,93 @@
     clippy::wildcard_enum_match_arm,
 )]
 
+use std::collections::BTreeMap;
 use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use anyhow::Context;
 use bitflags::bitflags;
+use rand::Rng;
+
+mod align;
+mod batch;
+mod c_json;
+mod cancel;
+mod capacity;
+#[cfg(feature = "compress")]
+mod compress;
+mod config;
+#[cfg(feature = "crypto")]
+mod crypto;
+#[cfg(feature = "dlopen")]
+mod dl;
+mod eid;
+mod error;
+mod events;
+mod filename;
+mod flat;
+mod guard;
+mod ha;
+mod health;
+#[cfg(feature = "hook")]
+mod hook;
+#[cfg(all(unix, feature = "mmap"))]
+mod mapped;
+mod memlink_dir;
+mod meta;
+mod metrics;
+mod placement;
+mod preflight;
+mod profile;
+mod progress;
+mod provider;
+mod ratelimit;
+mod registry;
+mod selftest;
+mod stats;
+mod toml_desc;
+mod transfer;
+#[cfg(all(unix, feature = "uds"))]
+mod uds;
+
+pub use align::AlignUpToPage;
+pub use batch::{TimedUnexport, UnexportSummary, mem_import_many, mem_unexport_many, mem_unexport_many_timed};
+pub use cancel::{CancelToken, cancel_in_flight_exports, mem_export_cancellable};
+pub use capacity::{SpreadByCapacity, exportable_capacity};
+pub use config::{ENV_MEMLINK_DIR, ENV_MEMLINK_FLAGS, ENV_MEMLINK_NUMA_NODE, ENV_MEMLINK_SIZE, MemlinkConfig};
+#[cfg(feature = "dlopen")]
+pub use dl::{
+    ENV_OBMM_LIBRARY, obmm_access_stats, obmm_cancel_export, obmm_capacity, obmm_export, obmm_export_acl, obmm_export_cb, obmm_export_useraddr,
+    obmm_grant, obmm_import, obmm_import_at, obmm_pin, obmm_query_memid_by_pa, obmm_query_owner, obmm_query_pa_by_memid, obmm_set_cacheable,
+    obmm_supported_flags, obmm_unexport, obmm_unimport, obmm_unpin,
+};
+pub use eid::Eid;
+pub use error::ObmmError;
+pub use events::{ObmmEvent, SubscriptionId, subscribe, unsubscribe};
+pub use filename::{DEFAULT_FILENAME_PREFIX, MemlinkFilename};
+pub use flat::FlatMemDesc;
+pub use guard::{ExportedMemory, SliceExport, export_each, mem_export_slice};
+pub use ha::{HaExport, mem_export_ha, set_backup_cna};
+pub use health::{HealthStatus, HealthWatchdog, obmm_version};
+#[cfg(feature = "hook")]
+pub use hook::{record_hook_access, set_hook_capacity, set_hook_healthy, set_hook_import_node, set_hook_next_memid, set_hook_pa_owner};
+#[cfg(all(unix, feature = "mmap"))]
+pub use mapped::{FileExport, MappedRegion};
+pub use memlink_dir::{ArchiveCollision, DirComparison, MemlinkDir, mem_export_idempotent};
+pub use meta::MemlinkMeta;
+pub use metrics::{EXPORT_SIZE_BUCKETS, export_size_histogram, export_size_histogram_by_lifetime, imported_bytes_per_node, imported_bytes_unassigned};
+pub use placement::{ImportPlacement, mem_import_placed, mem_import_strict, nearest_node, numa_distance_matrix};
+pub use preflight::set_allow_overcommit;
+pub use profile::ExportProfile;
+pub use progress::mem_export_progress;
+pub use provider::{BufferProvider, mem_export_with};
+pub use ratelimit::{RateLimitMode, set_export_rate_limit, set_export_rate_limit_mode};
+pub use registry::{check_overlaps, find_overlaps, live_memids, lookup_descriptor, obmm_cleanup};
+pub use selftest::{SelftestReport, SelftestStep, run_selftest};
+pub use stats::{AccessStats, mem_access_stats};
+#[cfg(all(unix, feature = "uds"))]
+pub use uds::{DescClient, DescServer};
 use serde::{Serialize, Deserialize};
 
 /// Maximum number of NUMA nodes supported
@@ -92,10 +177,117 @@ pub const OBMM_INVALID_MEMID: u64 = 0;
 pub const OBMM_MAX_LOCAL_NUMA_NODES: usize = 16;
 /// Memory ID type
 pub type MemId = u64;
+/// Per-NUMA-node export lengths, as taken by `mem_export`
+pub type NumaLengths = [usize; MAX_NUMA_NODES];
+/// One mebibyte, for building `NumaLengths`
+pub const MB: usize = 1024 * 1024;
+/// One gibibyte, for building `NumaLengths`
+pub const GB: usize = 1024 * MB;
+
+/// Build a `NumaLengths` with the given nodes set and the rest zero
+///
+/// Node indices must be constant expressions and are checked at compile time,
+/// so a node at or past `MAX_NUMA_NODES` fails to build. A node given twice
+/// keeps its last length.
+///
+/// ```
+/// use obmm_rs::{MB, numa_lengths};
+///
+/// let lengths = numa_lengths! { 1 => 128 * MB, 3 => 64 * MB };
+/// assert_eq!((lengths[1], lengths[3], lengths[0]), (128 * MB, 64 * MB, 0));
+/// ```
+///
+/// ```compile_fail
+/// use obmm_rs::{MB, numa_lengths};
+///
+/// let lengths = numa_lengths! { 16 => 128 * MB };
+/// ```
+#[macro_export]
+macro_rules! numa_lengths {
+    ($($node:expr => $length:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut lengths: $crate::NumaLengths = [0; $crate::MAX_NUMA_NODES];
+        $(
+            const { assert!($node < $crate::MAX_NUMA_NODES, "NUMA node out of range") };
+            if let Some(slot) = lengths.get_mut($node) {
+                *slot = $length;
+            }
+        )*
+        lengths
+    }};
+}
+/// Maximum serialized size of `priv_data`, bounded by the `u16` `priv_len` field
+pub const MAX_PRIV_DATA_LEN: u16 = u16::MAX;
+/// Schema version emitted when serializing descriptors
+pub const OBMM_DESC_SCHEMA_VERSION: u32 = 2;
+/// Alignment assumed for descriptor addresses when `page_size` can't query the OS
+pub const UB_ADDR_ALIGN: u64 = 0x1000;
+/// Alignment of user buffers exported by `obmm_export_useraddr`, the 2MB huge page size
+pub const USERADDR_ALIGN: usize = 2 * MB;
+/// First address of the UB address window (inclusive)
+pub const UB_ADDR_WINDOW_START: u64 = 0xffff_fc00_0000;
+/// End of the UB address window (exclusive)
+pub const UB_ADDR_WINDOW_END: u64 = 0x1_0000_0000_0000;
+/// Pointer tag bits of `addr`, the top byte ignored by aarch64 TBI
+#[cfg(target_arch = "aarch64")]
+pub const ADDR_TAG_MASK: u64 = 0xff00_0000_0000_0000;
+/// Pointer tag bits of `addr`, bits 57 to 62 used by x86-64 LAM
+#[cfg(target_arch = "x86_64")]
+pub const ADDR_TAG_MASK: u64 = 0x7e00_0000_0000_0000;
+/// Pointer tag bits of `addr`, none on architectures without pointer tagging
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
+pub const ADDR_TAG_MASK: u64 = 0;
+
+/// Check that the OBMM library can be called
+/// # Errors
+/// `ObmmError::LibraryUnavailable` if it failed to load at runtime
+#[cfg(all(not(feature = "hook"), feature = "dlopen"))]
+fn ffi_ready() -> Result<(), ObmmError> {
+    dl::ensure_loaded()
+}
+
+/// Check that the OBMM library can be called, always true when linked statically
+/// # Errors
+/// Never fails
+#[cfg(all(not(feature = "hook"), not(feature = "dlopen")))]
+#[allow(clippy::unnecessary_wraps)]
+fn ffi_ready() -> Result<(), ObmmError> {
+    Ok(())
+}
+
+/// Page size of the host, which descriptor addresses must be aligned to
+///
+/// Queried from the OS once and cached, falling back to `UB_ADDR_ALIGN` if
+/// the query fails or the platform has no such query.
+#[inline]
+#[must_use]
+pub fn page_size() -> u64 {
+    /// Page size queried on first use
+    static PAGE_SIZE: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+    *PAGE_SIZE.get_or_init(|| query_page_size().unwrap_or(UB_ADDR_ALIGN))
+}
+
+/// Page size reported by `sysconf(_SC_PAGESIZE)`
+#[cfg(unix)]
+fn query_page_size() -> Option<u64> {
+    // SAFETY: sysconf has no preconditions
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(size).ok().filter(|&size| size > 0)
+}
+
+/// Page size query for platforms without `sysconf`
+#[cfg(not(unix))]
+fn query_page_size() -> Option<u64> {
+    None
+}
 
 bitflags! {
     /// Privilege data for UB memory regions
-    #[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+    ///
+    /// JSON and other human-readable formats carry the flag names, e.g.
+    /// `"OCHIP | CACHEABLE"`, while the binary codec carries the raw `u16` bits.
+    /// Both decode to the same flags.
+    #[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
     #[serde(transparent)]
     pub struct UbPrivData: u16 {
         /// Owner Chip ID
@@ -105,29 +297,348 @@ bitflags! {
     }
 }
 
+impl std::str::FromStr for UbPrivData {
+    type Err = ObmmError;
+
+    /// Parse `|`-separated flag names such as `"OCHIP | CACHEABLE"`
+    ///
+    /// Names are trimmed and matched case-insensitively, `0x` hex literals are
+    /// accepted for raw bits and the empty string yields empty flags.
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = UbPrivData::empty();
+        if s.trim().is_empty() {
+            return Ok(flags);
+        }
+        let mut unknown = Vec::new();
+        for name in s.split('|').map(str::trim) {
+            let parsed = match name.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16).ok().map(UbPrivData::from_bits_retain),
+                None => UbPrivData::all()
+                    .iter_names()
+                    .find(|&(flag_name, _)| flag_name.eq_ignore_ascii_case(name))
+                    .map(|(_, flag)| flag),
+            };
+            match parsed {
+                Some(flag) => flags.insert(flag),
+                None => unknown.push(name),
+            }
+        }
+        if unknown.is_empty() {
+            Ok(flags)
+        } else {
+            Err(ObmmError::UnknownFlags(unknown.join(", ")))
+        }
+    }
+}
+
+impl std::fmt::Display for UbPrivData {
+    /// Format as `|`-separated flag names, the syntax accepted by `from_str`
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
 bitflags! {
     /// Export flags for memory exporting
-    #[derive(Default, Debug)]
+    #[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(transparent)]
     pub struct ObmmExportFlags: u64 {
         /// Allow memory mapping
         const ALLOWMMAP = 1 << 0;
         /// Export to remote NUMA nodes
         const REMOTENUMA = 1 << 1;
+        /// Importers may only map the region read-only
+        const READONLY = 1 << 2;
+    }
+}
+
+bitflags! {
+    /// Import flags for memory importing, mirroring `OBMM_IMPORT_FLAG_*`
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ObmmImportFlags: u64 {
+        /// Allow memory mapping
+        const ALLOWMMAP = 1 << 0;
+        /// Region was preimported
+        const PREIMPORT = 1 << 1;
+        /// Import as a remote NUMA node
+        const REMOTENUMA = 1 << 2;
+    }
+}
+
+impl std::str::FromStr for ObmmImportFlags {
+    type Err = ObmmError;
+
+    /// Parse `|`-separated flag names such as `"ALLOWMMAP | REMOTENUMA"`
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        bitflags::parser::from_str(s).map_err(|e| ObmmError::UnknownFlags(e.to_string()))
+    }
+}
+
+/// Add a `flag_names` iterator to bitflags types
+macro_rules! impl_flag_names {
+    ($($flags:ty),+) => {$(
+        impl $flags {
+            /// Names of the set flags in declaration order, without allocating
+            ///
+            /// Bits with no defined name are skipped. Unlike the generated
+            /// `iter_names`, only the names are yielded.
+            #[inline]
+            pub fn flag_names(&self) -> impl Iterator<Item = &'static str> {
+                self.iter_names().map(|(name, _)| name)
+            }
+        }
+    )+};
+}
+
+impl_flag_names!(UbPrivData, ObmmExportFlags, ObmmImportFlags, ObmmUnexportFlags, ObmmUnimportFlags);
+
+/// Add a `from_bits_validated` constructor to bitflags types
+macro_rules! impl_from_bits_validated {
+    ($($flags:ty: $bits:ty),+) => {$(
+        impl $flags {
+            /// Convert raw bits from storage or a peer, rejecting undefined bits
+            ///
+            /// Unlike `from_bits_truncate`, bits this version doesn't define
+            /// are reported instead of silently dropped.
+            /// # Arguments
+            /// * `bits` - Raw flag bits
+            /// # Returns
+            /// # Errors
+            /// Flags on success, `ObmmError::UnknownFlags` with the undefined bits in hex otherwise
+            #[inline]
+            pub fn from_bits_validated(bits: $bits) -> Result<Self, ObmmError> {
+                Self::from_bits(bits).ok_or_else(|| ObmmError::UnknownFlags(format!("{:#x}", bits & !Self::all().bits())))
+            }
+        }
+    )+};
+}
+
+impl_from_bits_validated!(UbPrivData: u16, ObmmExportFlags: u64, ObmmImportFlags: u64, ObmmUnexportFlags: u64, ObmmUnimportFlags: u64);
+
+/// Export flags supported by the OBMM driver
+/// # Returns
+/// # Errors
+/// Every defined flag, as the hooked driver supports them all
+#[cfg(feature = "hook")]
+#[inline]
+pub fn supported_export_flags() -> Result<ObmmExportFlags, ObmmError> {
+    // hooked implementation
+    Ok(ObmmExportFlags::all())
+}
+
+/// Export flags supported by the OBMM driver
+/// # Returns
+/// # Errors
+/// Capability mask reported by the driver, which may include bits this crate doesn't define,
+/// `ObmmError::LibraryUnavailable` if the OBMM library failed to load
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn supported_export_flags() -> Result<ObmmExportFlags, ObmmError> {
+    ffi_ready()?;
+    Ok(ObmmExportFlags::from_bits_retain(unsafe { obmm_supported_flags() }))
+}
+
+/// Reject export flags the driver doesn't support, naming the offending bits
+fn check_supported_export_flags(flags: ObmmExportFlags) -> Result<(), ObmmError> {
+    let unsupported = flags.difference(supported_export_flags()?);
+    if unsupported.is_empty() {
+        return Ok(());
+    }
+    let mut names: Vec<String> = unsupported.flag_names().map(str::to_owned).collect();
+    let undefined = unsupported.bits() & !ObmmExportFlags::all().bits();
+    if undefined != 0 {
+        names.push(format!("{undefined:#x}"));
+    }
+    Err(ObmmError::UnsupportedFlags(names.join(", ")))
+}
+
+/// Check that import flags are compatible with how a region was exported
+///
+/// | Import flag  | Requires export flag |
+/// |--------------|----------------------|
+/// | `ALLOWMMAP`  | `ALLOWMMAP`          |
+/// | `REMOTENUMA` | `REMOTENUMA`         |
+/// | `PREIMPORT`  | none                 |
+///
+/// An empty `export` set means the flags are unknown, as for descriptors of
+/// schema version 1, and is accepted with any import flags. Bits neither
+/// side defines are rejected, as `export` comes from the peer's descriptor.
+/// # Arguments
+/// * `export` - Flags the region was exported with
+/// * `import` - Flags requested for the import
+/// # Returns
+/// # Errors
+/// Ok(()) if compatible, `ObmmError::UnknownFlags` on undefined bits,
+/// `ObmmError::IncompatibleFlags` otherwise
+#[inline]
+pub fn flags_compatible(export: ObmmExportFlags, import: ObmmImportFlags) -> Result<(), ObmmError> {
+    let _export = ObmmExportFlags::from_bits_validated(export.bits())?;
+    let _import = ObmmImportFlags::from_bits_validated(import.bits())?;
+    if export.is_empty() {
+        return Ok(());
+    }
+    if import.contains(ObmmImportFlags::ALLOWMMAP) && !export.contains(ObmmExportFlags::ALLOWMMAP) {
+        return Err(ObmmError::IncompatibleFlags("ALLOWMMAP import of a region exported without ALLOWMMAP".to_owned()));
+    }
+    if import.contains(ObmmImportFlags::REMOTENUMA) && !export.contains(ObmmExportFlags::REMOTENUMA) {
+        return Err(ObmmError::IncompatibleFlags("REMOTENUMA import of a region exported without REMOTENUMA".to_owned()));
     }
+    Ok(())
 }
 
 bitflags! {
     /// Unexport flags for memory unexporting
-    #[derive(Default, Debug)]
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct ObmmUnexportFlags: u64 {
         /// Force unexport
         const FORCE = 1 << 0;
     }
 }
 
-/// Memory descriptor structure
+bitflags! {
+    /// Unimport flags for memory unimporting
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ObmmUnimportFlags: u64 {
+        /// Force unimport
+        const FORCE = 1 << 0;
+    }
+}
+
+/// Keeps `FORCE`, the only bit shared by both sides, and drops every other bit
+impl From<ObmmUnexportFlags> for ObmmUnimportFlags {
+    #[inline]
+    fn from(flags: ObmmUnexportFlags) -> Self {
+        if flags.contains(ObmmUnexportFlags::FORCE) {
+            ObmmUnimportFlags::FORCE
+        } else {
+            ObmmUnimportFlags::empty()
+        }
+    }
+}
+
+/// Keeps `FORCE`, the only bit shared by both sides, and drops every other bit
+impl From<ObmmUnimportFlags> for ObmmUnexportFlags {
+    #[inline]
+    fn from(flags: ObmmUnimportFlags) -> Self {
+        if flags.contains(ObmmUnimportFlags::FORCE) {
+            ObmmUnexportFlags::FORCE
+        } else {
+            ObmmUnexportFlags::empty()
+        }
+    }
+}
+
+/// How long an export is meant to live, for schedulers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum LifetimeClass {
+    /// Short-lived scratch memory
+    Ephemeral,
+    /// Lives as long as the exporting session, the default
+    #[default]
+    Session,
+    /// Long-lived memory shared across sessions
+    Persistent,
+}
+
+/// C `struct obmm_mem_desc`, the form descriptors take across the FFI
+///
+/// Byte-identical to the C header for a `priv_data` of alignment at most 2,
+/// such as `UbPrivData`, which then starts where the flexible `priv[]` array
+/// does. Only the fields the driver sees live here, `ObmmMemDesc` adds the
+/// Rust-side metadata and converts from and to this form.
 #[repr(C)]
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ObmmRawMemDesc<T> {
+    /// Base address of the memory region
+    pub addr: u64,
+    /// Length of the memory region
+    pub length: u64,
+    /// 128bit eid, ordered by little-endian
+    pub seid: [u8; 16],
+    /// 128bit deid, ordered by little-endian
+    pub deid: [u8; 16],
+    /// Token ID
+    pub tokenid: u32,
+    /// Source CNA
+    pub scna: u32,
+    /// Destination CNA
+    pub dcna: u32,
+    /// Length of privilege data
+    pub priv_len: u16,
+    /// Privilege data, in place of `priv[]`
+    pub priv_data: T,
+}
+
+const _: () = {
+    assert!(size_of::<ObmmRawMemDesc<UbPrivData>>() == 64, "ObmmRawMemDesc must match struct obmm_mem_desc");
+    assert!(core::mem::offset_of!(ObmmRawMemDesc<UbPrivData>, priv_data) == 62, "priv_data must start at priv[]");
+};
+
+impl<T: Copy> ObmmRawMemDesc<T> {
+    /// Read a C descriptor from a raw pointer
+    /// # Arguments
+    /// * `ptr` - Pointer to a descriptor owned by another binding
+    /// # Returns
+    /// A copy of the pointed-to descriptor
+    /// # Safety
+    /// `ptr` must be non-null and point to a readable, initialized
+    /// `struct obmm_mem_desc` followed by privilege data that is a valid `T`,
+    /// i.e. `size_of::<ObmmRawMemDesc<T>>()` readable bytes. The pointer does
+    /// not need to be aligned.
+    #[inline]
+    #[must_use]
+    pub unsafe fn from_raw_ptr(ptr: *const c_void) -> Self {
+        // SAFETY: the caller guarantees `ptr` points to a valid `ObmmRawMemDesc<T>`
+        unsafe { ptr.cast::<Self>().read_unaligned() }
+    }
+
+    /// Get a raw C descriptor pointer to this descriptor
+    /// # Returns
+    /// Pointer valid for as long as `self` is borrowed
+    #[inline]
+    #[must_use]
+    pub fn as_raw_ptr(&self) -> *const c_void {
+        core::ptr::from_ref(self).cast::<c_void>()
+    }
+}
+
+impl<T> From<ObmmRawMemDesc<T>> for ObmmMemDesc<T> {
+    /// Descriptor of the current schema version with no Rust-side metadata
+    #[inline]
+    fn from(raw: ObmmRawMemDesc<T>) -> Self {
+        ObmmMemDesc {
+            addr: raw.addr,
+            length: raw.length,
+            seid: raw.seid,
+            deid: raw.deid,
+            tokenid: raw.tokenid,
+            scna: raw.scna,
+            dcna: raw.dcna,
+            priv_len: raw.priv_len,
+            priv_data: raw.priv_data,
+            schema_version: OBMM_DESC_SCHEMA_VERSION,
+            per_node: None,
+            export_flags: ObmmExportFlags::empty(),
+            expires_at: None,
+            lifetime: LifetimeClass::Session,
+        }
+    }
+}
+
+/// Memory descriptor structure
+///
+/// The fields up to and including `priv_data` are those of the C
+/// `obmm_mem_desc`, the rest is Rust-side metadata the driver never sees.
+/// The FFI takes the `ObmmRawMemDesc` form from `to_raw`.
+// `from_raw_ptr` is the only unsafe method and upholds no invariant that
+// deserialization could break.
+#[allow(clippy::unsafe_derive_deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ObmmMemDesc<T> {
     /// Base address of the memory region
@@ -148,8 +659,98 @@ pub struct ObmmMemDesc<T> {
     pub priv_len: u16,
     /// Privilege data
     pub priv_data: T,
+    /// Descriptor schema version, JSON without it is version 1
+    #[serde(default = "schema_version_v1")]
+    pub schema_version: u32,
+    /// Per-NUMA-node breakdown of `length`, added in schema version 2
+    #[serde(default)]
+    pub per_node: Option<[u64; MAX_NUMA_NODES]>,
+    /// Flags the region was exported with, added in schema version 2
+    #[serde(default)]
+    pub export_flags: ObmmExportFlags,
+    /// Time after which the descriptor is stale, set by `to_json_file_with_ttl`
+    #[serde(default)]
+    pub expires_at: Option<SystemTime>,
+    /// Intended lifetime of the export, `Session` in JSON without it
+    #[serde(default)]
+    pub lifetime: LifetimeClass,
+}
+
+/// Schema version assumed for descriptors serialized without one
+fn schema_version_v1() -> u32 {
+    1
+}
+
+impl<T: Default> Default for ObmmMemDesc<T> {
+    #[inline]
+    fn default() -> Self {
+        ObmmMemDesc {
+            addr: 0,
+            length: 0,
+            seid: [0; 16],
+            deid: [0; 16],
+            tokenid: 0,
+            scna: 0,
+            dcna: 0,
+            priv_len: 0,
+            priv_data: T::default(),
+            schema_version: OBMM_DESC_SCHEMA_VERSION,
+            per_node: None,
+            export_flags: ObmmExportFlags::empty(),
+            expires_at: None,
+            lifetime: LifetimeClass::Session,
+        }
+    }
+}
+
+
+impl<T: std::fmt::Debug> std::fmt::Debug for ObmmMemDesc<T> {
+    /// Compact form for logs, with `addr` and `length` in hex and eids
+    /// shortened to their first and last two bytes
+    ///
+    /// The alternate form `{:#?}` prints every field in full, as derived
+    /// `Debug` would.
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let full = f.alternate();
+        let mut out = f.debug_struct("ObmmMemDesc");
+        if full {
+            let _out = out
+                .field("addr", &self.addr)
+                .field("length", &self.length)
+                .field("seid", &self.seid)
+                .field("deid", &self.deid);
+        } else {
+            let _out = out
+                .field("addr", &format_args!("{:#x}", self.addr))
+                .field("length", &format_args!("{:#x}", self.length))
+                .field("seid", &format_args!("{}", ShortEid(&self.seid)))
+                .field("deid", &format_args!("{}", ShortEid(&self.deid)));
+        }
+        out.field("tokenid", &self.tokenid)
+            .field("scna", &self.scna)
+            .field("dcna", &self.dcna)
+            .field("priv_len", &self.priv_len)
+            .field("priv_data", &self.priv_data)
+            .field("schema_version", &self.schema_version)
+            .field("per_node", &self.per_node)
+            .field("export_flags", &self.export_flags)
+            .field("expires_at", &self.expires_at)
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
 }
 
+/// An eid rendered as its first and last two bytes, e.g. `0x0101..0101`
+struct ShortEid<'a>(&'a [u8; 16]);
+
+impl std::fmt::Display for ShortEid<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [first, second] = *self.0.first_chunk::<2>().unwrap_or(&[0; 2]);
+        let [penultimate, last] = *self.0.last_chunk::<2>().unwrap_or(&[0; 2]);
+        write!(f, "0x{first:02x}{second:02x}..{penultimate:02x}{last:02x}")
+    }
+}
 
 impl<T> ObmmMemDesc<T>  
     where
@@ -162,6 +763,93 @@ impl<T> ObmmMemDesc<T>
         ObmmMemDesc::<T>::default()
     }
 
+    /// A realistic descriptor for examples: 128MB on NUMA node 1
+    ///
+    /// Unlike `default`, the result passes `sanity_check` and
+    /// `validate_address`. The eids, token and CNAs stay zero.
+    #[inline]
+    #[must_use]
+    pub fn example() -> Self {
+        const LENGTH: u64 = 128 * 1024 * 1024;
+        let mut per_node = [0; MAX_NUMA_NODES];
+        if let Some(node) = per_node.get_mut(1) {
+            *node = LENGTH;
+        }
+        let mut desc = ObmmMemDesc::<T>::default();
+        desc.addr = 0xffff_fc00_0000;
+        desc.length = LENGTH;
+        desc.per_node = Some(per_node);
+        desc.priv_len = priv_data_len(&desc.priv_data).unwrap_or_default();
+        desc
+    }
+
+    /// Create an `ObmmMemDescBuilder` starting from default values
+    #[inline]
+    pub fn builder() -> ObmmMemDescBuilder<T> {
+        ObmmMemDescBuilder::default()
+    }
+
+    /// Recompute `priv_len` from the binary serialized size of `priv_data`
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` if `priv_data` exceeds `MAX_PRIV_DATA_LEN`
+    #[inline]
+    pub fn recompute_priv_len(&mut self) -> anyhow::Result<()> {
+        self.priv_len = priv_data_len(&self.priv_data)?;
+        Ok(())
+    }
+
+    /// Bring the descriptor to its canonical form
+    ///
+    /// Semantically equal descriptors have identical binary forms once
+    /// canonical, which `fingerprint` and `sign` rely on. In canonical form:
+    /// * `priv_len` matches the serialized `priv_data`, unless it exceeds
+    ///   `MAX_PRIV_DATA_LEN`, in which case it is left as is
+    /// * `schema_version` is `OBMM_DESC_SCHEMA_VERSION`
+    /// * an all-zero `per_node` is `None`
+    ///
+    /// `expires_at` and `lifetime` are kept as they are and covered by the
+    /// fingerprint and MAC, so a changed expiry or lifetime class changes
+    /// both. They have a single binary form each, so there is nothing to
+    /// normalize. The binary codec writes no padding and the descriptor has
+    /// no reserved fields, so there is nothing further to zero.
+    #[inline]
+    pub fn canonicalize(&mut self) {
+        if let Ok(len) = priv_data_len(&self.priv_data) {
+            self.priv_len = len;
+        }
+        self.schema_version = OBMM_DESC_SCHEMA_VERSION;
+        if self.per_node.is_some_and(|per_node| per_node.iter().all(|&len| len == 0)) {
+            self.per_node = None;
+        }
+    }
+
+    /// Binary form of the canonical copy of the descriptor
+    pub(crate) fn canonical_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut canonical: Self = bincode::deserialize(&bincode::serialize(self)?)?;
+        canonical.canonicalize();
+        Ok(bincode::serialize(&canonical)?)
+    }
+
+    /// Stable 64-bit FNV-1a fingerprint of the canonical descriptor
+    ///
+    /// Suitable for deduplication and cache keys, not for authentication;
+    /// use `sign` for that.
+    /// # Returns
+    /// # Errors
+    /// Fingerprint on success, `anyhow::Error` if the descriptor fails to serialize
+    #[inline]
+    pub fn fingerprint(&self) -> anyhow::Result<u64> {
+        /// FNV-1a 64-bit offset basis
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        /// FNV-1a 64-bit prime
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+        let bytes = self.canonical_bytes()?;
+        Ok(bytes
+            .iter()
+            .fold(FNV_OFFSET, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)))
+    }
+
     /// Deserialize the `ObmmMemDesc` from json format
     /// # Arguments
     /// * `json_str` - JSON string representation
@@ -174,6 +862,52 @@ impl<T> ObmmMemDesc<T>
         Ok(desc)
     }
 
+    /// Deserialize the `ObmmMemDesc` from the JSON schema of the C OBMM tools
+    ///
+    /// The C schema carries eids as hex strings and flags and privilege data
+    /// as integers, see the `c_json` module for the accepted fields.
+    /// `from_json` stays strict and rejects it.
+    /// # Arguments
+    /// * `json_str` - JSON string printed by a C tool
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_c_json(json_str: &str) -> anyhow::Result<Self> {
+        serde_json::from_str::<c_json::CJsonDesc>(json_str)?.into_desc()
+    }
+
+    /// Deserialize the `ObmmMemDesc` from json format of any known schema version
+    ///
+    /// Version 1 descriptors carry no per-node breakdown, export flags or expiry, so fields added in
+    /// later versions are left at their defaults. The result is upgraded to
+    /// `OBMM_DESC_SCHEMA_VERSION`.
+    /// # Arguments
+    /// * `json_str` - JSON string representation
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure or an unknown schema version
+    #[inline]
+    pub fn from_json_versioned(json_str: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json_str)?;
+        let version = match value.get("schema_version") {
+            None => schema_version_v1(),
+            Some(version) => serde_json::from_value(version.clone())?,
+        };
+        let mut desc: ObmmMemDesc<T> = serde_json::from_value(value)?;
+        match version {
+            1 => {
+                desc.per_node = None;
+                desc.export_flags = ObmmExportFlags::empty();
+                desc.expires_at = None;
+            }
+            2 => (),
+            _ => anyhow::bail!("Unsupported descriptor schema version {version}"),
+        }
+        desc.schema_version = OBMM_DESC_SCHEMA_VERSION;
+        Ok(desc)
+    }
+
     /// Serialize the `ObmmMemDesc` to json format
     /// # Returns
     /// # Errors
@@ -184,317 +918,3122 @@ impl<T> ObmmMemDesc<T>
         Ok(json_str)
     }
 
-    /// Read the `ObmmMemDesc` from a json file
+    /// Serialize the `ObmmMemDesc` to json format into a reusable buffer
     /// # Arguments
-    /// * `mem_id` - Memory ID
+    /// * `buf` - Buffer cleared and overwritten with the JSON string
     /// # Returns
     /// # Errors
-    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
+    /// Ok(()) on success, `anyhow::Error` on failure
     #[inline]
-    pub fn from_json_file(mem_id: MemId) -> anyhow::Result<Self> {
-        let file_path = format!("/tmp/memlink/memdesc_{mem_id}.json");
-        let json_str = std::fs::read_to_string(file_path)?;
-        let desc: ObmmMemDesc<T> = serde_json::from_str(&json_str)?;
-        Ok(desc)
+    pub fn to_json_into(&self, buf: &mut String) -> anyhow::Result<()> {
+        let mut bytes = std::mem::take(buf).into_bytes();
+        bytes.clear();
+        serde_json::to_writer(&mut bytes, self)?;
+        *buf = String::from_utf8(bytes)?;
+        Ok(())
     }
 
-    /// Write the `ObmmMemDesc` to a json file
+    /// Deserialize the `ObmmMemDesc` from TOML format
+    ///
+    /// `seid` and `deid` are hex strings of 32 digits in byte order.
     /// # Arguments
-    /// * `mem_id` - Memory ID
+    /// * `toml_str` - TOML string representation
     /// # Returns
     /// # Errors
-    /// Ok(()) on success, `anyhow::Error` on failure
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
     #[inline]
-    pub fn to_json_file(&self, mem_id: MemId) -> anyhow::Result<()> {
-        let file_path = format!("/tmp/memlink/memdesc_{mem_id}.json");
-        let json_str = serde_json::to_string_pretty(self)?;
-        std::fs::write(file_path, json_str)?;
-        Ok(())
+    pub fn from_toml(toml_str: &str) -> anyhow::Result<Self> {
+        let desc: toml_desc::TomlDesc<T> = toml::from_str(toml_str)?;
+        Ok(desc.into())
     }
-}
 
-/// Export memory region
-/// # Arguments
-/// * `length` - Array of lengths for each NUMA node
-/// * `flags` - Export flags
-/// # Returns
-/// # Errors
-/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
-#[cfg(feature = "hook")]
-#[inline]
-pub fn mem_export<T: Default>(length: &[usize], _: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
-    let mut desc = ObmmMemDesc::<T>::default();
-    // hooked implementation
-    let memid = 1;
-    desc.addr = 0xffff_fc00_0000;
-    desc.length = length.iter().sum::<usize>().try_into()?;
-    if memid == OBMM_INVALID_MEMID {
-        Err(anyhow::anyhow!("Failed to export memory"))
-    } else {
-        Ok((memid, desc))
+    /// Serialize the `ObmmMemDesc` to TOML format
+    ///
+    /// JSON stays the default interchange format; TOML suits hand-edited
+    /// fixtures, with `seid` and `deid` rendered as hex strings.
+    /// # Returns
+    /// # Errors
+    /// TOML string representation on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(&toml_desc::TomlDesc::from(self))?)
     }
-}
 
-/// Export memory region
+    /// Serialize only the fields of the `ObmmMemDesc` meaningful to a remote peer
+    ///
+    /// Keeps `addr`, `length`, `seid`, `deid`, `tokenid`, `scna`, `dcna` and
+    /// `priv_data`, everything `mem_import` hands to the driver. The local-only
+    /// fields, see the `transfer` module, are left out.
+    /// # Returns
+    /// # Errors
+    /// JSON string on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_transfer_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(&transfer::TransferDesc::from(self))?)
+    }
+
+    /// Deserialize an `ObmmMemDesc` from the form written by `to_transfer_json`
+    ///
+    /// Local-only fields take their defaults and `priv_len` is recomputed from
+    /// the transferred privilege data, so the descriptor can be passed to
+    /// `mem_import` as is. As `export_flags` is empty, imports with flags that
+    /// require a matching export flag, such as `ALLOWMMAP`, are rejected by
+    /// `flags_compatible`.
+    /// # Arguments
+    /// * `json_str` - Transfer JSON string
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_transfer_json(json_str: &str) -> anyhow::Result<Self>
+    where
+        T: Default + Serialize,
+    {
+        let transferred: transfer::TransferDesc<T> = serde_json::from_str(json_str)?;
+        let mut desc = ObmmMemDesc::default();
+        transferred.apply_to(&mut desc);
+        desc.priv_len = priv_data_len(&desc.priv_data)?;
+        Ok(desc)
+    }
+
+    /// Deserialize the `ObmmMemDesc` from binary format
+    ///
+    /// Safe on bytes from untrusted peers: malformed input of any kind is an
+    /// error, never a panic or an unbounded allocation. Unknown export flag
+    /// bits and a `priv_len` not matching `priv_data` are rejected.
+    /// # Arguments
+    /// * `bytes` - Binary representation
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(decode_bytes(bytes)?)
+    }
+
+    /// Serialize the `ObmmMemDesc` to binary format
+    /// # Returns
+    /// # Errors
+    /// Binary representation on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = bincode::serialize(self)?;
+        Ok(bytes)
+    }
+
+    /// Serialize the `ObmmMemDesc` to binary format into a reusable buffer
+    /// # Arguments
+    /// * `buf` - Buffer cleared and overwritten with the binary representation
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_bytes_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+        buf.clear();
+        bincode::serialize_into(&mut *buf, self)?;
+        Ok(())
+    }
+
+    /// Read the `ObmmMemDesc` from a json file
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_json_file(mem_id: MemId) -> anyhow::Result<Self> {
+        let file_path = desc_file_path(Path::new(DEFAULT_MEMLINK_DIR), mem_id);
+        let json_str = std::fs::read_to_string(file_path)?;
+        let desc: ObmmMemDesc<T> = serde_json::from_str(&json_str)?;
+        Ok(desc)
+    }
+
+    /// Write the `ObmmMemDesc` to a json file
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_json_file(&self, mem_id: MemId) -> anyhow::Result<()> {
+        let file_path = desc_file_path(Path::new(DEFAULT_MEMLINK_DIR), mem_id);
+        let json_str = serde_json::to_string_pretty(self)?;
+        write_file_atomic(&file_path, json_str.as_bytes())
+    }
+
+    /// Write the `ObmmMemDesc` to a json file, stale after `ttl`
+    ///
+    /// The written descriptor carries `expires_at` set to now plus `ttl`,
+    /// `self` is left unchanged.
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// * `ttl` - Time until the descriptor is stale
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_json_file_with_ttl(&self, dir: &Path, mem_id: MemId, ttl: Duration) -> anyhow::Result<()> {
+        let expires_at = SystemTime::now().checked_add(ttl).context("Descriptor TTL overflows")?;
+        let mut value = serde_json::to_value(self)?;
+        if let Some(fields) = value.as_object_mut() {
+            let _prev = fields.insert("expires_at".to_owned(), serde_json::to_value(expires_at)?);
+        }
+        write_file_atomic(&desc_file_path(dir, mem_id), serde_json::to_string_pretty(&value)?.as_bytes())
+    }
+
+    /// Write the `ObmmMemDesc` to a json file if it still holds `expected`
+    ///
+    /// The comparison and the replacement happen under an exclusive lock on
+    /// `memdesc_<id>.json.lock`, so concurrent writers using this method never
+    /// overwrite each other's changes unseen. The lock file is removed again
+    /// before the lock is released.
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// * `expected` - Descriptor the file must currently hold, `None` if it must not exist
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `ObmmError::Conflict` if the file does not match
+    /// `expected`, `anyhow::Error` on other failures
+    #[inline]
+    pub fn to_json_file_cas(&self, dir: &Path, mem_id: MemId, expected: Option<&Self>) -> anyhow::Result<()>
+    where
+        T: PartialEq,
+    {
+        let file_path = desc_file_path(dir, mem_id);
+        let lock_path = file_path.with_extension("json.lock");
+        let _lock = lock_exclusive(&lock_path)?;
+        let written = (|| {
+            let current = match std::fs::read_to_string(&file_path) {
+                Ok(json_str) => Some(serde_json::from_str::<Self>(&json_str)?),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err.into()),
+            };
+            if current.as_ref() != expected {
+                return Err(ObmmError::Conflict(mem_id).into());
+            }
+            write_file_atomic(&file_path, serde_json::to_string_pretty(self)?.as_bytes())
+        })();
+        // still locked, so writers waiting on this lock file notice it is gone and retry
+        std::fs::remove_file(&lock_path)?;
+        written
+    }
+
+    /// Read the `ObmmMemDesc` from a json file, rejecting stale descriptors
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `ObmmError::Expired` if past its `expires_at`,
+    /// `anyhow::Error` on other failures
+    #[inline]
+    pub fn from_json_file_checked(dir: &Path, mem_id: MemId) -> anyhow::Result<Self> {
+        let json_str = std::fs::read_to_string(desc_file_path(dir, mem_id))?;
+        let desc: ObmmMemDesc<T> = serde_json::from_str(&json_str)?;
+        if desc.expires_at.is_some_and(|expires_at| SystemTime::now() >= expires_at) {
+            return Err(ObmmError::Expired(mem_id).into());
+        }
+        Ok(desc)
+    }
+
+    /// Re-read the on-disk descriptor and update `self` in place
+    ///
+    /// Picks up a re-export by the peer after a restart, which typically moves
+    /// `addr`. Stale descriptors are rejected as by `from_json_file_checked`.
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// Whether any field changed on success, `anyhow::Error` on failure, leaving `self` untouched
+    #[inline]
+    pub fn refresh(&mut self, dir: &Path, mem_id: MemId) -> anyhow::Result<bool>
+    where
+        T: PartialEq,
+    {
+        let fresh = Self::from_json_file_checked(dir, mem_id)?;
+        let changed = !self.same_region(&fresh) || !self.diff(&fresh).is_empty();
+        *self = fresh;
+        Ok(changed)
+    }
+}
+
+/// Serialize many descriptors into one JSON object keyed by Memory ID
 /// # Arguments
-/// * `length` - Array of lengths for each NUMA node
-/// * `flags` - Export flags
+/// * `descs` - Memory IDs and their descriptors, in any order
 /// # Returns
-/// Tuple of Memory ID and Memory Descriptor on success, anyhow::Error on failure
-#[cfg(not(feature = "hook"))]
-pub fn mem_export<T: Default>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
-    let mut desc = ObmmMemDesc::<T>::default();
-    let memid = unsafe {
-        obmm_export(
-            length.as_ptr(),
-            flags.bits(),
-            &mut desc as *mut ObmmMemDesc<T> as *mut c_void,
-        )
-    };
-    if memid == OBMM_INVALID_MEMID {
-        Err(anyhow::anyhow!("Failed to export memory"))
-    } else {
-        Ok((memid, desc))
+/// # Errors
+/// JSON string with the descriptors sorted by Memory ID on success,
+/// `anyhow::Error` on a duplicate Memory ID or failure
+#[inline]
+pub fn descs_to_json<T: Serialize>(descs: &[(MemId, ObmmMemDesc<T>)]) -> anyhow::Result<String> {
+    let mut by_id = BTreeMap::new();
+    for &(memid, ref desc) in descs {
+        if by_id.insert(memid, desc).is_some() {
+            anyhow::bail!("Duplicate MemID {memid} in descriptor document");
+        }
     }
+    Ok(serde_json::to_string(&by_id)?)
 }
 
-/// Unexport memory region
+/// Deserialize a JSON object of descriptors written by `descs_to_json`
 /// # Arguments
-/// * `memid` - Memory ID to unexport
-/// * `flags` - Unexport flags
+/// * `json_str` - JSON object keyed by Memory ID string
 /// # Returns
-/// Ok(()) on success, Err(i32) on failure
 /// # Errors
-#[cfg(feature = "hook")]
+/// Memory IDs and descriptors sorted by Memory ID on success, `anyhow::Error` on failure
 #[inline]
-pub fn mem_unexport(_: MemId, _: ObmmUnexportFlags) -> Result<(), i32> {
-    // hooked implementation
+pub fn descs_from_json<T: for<'de> Deserialize<'de>>(json_str: &str) -> anyhow::Result<Vec<(MemId, ObmmMemDesc<T>)>> {
+    let by_id: BTreeMap<MemId, ObmmMemDesc<T>> = serde_json::from_str(json_str)?;
+    Ok(by_id.into_iter().collect())
+}
+
+/// Directory descriptor files are read from and written to by default
+const DEFAULT_MEMLINK_DIR: &str = "/tmp/memlink";
+
+/// Path of the descriptor file of `mem_id` in `dir`
+fn desc_file_path(dir: &Path, mem_id: MemId) -> PathBuf {
+    dir.join(MemlinkFilename::new(mem_id, "json").to_string())
+}
+
+/// Create and exclusively lock the lock file at `path`
+///
+/// Holders remove the lock file before unlocking it, so a file that was
+/// removed while waiting for the lock is stale: the lock is then retried on
+/// a fresh file.
+fn lock_exclusive(path: &Path) -> anyhow::Result<std::fs::File> {
+    loop {
+        let lock = std::fs::File::create(path)?;
+        lock.lock()?;
+        if is_same_file(&lock, path)? {
+            return Ok(lock);
+        }
+    }
+}
+
+/// Whether `file` is still the file at `path`
+#[cfg(unix)]
+fn is_same_file(file: &std::fs::File, path: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::MetadataExt as _;
+    let opened = file.metadata()?;
+    match std::fs::metadata(path) {
+        Ok(current) => Ok(opened.dev() == current.dev() && opened.ino() == current.ino()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether `file` is still the file at `path`, always true where open files can't be removed
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+fn is_same_file(_: &std::fs::File, _: &Path) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+/// Replace `path` with `contents` so readers see either the old or the new file
+///
+/// The contents go to a temporary file in the same directory first, which is
+/// then renamed over `path`.
+fn write_file_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    /// Distinguishes temporary files of concurrent writers in one process
+    static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+    let tmp_name = format!(
+        ".{}.{}.{}.tmp",
+        path.file_name().and_then(|name| name.to_str()).unwrap_or("memdesc"),
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    );
+    let tmp_path = path.with_file_name(tmp_name);
+    std::fs::write(&tmp_path, contents)?;
+    if let Err(err) = std::fs::rename(&tmp_path, path) {
+        let _result = std::fs::remove_file(&tmp_path);
+        return Err(err.into());
+    }
     Ok(())
 }
 
-/// Unexport memory region
+/// Upper bound on the binary size of a descriptor, capping decoder allocations
+const MAX_DESC_BYTES: u64 = 128 * 1024;
+
+/// Decode a binary descriptor from an untrusted source
+///
+/// Lengths inside the encoding are bounded by `MAX_DESC_BYTES`, so a forged
+/// length prefix fails instead of allocating.
 /// # Arguments
-/// * `memid` - Memory ID to unexport
-/// * `flags` - Unexport flags
+/// * `bytes` - Binary representation, trailing bytes are ignored
 /// # Returns
-/// Ok(()) on success, Err(i32) on failure
-#[cfg(not(feature = "hook"))]
-pub fn mem_unexport(memid: MemId, flags: ObmmUnexportFlags) -> Result<(), i32> {
-    let ret = unsafe { obmm_unexport(memid, flags.bits()) };
-    if ret == 0 {
-        Ok(())
-    } else {
-        Err(ret)
-    }
+/// # Errors
+/// `ObmmMemDesc` on success, `ObmmError` on malformed input
+fn decode_bytes<T: Serialize + for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<ObmmMemDesc<T>, ObmmError> {
+    use bincode::Options as _;
+    let desc: ObmmMemDesc<T> = bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_DESC_BYTES)
+        .deserialize(bytes)
+        .map_err(|e| ObmmError::Codec(e.to_string()))?;
+    let _flags = ObmmExportFlags::from_bits_validated(desc.export_flags.bits())?;
+    desc.validate_priv_len()?;
+    Ok(desc)
 }
 
-/// Import memory region
+/// Binary serialized size of a `priv_data` payload
 /// # Arguments
-/// * `desc` - Memory Descriptor from remote
-/// * `flags` - Import flags
-/// * `base_dist` - Base distribution hint
+/// * `priv_data` - Privilege data to measure
 /// # Returns
 /// # Errors
-/// Tuple of Memory ID and NUMA node on success, Err(i32) on failure
-#[cfg(feature = "hook")]
-#[inline]
-pub fn mem_import(
-    _: &ObmmMemDesc<UbPrivData>,
-    _: ObmmExportFlags,
-    _: i32,
-) -> Result<(MemId, i32), i32> {
-    // hooked implementation
-    let memid = 1;
-    let numa = 0;
-    if memid == OBMM_INVALID_MEMID {
-        Err(-1)
-    } else {
-        Ok((memid, numa))
+/// Size in bytes on success, `anyhow::Error` if it exceeds `MAX_PRIV_DATA_LEN`
+fn priv_data_len<T: Serialize>(priv_data: &T) -> anyhow::Result<u16> {
+    let size = bincode::serialized_size(priv_data)?;
+    u16::try_from(size).with_context(|| {
+        format!("priv_data serializes to {size} bytes, exceeding MAX_PRIV_DATA_LEN ({MAX_PRIV_DATA_LEN})")
+    })
+}
+
+/// Builder for `ObmmMemDesc`
+#[derive(Default, Debug, Clone)]
+#[must_use]
+pub struct ObmmMemDescBuilder<T> {
+    /// Descriptor under construction
+    desc: ObmmMemDesc<T>,
+}
+
+impl<T> ObmmMemDescBuilder<T>
+    where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    /// Set the base address of the memory region
+    #[inline]
+    pub fn addr(mut self, addr: u64) -> Self {
+        self.desc.addr = addr;
+        self
+    }
+
+    /// Set the length of the memory region
+    #[inline]
+    pub fn length(mut self, length: u64) -> Self {
+        self.desc.length = length;
+        self
+    }
+
+    /// Set the 128bit seid, ordered by little-endian
+    #[inline]
+    pub fn seid(mut self, seid: [u8; 16]) -> Self {
+        self.desc.seid = seid;
+        self
+    }
+
+    /// Set the 128bit deid, ordered by little-endian
+    #[inline]
+    pub fn deid(mut self, deid: [u8; 16]) -> Self {
+        self.desc.deid = deid;
+        self
+    }
+
+    /// Set the token ID
+    #[inline]
+    pub fn tokenid(mut self, tokenid: u32) -> Self {
+        self.desc.tokenid = tokenid;
+        self
+    }
+
+    /// Set the source CNA
+    #[inline]
+    pub fn scna(mut self, scna: u32) -> Self {
+        self.desc.scna = scna;
+        self
+    }
+
+    /// Set the destination CNA
+    #[inline]
+    pub fn dcna(mut self, dcna: u32) -> Self {
+        self.desc.dcna = dcna;
+        self
+    }
+
+    /// Set the per-NUMA-node breakdown of the length
+    #[inline]
+    pub fn per_node(mut self, per_node: [u64; MAX_NUMA_NODES]) -> Self {
+        self.desc.per_node = Some(per_node);
+        self
+    }
+
+    /// Set the flags the region was exported with
+    #[inline]
+    pub fn export_flags(mut self, export_flags: ObmmExportFlags) -> Self {
+        self.desc.export_flags = export_flags;
+        self
+    }
+
+    /// Set the intended lifetime of the export
+    #[inline]
+    pub fn lifetime(mut self, lifetime: LifetimeClass) -> Self {
+        self.desc.lifetime = lifetime;
+        self
+    }
+
+    /// Set the privilege data
+    #[inline]
+    pub fn priv_data(mut self, priv_data: T) -> Self {
+        self.desc.priv_data = priv_data;
+        self
+    }
+
+    /// Build the `ObmmMemDesc`, computing `priv_len` from `priv_data`
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` if `priv_data` exceeds
+    /// `MAX_PRIV_DATA_LEN` or `check_length_consistency` fails
+    #[inline]
+    pub fn build(mut self) -> anyhow::Result<ObmmMemDesc<T>> {
+        self.desc.check_length_consistency()?;
+        self.desc.recompute_priv_len()?;
+        Ok(self.desc)
     }
 }
 
-/// Import memory region
-/// # Arguments
-/// * `desc` - Memory Descriptor from remote
-/// * `flags` - Import flags
-/// * `base_dist` - Base distribution hint
-/// # Returns
-/// Tuple of Memory ID and NUMA node on success, Err(i32) on failure
-#[cfg(not(feature = "hook"))]
-pub fn mem_import(
-    desc: &ObmmMemDesc<UbPrivData>,
-    flags: ObmmExportFlags,
-    base_dist: i32,
-) -> Result<(MemId, i32), i32> {
-    let mut numa: i32 = -1;
-    let memid = unsafe {
-        obmm_import(
-            desc as *const ObmmMemDesc<UbPrivData> as *const c_void,
-            flags.bits(),
-            base_dist,
-            &mut numa as *mut i32,
-        )
-    };
-    if memid == OBMM_INVALID_MEMID {
-        Err(-1)
-    } else {
-        Ok((memid, numa))
+impl<T> ObmmMemDesc<T> {
+    /// Whether `other` describes the same memory region as `self`
+    ///
+    /// Unlike `PartialEq`, which compares every field, only `addr`, `length`,
+    /// `seid` and `deid` are compared. `tokenid`, `scna`, `dcna` and the
+    /// remaining fields may legitimately differ between a locally stored
+    /// descriptor and one received from a peer for the same region.
+    #[inline]
+    #[must_use]
+    pub fn same_region(&self, other: &Self) -> bool {
+        self.addr == other.addr
+            && self.length == other.length
+            && self.seid() == other.seid()
+            && self.deid() == other.deid()
+    }
+
+    /// `seid` as an `Eid`, decoded little-endian
+    #[inline]
+    #[must_use]
+    pub fn seid(&self) -> Eid {
+        Eid::from_le_bytes(self.seid)
+    }
+
+    /// `deid` as an `Eid`, decoded little-endian
+    #[inline]
+    #[must_use]
+    pub fn deid(&self) -> Eid {
+        Eid::from_le_bytes(self.deid)
+    }
+
+    /// `seid` as an integer, decoded little-endian
+    #[inline]
+    #[must_use]
+    pub fn seid_u128(&self) -> u128 {
+        self.seid().get()
+    }
+
+    /// Set `seid` from an integer, encoded little-endian
+    /// # Arguments
+    /// * `seid` - 128bit eid
+    #[inline]
+    pub fn set_seid(&mut self, seid: u128) {
+        self.seid = Eid::new(seid).to_le_bytes();
+    }
+
+    /// `deid` as an integer, decoded little-endian
+    #[inline]
+    #[must_use]
+    pub fn deid_u128(&self) -> u128 {
+        self.deid().get()
+    }
+
+    /// Set `deid` from an integer, encoded little-endian
+    /// # Arguments
+    /// * `deid` - 128bit deid
+    #[inline]
+    pub fn set_deid(&mut self, deid: u128) {
+        self.deid = Eid::new(deid).to_le_bytes();
+    }
+
+    /// Move the C fields out for the driver to fill, leaving a default `priv_data`
+    #[cfg(not(feature = "hook"))]
+    fn take_raw(&mut self) -> ObmmRawMemDesc<T>
+    where
+        T: Default,
+    {
+        ObmmRawMemDesc {
+            addr: self.addr,
+            length: self.length,
+            seid: self.seid,
+            deid: self.deid,
+            tokenid: self.tokenid,
+            scna: self.scna,
+            dcna: self.dcna,
+            priv_len: self.priv_len,
+            priv_data: core::mem::take(&mut self.priv_data),
+        }
+    }
+
+    /// Replace the C fields with those filled by the driver, keeping the metadata
+    #[cfg(not(feature = "hook"))]
+    fn set_raw(&mut self, raw: ObmmRawMemDesc<T>) {
+        self.addr = raw.addr;
+        self.length = raw.length;
+        self.seid = raw.seid;
+        self.deid = raw.deid;
+        self.tokenid = raw.tokenid;
+        self.scna = raw.scna;
+        self.dcna = raw.dcna;
+        self.priv_len = raw.priv_len;
+        self.priv_data = raw.priv_data;
+    }
+
+    /// `priv_data` in its binary serialized form, the `priv_len` bytes the driver sees
+    ///
+    /// Works for any `T`, e.g. to log the privilege data of a descriptor whose
+    /// `priv_data` type isn't known.
+    /// # Returns
+    /// # Errors
+    /// Serialized bytes on success, `anyhow::Error` if `priv_data` can't be serialized
+    #[inline]
+    pub fn priv_data_bytes(&self) -> anyhow::Result<Vec<u8>>
+    where
+        T: Serialize,
+    {
+        Ok(bincode::serialize(&self.priv_data)?)
+    }
+
+    /// Names of the fields that differ between `self` and `other`
+    /// # Arguments
+    /// * `other` - Descriptor to compare against
+    /// # Returns
+    /// Field names in declaration order, empty if the descriptors are equal
+    #[inline]
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<&'static str>
+    where
+        T: PartialEq,
+    {
+        [
+            ("addr", self.addr == other.addr),
+            ("length", self.length == other.length),
+            ("seid", self.seid == other.seid),
+            ("deid", self.deid == other.deid),
+            ("tokenid", self.tokenid == other.tokenid),
+            ("scna", self.scna == other.scna),
+            ("dcna", self.dcna == other.dcna),
+            ("priv_len", self.priv_len == other.priv_len),
+            ("priv_data", self.priv_data == other.priv_data),
+            ("schema_version", self.schema_version == other.schema_version),
+            ("per_node", self.per_node == other.per_node),
+            ("export_flags", self.export_flags == other.export_flags),
+            ("expires_at", self.expires_at == other.expires_at),
+            ("lifetime", self.lifetime == other.lifetime),
+        ]
+        .into_iter()
+        .filter_map(|(name, equal)| (!equal).then_some(name))
+        .collect()
+    }
+
+    /// Split the descriptor into consecutive sub-range descriptors
+    ///
+    /// `boundaries` are offsets from `addr` at which to cut, so `n` boundaries
+    /// yield `n + 1` descriptors. Each keeps the eids, token, CNAs, privilege
+    /// data and flags of `self`. `per_node` is cleared as the per-node
+    /// breakdown of a sub-range is unknown.
+    /// # Arguments
+    /// * `boundaries` - Strictly increasing offsets within `(0, length)`
+    /// # Returns
+    /// # Errors
+    /// Sub-range descriptors on success, `anyhow::Error` if the boundaries are
+    /// unsorted, out of range or would produce an empty sub-range
+    #[inline]
+    pub fn split(&self, boundaries: &[u64]) -> anyhow::Result<Vec<Self>>
+    where
+        T: Clone,
+    {
+        let _end = self.end_addr()?;
+        let mut parts = Vec::with_capacity(boundaries.len().saturating_add(1));
+        let mut start = 0_u64;
+        for &end in boundaries.iter().chain(core::iter::once(&self.length)) {
+            if end <= start {
+                anyhow::bail!("Split boundary {end:#x} does not follow {start:#x}");
+            }
+            if end > self.length {
+                anyhow::bail!("Split boundary {end:#x} exceeds region length {:#x}", self.length);
+            }
+            let mut part = self.clone();
+            part.addr = self.addr.checked_add(start).context("Sub-range address overflows u64")?;
+            part.length = end.checked_sub(start).context("Sub-range length underflows")?;
+            part.per_node = None;
+            parts.push(part);
+            start = end;
+        }
+        Ok(parts)
+    }
+
+    /// Copy of the descriptor with sensitive fields zeroed, for audit logs
+    ///
+    /// `seid`, `deid` and `tokenid` are zeroed while the region shape and
+    /// privilege data are kept. `self` is left untouched.
+    #[inline]
+    #[must_use]
+    pub fn redacted(&self) -> Self
+    where
+        T: Clone + Default,
+    {
+        let mut desc = self.clone();
+        desc.seid = [0; 16];
+        desc.deid = [0; 16];
+        desc.tokenid = 0;
+        desc
+    }
+
+    /// Display the redacted copy of the descriptor
+    #[inline]
+    #[must_use]
+    pub fn display_redacted(&self) -> Redacted<'_, T> {
+        Redacted(self)
+    }
+
+    /// `addr` with the pointer tag bits of `ADDR_TAG_MASK` cleared
+    ///
+    /// Use this wherever `addr` is used as an address; `addr` keeps the tag
+    /// as exported.
+    #[inline]
+    #[must_use]
+    pub fn untagged_addr(&self) -> u64 {
+        self.addr & !ADDR_TAG_MASK
+    }
+
+    /// End of the region, one past its last byte
+    /// # Returns
+    /// # Errors
+    /// `addr + length` on success, `anyhow::Error` if it overflows `u64`
+    #[inline]
+    pub fn end_addr(&self) -> anyhow::Result<u64> {
+        self.addr
+            .checked_add(self.length)
+            .with_context(|| format!("Region {:#x}+{:#x} ends past u64::MAX", self.addr, self.length))
+    }
+
+    /// Whether `a` lies within `[addr, end_addr)`
+    ///
+    /// A region whose end overflows contains no address.
+    /// # Arguments
+    /// * `a` - Address to check
+    #[inline]
+    #[must_use]
+    pub fn contains_addr(&self, a: u64) -> bool {
+        self.addr <= a && self.end_addr().is_ok_and(|end| a < end)
+    }
+
+    /// Check that the untagged `addr` is aligned to `page_size()` and within
+    /// `[UB_ADDR_WINDOW_START, UB_ADDR_WINDOW_END)`
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `ObmmError` describing the invalid address on failure
+    #[inline]
+    pub fn validate_address(&self) -> Result<(), ObmmError> {
+        let addr = self.untagged_addr();
+        if !addr.is_multiple_of(page_size()) {
+            return Err(ObmmError::UnalignedAddress(self.addr));
+        }
+        if !(UB_ADDR_WINDOW_START..UB_ADDR_WINDOW_END).contains(&addr) {
+            return Err(ObmmError::AddressOutOfWindow(self.addr));
+        }
+        Ok(())
+    }
+
+    /// Check that `priv_len` matches the serialized length of `priv_data`
+    ///
+    /// The C library reads `priv_len` bytes of privilege data, so a descriptor
+    /// from an untrusted peer must pass this check before it reaches the FFI,
+    /// or an inflated `priv_len` makes the driver read past the descriptor.
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `ObmmError::PrivLenMismatch` on failure
+    #[inline]
+    pub fn validate_priv_len(&self) -> Result<(), ObmmError>
+    where
+        T: Serialize,
+    {
+        let actual = bincode::serialized_size(&self.priv_data)
+            .ok()
+            .and_then(|len| u16::try_from(len).ok());
+        if actual == Some(self.priv_len) {
+            Ok(())
+        } else {
+            Err(ObmmError::PrivLenMismatch { declared: self.priv_len, actual })
+        }
+    }
+
+    /// Check that the descriptor is internally consistent
+    ///
+    /// The region must be non-empty and pass `check_length_consistency` and
+    /// `validate_priv_len`. The address is checked separately by
+    /// `validate_address`.
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `ObmmError` describing the first inconsistency on failure
+    #[inline]
+    pub fn sanity_check(&self) -> Result<(), ObmmError>
+    where
+        T: Serialize,
+    {
+        if self.length == 0 {
+            return Err(ObmmError::ZeroLength);
+        }
+        self.check_length_consistency()?;
+        self.validate_priv_len()
+    }
+
+    /// Check that `length` equals the sum of `per_node`, if present
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success or without `per_node`, `ObmmError::LengthMismatch` if
+    /// the sum differs or overflows
+    #[inline]
+    pub fn check_length_consistency(&self) -> Result<(), ObmmError> {
+        let Some(ref per_node) = self.per_node else {
+            return Ok(());
+        };
+        let per_node_sum = per_node.iter().try_fold(0_u64, |total, &len| total.checked_add(len));
+        if per_node_sum == Some(self.length) {
+            Ok(())
+        } else {
+            Err(ObmmError::LengthMismatch { length: self.length, per_node_sum })
+        }
+    }
+
+    /// Physical address of each page of the region, queried lazily
+    ///
+    /// Steps through `[0, length)` by `page_size()`, calling `mem_query_pa`
+    /// for every page as the iterator advances.
+    /// # Arguments
+    /// * `memid` - Memory ID the region is live under
+    /// # Returns
+    /// Iterator of the offset and physical address of each page, or the error
+    /// querying it
+    #[inline]
+    pub fn pages(&self, memid: MemId) -> impl Iterator<Item = anyhow::Result<(u64, u64)>> + use<T> {
+        let (length, step) = (self.length, page_size());
+        std::iter::successors((length > 0).then_some(0_u64), move |&offset| {
+            offset.checked_add(step).filter(|&next| next < length)
+        })
+        .map(move |offset| mem_query_pa(memid, offset).map(|pa| (offset, pa)))
+    }
+}
+
+/// Binary form of the descriptor, as produced by `ObmmMemDesc::to_bytes`
+impl<T: Serialize> TryFrom<ObmmMemDesc<T>> for Vec<u8> {
+    type Error = ObmmError;
+
+    #[inline]
+    fn try_from(desc: ObmmMemDesc<T>) -> Result<Self, Self::Error> {
+        bincode::serialize(&desc).map_err(|e| ObmmError::Codec(e.to_string()))
+    }
+}
+
+/// Descriptor from its binary form, as read by `ObmmMemDesc::from_bytes`
+impl<T: Serialize + for<'de> Deserialize<'de>> TryFrom<&[u8]> for ObmmMemDesc<T> {
+    type Error = ObmmError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        decode_bytes(bytes)
+    }
+}
+
+/// `Display` adapter printing an `ObmmMemDesc` with sensitive fields zeroed
+#[derive(Debug, Clone, Copy)]
+pub struct Redacted<'a, T>(&'a ObmmMemDesc<T>);
+
+impl<T: Clone + Default + std::fmt::Debug> std::fmt::Display for Redacted<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0.redacted())
+    }
+}
+
+impl ObmmMemDesc<UbPrivData> {
+    /// Raw bits of the UB privilege data
+    #[inline]
+    #[must_use]
+    pub const fn priv_bits(&self) -> u16 {
+        self.priv_data.bits()
+    }
+}
+
+impl<T: Copy> ObmmMemDesc<T> {
+    /// Read an `ObmmMemDesc` from a raw C descriptor pointer
+    ///
+    /// Only the C fields are read, the Rust-side metadata takes its defaults.
+    /// # Arguments
+    /// * `ptr` - Pointer to a descriptor owned by another binding
+    /// # Returns
+    /// A copy of the pointed-to descriptor
+    /// # Safety
+    /// As for `ObmmRawMemDesc::from_raw_ptr`
+    #[inline]
+    #[must_use]
+    pub unsafe fn from_raw_ptr(ptr: *const c_void) -> Self {
+        // SAFETY: forwarded under the caller's contract
+        Self::from(unsafe { ObmmRawMemDesc::from_raw_ptr(ptr) })
+    }
+
+    /// The C fields of the descriptor, as handed to the driver
+    #[inline]
+    #[must_use]
+    pub fn to_raw(&self) -> ObmmRawMemDesc<T> {
+        ObmmRawMemDesc {
+            addr: self.addr,
+            length: self.length,
+            seid: self.seid,
+            deid: self.deid,
+            tokenid: self.tokenid,
+            scna: self.scna,
+            dcna: self.dcna,
+            priv_len: self.priv_len,
+            priv_data: self.priv_data,
+        }
+    }
+}
+
+/// Lengths zero-padded to the `MAX_NUMA_NODES` entries the driver reads
+///
+/// Called before any export reaches the driver, so an oversized slice is
+/// rejected instead of read past its end or exported and then refused.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// # Returns
+/// # Errors
+/// Padded lengths on success, `anyhow::Error` if more than `MAX_NUMA_NODES` are given
+fn padded_lengths(length: &[usize]) -> anyhow::Result<NumaLengths> {
+    let mut padded = [0; MAX_NUMA_NODES];
+    padded
+        .get_mut(..length.len())
+        .with_context(|| format!("{} NUMA node lengths given, at most {MAX_NUMA_NODES} are supported", length.len()))?
+        .copy_from_slice(length);
+    Ok(padded)
+}
+
+/// Per-node breakdown recorded in exported descriptors
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// # Returns
+/// # Errors
+/// Lengths padded to `MAX_NUMA_NODES` on success, `anyhow::Error` if more nodes are given
+fn per_node_lengths(length: &[usize]) -> anyhow::Result<[u64; MAX_NUMA_NODES]> {
+    let mut per_node = [0; MAX_NUMA_NODES];
+    for (node, len) in per_node.iter_mut().zip(padded_lengths(length)?) {
+        *node = u64::try_from(len)?;
+    }
+    Ok(per_node)
+}
+
+/// Successful export along with the caveats it was made with
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExportOutcome<T> {
+    /// Memory ID of the export
+    pub memid: MemId,
+    /// Memory Descriptor of the export
+    pub desc: ObmmMemDesc<T>,
+    /// Human readable caveats, empty if the export went as requested
+    pub warnings: Vec<String>,
+}
+
+/// Export, collecting warnings for lengths on NUMA nodes the host does not list
+/// and, if `lenient`, for unsupported flags, which are dropped instead of rejected
+fn export_outcome<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags, lenient: bool) -> anyhow::Result<ExportOutcome<T>> {
+    let mut warnings = Vec::new();
+    let mut flags = flags;
+    if lenient && let Err(err) = check_supported_export_flags(flags) {
+        warnings.push(format!("{err}, exporting without them"));
+        flags = flags.intersection(supported_export_flags()?);
+    }
+    let absent = placement::absent_nodes(length);
+    if !absent.is_empty() {
+        warnings.push(format!("NUMA nodes {absent:?} are not present on this host, the driver may place their lengths elsewhere"));
+    }
+    let (memid, desc) = export_from(length, flags, ObmmMemDesc::default())?;
+    Ok(ExportOutcome { memid, desc, warnings })
+}
+
+/// Export memory region, reporting caveats instead of failing on them
+///
+/// Unlike `mem_export`, flags missing from `supported_export_flags` are
+/// dropped with a warning. Lengths given for NUMA nodes the host does not
+/// list are warned about too.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Memory ID, Memory Descriptor and warnings on success, `anyhow::Error` on failure
+#[inline]
+pub fn mem_export_verbose<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<ExportOutcome<T>> {
+    export_outcome(length, flags, true)
+}
+
+/// Export memory region
+///
+/// Requests larger than the physical memory of the host are rejected before
+/// reaching the driver unless overcommit is allowed with `set_allow_overcommit`,
+/// as are flags missing from `supported_export_flags`. Warnings are
+/// discarded, see `mem_export_verbose`.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_export<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    let outcome = export_outcome(length, flags, false)?;
+    Ok((outcome.memid, outcome.desc))
+}
+
+/// Export memory region, starting from a caller-filled descriptor
+#[cfg(feature = "hook")]
+fn export_from<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags, desc: ObmmMemDesc<T>) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    export_restricted(length, flags, desc, None)
+}
+
+/// Export memory region, tracking it together with its CNA allow-list if any
+#[cfg(feature = "hook")]
+fn export_restricted<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    mut desc: ObmmMemDesc<T>,
+    allowed_cnas: Option<&[u32]>,
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    check_supported_export_flags(flags)?;
+    let per_node = per_node_lengths(length)?;
+    let total: u64 = length.iter().sum::<usize>().try_into()?;
+    preflight::check_physical_memory(total)?;
+    ratelimit::acquire()?;
+    // hooked implementation
+    let memid = hook::next_memid();
+    desc.addr = 0xffff_fc00_0000;
+    desc.length = total;
+    desc.per_node = Some(per_node);
+    desc.export_flags = flags;
+    desc.priv_len = priv_data_len(&desc.priv_data)?;
+    if memid == OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory"))
+    } else {
+        metrics::record_export(desc.length, desc.lifetime);
+        match allowed_cnas {
+            Some(cnas) => registry::record_restricted(memid, &desc, cnas),
+            None => registry::record(memid, registry::Kind::Export, &desc),
+        }
+        Ok((memid, desc))
+    }
+}
+
+/// Export memory region
+///
+/// Requests larger than the physical memory of the host are rejected before
+/// reaching the driver unless overcommit is allowed with `set_allow_overcommit`,
+/// as are flags missing from `supported_export_flags`.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_export<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    let outcome = export_outcome(length, flags, false)?;
+    Ok((outcome.memid, outcome.desc))
+}
+
+/// Export memory region, starting from a caller-filled descriptor
+#[cfg(not(feature = "hook"))]
+fn export_from<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags, mut desc: ObmmMemDesc<T>) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    ffi_ready()?;
+    check_supported_export_flags(flags)?;
+    let padded = padded_lengths(length)?;
+    let per_node = per_node_lengths(length)?;
+    preflight::check_physical_memory(length.iter().sum::<usize>().try_into()?)?;
+    ratelimit::acquire()?;
+    let mut raw = desc.take_raw();
+    let memid = unsafe { obmm_export(padded.as_ptr(), flags.bits(), core::ptr::from_mut(&mut raw).cast()) };
+    desc.set_raw(raw);
+    desc.per_node = Some(per_node);
+    desc.export_flags = flags;
+    if memid == OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory"))
+    } else {
+        metrics::record_export(desc.length, desc.lifetime);
+        registry::record(memid, registry::Kind::Export, &desc);
+        Ok((memid, desc))
+    }
+}
+
+/// Export memory region importable only by the given remote CNAs
+///
+/// The allow-list is enforced by the driver and recorded locally together
+/// with the export, before `ObmmEvent::Exported` is emitted, so `mem_import`
+/// of the region in this process rejects other CNAs from the start.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `allowed_cnas` - CNAs allowed to import the region, must not be empty
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on an
+/// empty allow-list or failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_export_to<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    allowed_cnas: &[u32],
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    if allowed_cnas.is_empty() {
+        anyhow::bail!("Empty CNA allow-list, no remote could import the region");
+    }
+    // hooked implementation
+    export_restricted(length, flags, ObmmMemDesc::default(), Some(allowed_cnas))
+}
+
+/// Export memory region importable only by the given remote CNAs
+///
+/// The allow-list is enforced by the driver and recorded locally together
+/// with the export, before `ObmmEvent::Exported` is emitted, so `mem_import`
+/// of the region in this process rejects other CNAs from the start.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `allowed_cnas` - CNAs allowed to import the region, must not be empty
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on an
+/// empty allow-list or failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_export_to<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    allowed_cnas: &[u32],
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    ffi_ready()?;
+    if allowed_cnas.is_empty() {
+        anyhow::bail!("Empty CNA allow-list, no remote could import the region");
+    }
+    check_supported_export_flags(flags)?;
+    let padded = padded_lengths(length)?;
+    let per_node = per_node_lengths(length)?;
+    preflight::check_physical_memory(length.iter().sum::<usize>().try_into()?)?;
+    ratelimit::acquire()?;
+    let mut raw = ObmmRawMemDesc::<T>::default();
+    let memid = unsafe {
+        obmm_export_acl(
+            padded.as_ptr(),
+            flags.bits(),
+            core::ptr::from_mut(&mut raw).cast(),
+            allowed_cnas.as_ptr(),
+            allowed_cnas.len(),
+        )
+    };
+    let mut desc = ObmmMemDesc::from(raw);
+    desc.per_node = Some(per_node);
+    desc.export_flags = flags;
+    if memid == OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory to CNAs {allowed_cnas:?}"))
+    } else {
+        metrics::record_export(desc.length, desc.lifetime);
+        registry::record_restricted(memid, &desc, allowed_cnas);
+        Ok((memid, desc))
+    }
+}
+
+/// Export memory region with eids and token assigned by the caller
+///
+/// For provisioning flows that assign ids out-of-band. All three ids are
+/// handed to the driver in the descriptor. If the driver reports a different
+/// `seid`, `deid` or `tokenid` the region is unexported again and an error returned,
+/// so a returned descriptor always carries the ids the driver actually uses.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `seid` - Source eid
+/// * `deid` - Destination eid, must differ from `seid`
+/// * `tokenid` - Token ID
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` if
+/// `seid == deid`, the export fails or the driver didn't honor the ids
+#[inline]
+pub fn mem_export_with_ids<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    seid: u128,
+    deid: u128,
+    tokenid: u32,
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    if seid == deid {
+        anyhow::bail!("seid and deid must differ, both are {seid:#x}");
+    }
+    let mut desc = ObmmMemDesc::<T>::default();
+    desc.set_seid(seid);
+    desc.set_deid(deid);
+    desc.tokenid = tokenid;
+    let (memid, desc) = export_from(length, flags, desc)?;
+    let reported = (desc.seid_u128(), desc.deid_u128(), desc.tokenid);
+    if reported != (seid, deid, tokenid) {
+        // the region is being discarded, a failed unexport can only be reported with the mismatch
+        let unexported = mem_unexport(memid, ObmmUnexportFlags::empty());
+        anyhow::bail!(
+            "Driver exported Memory ID {memid} with seid {:#x}, deid {:#x} and tokenid {} instead of \
+             seid {seid:#x}, deid {deid:#x} and tokenid {tokenid} (unexport: {unexported:?})",
+            reported.0,
+            reported.1,
+            reported.2
+        );
+    }
+    Ok((memid, desc))
+}
+
+/// Export memory region of the given lifetime class
+///
+/// The class is carried in the returned descriptor and the export is counted
+/// in its `export_size_histogram_by_lifetime`, plain `mem_export` counts as
+/// `LifetimeClass::Session`.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `lifetime` - Intended lifetime of the export
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[inline]
+pub fn mem_export_with_lifetime<T: Default + Serialize>(
+    length: &[usize],
+    flags: ObmmExportFlags,
+    lifetime: LifetimeClass,
+) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    let desc = ObmmMemDesc::<T> { lifetime, ..ObmmMemDesc::default() };
+    export_from(length, flags, desc)
+}
+
+/// Export an already allocated buffer
+///
+/// The buffer is pinned by the driver for the lifetime of the export, so it
+/// must stay mapped until `mem_unexport`. `obmm_export_useraddr` requires
+/// `addr` and `length` to be multiples of `USERADDR_ALIGN` and the buffer to
+/// be backed by 2MB huge pages. No physical memory check is done as the
+/// memory is already allocated, but the export counts against the rate limit
+/// and in the export metrics like `mem_export`.
+/// # Arguments
+/// * `addr` - Virtual address of the buffer in the calling process
+/// * `length` - Length of the buffer
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_export_at<T: Default + Serialize>(addr: u64, length: usize, flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    check_supported_export_flags(flags)?;
+    ratelimit::acquire()?;
+    let mut desc = ObmmMemDesc::<T>::default();
+    // hooked implementation
+    let memid = hook::next_memid();
+    desc.addr = addr;
+    desc.length = length.try_into()?;
+    desc.export_flags = flags;
+    desc.priv_len = priv_data_len(&desc.priv_data)?;
+    if memid == OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory"))
+    } else {
+        metrics::record_export(desc.length, desc.lifetime);
+        registry::record(memid, registry::Kind::Export, &desc);
+        Ok((memid, desc))
+    }
+}
+
+/// Export an already allocated buffer
+///
+/// The buffer is pinned by the driver for the lifetime of the export, so it
+/// must stay mapped until `mem_unexport`. `obmm_export_useraddr` requires
+/// `addr` and `length` to be multiples of `USERADDR_ALIGN` and the buffer to
+/// be backed by 2MB huge pages. No physical memory check is done as the
+/// memory is already allocated, but the export counts against the rate limit
+/// and in the export metrics like `mem_export`.
+/// # Arguments
+/// * `addr` - Virtual address of the buffer in the calling process
+/// * `length` - Length of the buffer
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_export_at<T: Default + Serialize>(addr: u64, length: usize, flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    ffi_ready()?;
+    check_supported_export_flags(flags)?;
+    ratelimit::acquire()?;
+    let mut raw = ObmmRawMemDesc::<T>::default();
+    let va = core::ptr::without_provenance_mut::<c_void>(usize::try_from(addr)?);
+    let memid = unsafe { obmm_export_useraddr(0, va, length, flags.bits(), core::ptr::from_mut(&mut raw).cast()) };
+    let mut desc = ObmmMemDesc::from(raw);
+    desc.export_flags = flags;
+    if memid == OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory at {addr:#x}"))
+    } else {
+        metrics::record_export(desc.length, desc.lifetime);
+        registry::record(memid, registry::Kind::Export, &desc);
+        Ok((memid, desc))
+    }
+}
+
+/// Preview the descriptor an export would produce without exporting
+///
+/// Only the fields computable without the driver are filled in: `length`,
+/// `per_node`, `export_flags` and `priv_len` for a default `priv_data`.
+/// `addr` stays zero as nothing is allocated, and no Memory ID is assigned.
+/// Flags are checked against `supported_export_flags` as by `mem_export`.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Predicted Memory Descriptor on success, `anyhow::Error` on invalid lengths,
+/// `ObmmError::UnsupportedFlags` on flags `mem_export` would reject
+#[inline]
+pub fn mem_export_dry_run<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<ObmmMemDesc<T>> {
+    check_supported_export_flags(flags)?;
+    let per_node = per_node_lengths(length)?;
+    let mut desc = ObmmMemDesc::<T>::default();
+    desc.length = per_node
+        .iter()
+        .try_fold(0_u64, |total, len| total.checked_add(*len))
+        .context("Total export length overflows u64")?;
+    desc.per_node = Some(per_node);
+    desc.export_flags = flags;
+    desc.priv_len = priv_data_len(&desc.priv_data)?;
+    Ok(desc)
+}
+
+/// Unexport memory region
+///
+/// A region still imported by this process, e.g. through a loopback import,
+/// is only unexported with `ObmmUnexportFlags::FORCE`, as unexporting it
+/// would leave the import pointing at released memory.
+/// # Arguments
+/// * `memid` - Memory ID to unexport
+/// * `flags` - Unexport flags
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::StillImported` if a live local import of
+/// the region exists, `ObmmError::Ffi` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_unexport(memid: MemId, flags: ObmmUnexportFlags) -> Result<(), ObmmError> {
+    if !flags.contains(ObmmUnexportFlags::FORCE) && registry::imported_locally(memid) {
+        return Err(ObmmError::StillImported(memid));
+    }
+    // hooked implementation
+    registry::forget(memid);
+    Ok(())
+}
+
+/// Unexport memory region
+///
+/// A region still imported by this process, e.g. through a loopback import,
+/// is only unexported with `ObmmUnexportFlags::FORCE`, as unexporting it
+/// would leave the import pointing at released memory.
+/// # Arguments
+/// * `memid` - Memory ID to unexport
+/// * `flags` - Unexport flags
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::StillImported` if a live local import of
+/// the region exists, `ObmmError::Ffi` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_unexport(memid: MemId, flags: ObmmUnexportFlags) -> Result<(), ObmmError> {
+    ffi_ready()?;
+    if !flags.contains(ObmmUnexportFlags::FORCE) && registry::imported_locally(memid) {
+        return Err(ObmmError::StillImported(memid));
+    }
+    let ret = unsafe { obmm_unexport(memid, flags.bits()) };
+    if ret == 0 {
+        registry::forget(memid);
+        Ok(())
+    } else {
+        Err(ObmmError::Ffi(ret))
+    }
+}
+
+/// Import memory region
+///
+/// The descriptor is checked with `validate_address` and `validate_priv_len`
+/// before reaching the driver, so descriptors from untrusted peers are safe to
+/// pass. `flags` are checked against its export flags with `flags_compatible`.
+/// Imports of a local export made with `mem_export_to` must come from one of
+/// its allowed CNAs, given as `dcna`. A NUMA node that does not satisfy
+/// `base_dist` is logged as a warning, `mem_import_strict` fails instead.
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and NUMA node on success, `ObmmError` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_import(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+) -> Result<(MemId, i32), ObmmError> {
+    desc.validate_address()?;
+    desc.validate_priv_len()?;
+    flags_compatible(desc.export_flags, flags)?;
+    registry::check_import_cna(desc)?;
+    // hooked implementation
+    let memid = hook::next_memid();
+    let numa = hook::import_node();
+    if memid == OBMM_INVALID_MEMID {
+        Err(ObmmError::Ffi(-1))
+    } else {
+        metrics::record_import(memid, numa, desc.length);
+        registry::record(memid, registry::Kind::Import(numa), desc);
+        placement::warn_on_mismatch(memid, base_dist, numa);
+        Ok((memid, numa))
+    }
+}
+
+/// Import memory region
+///
+/// The descriptor is checked with `validate_address` and `validate_priv_len`
+/// before reaching the driver, so descriptors from untrusted peers are safe to
+/// pass. `flags` are checked against its export flags with `flags_compatible`.
+/// Imports of a local export made with `mem_export_to` must come from one of
+/// its allowed CNAs, given as `dcna`. A NUMA node that does not satisfy
+/// `base_dist` is logged as a warning, `mem_import_strict` fails instead.
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and NUMA node on success, `ObmmError` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_import(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+) -> Result<(MemId, i32), ObmmError> {
+    ffi_ready()?;
+    desc.validate_address()?;
+    desc.validate_priv_len()?;
+    flags_compatible(desc.export_flags, flags)?;
+    registry::check_import_cna(desc)?;
+    let raw = desc.to_raw();
+    let mut numa: i32 = -1;
+    let memid = unsafe {
+        obmm_import(
+            raw.as_raw_ptr(),
+            flags.bits(),
+            base_dist,
+            core::ptr::from_mut(&mut numa),
+        )
+    };
+    if memid == OBMM_INVALID_MEMID {
+        Err(ObmmError::Ffi(-1))
+    } else {
+        metrics::record_import(memid, numa, desc.length);
+        registry::record(memid, registry::Kind::Import(numa), desc);
+        placement::warn_on_mismatch(memid, base_dist, numa);
+        Ok((memid, numa))
+    }
+}
+
+
+/// Import memory region at a caller-chosen virtual address
+///
+/// The descriptor is validated and a placement off `base_dist` is logged as
+/// in `mem_import`, and `va` must be aligned to `page_size`. The driver fails
+/// the import if it cannot map the region at `va`.
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint
+/// * `va` - Virtual address to map the region at
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID, NUMA node and mapped address on success, `ObmmError` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_import_at(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+    va: u64,
+) -> Result<(MemId, i32, u64), ObmmError> {
+    if !va.is_multiple_of(page_size()) {
+        return Err(ObmmError::UnalignedAddress(va));
+    }
+    desc.validate_address()?;
+    desc.validate_priv_len()?;
+    flags_compatible(desc.export_flags, flags)?;
+    registry::check_import_cna(desc)?;
+    // hooked implementation
+    let memid = hook::next_memid();
+    let numa = hook::import_node();
+    if memid == OBMM_INVALID_MEMID {
+        Err(ObmmError::Ffi(-1))
+    } else {
+        metrics::record_import(memid, numa, desc.length);
+        registry::record(memid, registry::Kind::Import(numa), desc);
+        placement::warn_on_mismatch(memid, base_dist, numa);
+        Ok((memid, numa, va))
+    }
+}
+
+/// Import memory region at a caller-chosen virtual address
+///
+/// The descriptor is validated and a placement off `base_dist` is logged as
+/// in `mem_import`, and `va` must be aligned to `page_size`. The driver fails
+/// the import if it cannot map the region at `va`.
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint
+/// * `va` - Virtual address to map the region at
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID, NUMA node and mapped address on success, `ObmmError` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_import_at(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+    va: u64,
+) -> Result<(MemId, i32, u64), ObmmError> {
+    ffi_ready()?;
+    if !va.is_multiple_of(page_size()) {
+        return Err(ObmmError::UnalignedAddress(va));
+    }
+    desc.validate_address()?;
+    desc.validate_priv_len()?;
+    flags_compatible(desc.export_flags, flags)?;
+    registry::check_import_cna(desc)?;
+    let addr = usize::try_from(va).map_err(|_err| ObmmError::UnalignedAddress(va))?;
+    let raw = desc.to_raw();
+    let mut numa: i32 = -1;
+    let memid = unsafe {
+        obmm_import_at(
+            raw.as_raw_ptr(),
+            flags.bits(),
+            base_dist,
+            core::ptr::without_provenance_mut::<c_void>(addr),
+            core::ptr::from_mut(&mut numa),
+        )
+    };
+    if memid == OBMM_INVALID_MEMID {
+        Err(ObmmError::Ffi(-1))
+    } else {
+        metrics::record_import(memid, numa, desc.length);
+        registry::record(memid, registry::Kind::Import(numa), desc);
+        placement::warn_on_mismatch(memid, base_dist, numa);
+        Ok((memid, numa, va))
+    }
+}
+
+
+/// Compute a full-jitter exponential backoff delay
+/// # Arguments
+/// * `rng` - Random number generator used for jitter
+/// * `base` - Nominal delay of the first retry
+/// * `cap` - Upper bound on any single delay
+/// * `attempt` - Zero-based retry attempt
+/// # Returns
+/// A delay drawn uniformly from `[0, min(cap, base * 2^attempt)]`, in whole milliseconds
+#[inline]
+pub fn backoff_delay<R: Rng + ?Sized>(rng: &mut R, base: Duration, cap: Duration, attempt: u32) -> Duration {
+    let base_ms = u64::try_from(base.as_millis()).unwrap_or(u64::MAX);
+    let cap_ms = u64::try_from(cap.as_millis()).unwrap_or(u64::MAX);
+    let factor = 1_u64.checked_shl(attempt).unwrap_or(u64::MAX);
+    let nominal_ms = base_ms.saturating_mul(factor).min(cap_ms);
+    Duration::from_millis(rng.random_range(0..=nominal_ms))
+}
+
+/// Import memory region, retrying with jittered exponential backoff
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint
+/// * `retries` - Number of retries after the first attempt
+/// * `base` - Nominal delay of the first retry
+/// * `cap` - Upper bound on any single delay
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and NUMA node on success, the last `ObmmError` once all
+/// retries fail. Descriptor validation and flag compatibility errors are
+/// returned without retrying.
+#[inline]
+pub fn mem_import_retry(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+    retries: u32,
+    base: Duration,
+    cap: Duration,
+) -> Result<(MemId, i32), ObmmError> {
+    let mut rng = rand::rng();
+    let mut attempt = 0;
+    loop {
+        match mem_import(desc, flags, base_dist) {
+            Ok(imported) => return Ok(imported),
+            Err(ObmmError::Ffi(_)) if attempt < retries => {
+                std::thread::sleep(backoff_delay(&mut rng, base, cap, attempt));
+                attempt = attempt.saturating_add(1);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Unimport memory region
+/// # Arguments
+/// * `memid` - Memory ID to unimport
+/// * `flags` - Unimport flags
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::Ffi` on failure
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_unimport(memid: MemId, _: ObmmUnimportFlags) -> Result<(), ObmmError> {
+    // hooked implementation
+    metrics::record_unimport(memid);
+    registry::forget(memid);
+    Ok(())
+}
+
+/// Unimport memory region
+/// # Arguments
+/// * `memid` - Memory ID to unimport
+/// * `flags` - Unimport flags
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::LibraryUnavailable` if the OBMM library
+/// failed to load, `ObmmError::Ffi` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_unimport(memid: MemId, flags: ObmmUnimportFlags) -> Result<(), ObmmError> {
+    ffi_ready()?;
+    let ret = unsafe { obmm_unimport(memid, flags.bits()) };
+    if ret == 0 {
+        metrics::record_unimport(memid);
+        registry::forget(memid);
+        Ok(())
+    } else {
+        Err(ObmmError::Ffi(ret))
+    }
+}
+
+/// Replace an imported region with a new one without a gap in between
+///
+/// The new descriptor is imported first and the old region is unimported only
+/// once that succeeded, so at every point at least one of the two is mapped.
+/// If the new import fails the old region is left untouched. If unimporting
+/// the old region fails, the new import is rolled back and the old region
+/// stays the live one.
+/// # Arguments
+/// * `old` - Memory ID of the live import to replace
+/// * `new_desc` - Memory Descriptor of the replacement region
+/// * `flags` - Import flags for the replacement
+/// * `base_dist` - Base distribution hint for the replacement
+/// # Returns
+/// # Errors
+/// Memory ID of the replacement on success, `anyhow::Error` if `old` is not a
+/// live import, the new import fails or the old region can't be unimported
+#[inline]
+pub fn mem_swap_import(old: MemId, new_desc: &ObmmMemDesc<UbPrivData>, flags: ObmmImportFlags, base_dist: i32) -> anyhow::Result<MemId> {
+    if !registry::is_import(old) {
+        return Err(ObmmError::UnknownMemId(old)).context("Cannot swap out a region that is not imported");
+    }
+    let (new, _) = mem_import(new_desc, flags, base_dist).context("Failed to import the replacement region")?;
+    if let Err(err) = mem_unimport(old, ObmmUnimportFlags::empty()) {
+        let rollback = mem_unimport(new, ObmmUnimportFlags::empty());
+        return Err(err).with_context(|| match rollback {
+            Ok(()) => format!("Failed to unimport MemID {old}, replacement MemID {new} rolled back"),
+            Err(rollback_err) => format!("Failed to unimport MemID {old}, rolling back MemID {new} failed: {rollback_err}"),
+        });
+    }
+    Ok(new)
+}
+
+/// Grant an additional CNA access to an existing export
+///
+/// The region is shared with `cna` without re-exporting it. An allow-list set
+/// with `mem_export_to` is extended with `cna`.
+/// # Arguments
+/// * `memid` - Memory ID of a live export of this process
+/// * `cna` - CNA to grant access to
+/// # Returns
+/// # Errors
+/// Descriptor for `cna` to import, with `dcna` set to it, on success,
+/// `anyhow::Error` if `memid` is not a live export
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_grant<T: for<'de> Deserialize<'de>>(memid: MemId, cna: u32) -> anyhow::Result<ObmmMemDesc<T>> {
+    // hooked implementation, the descriptor is derived from the tracked one
+    let mut desc = registry::export_descriptor::<T>(memid).with_context(|| format!("MemID {memid} is not a live export"))?;
+    desc.dcna = cna;
+    registry::allow_cna(memid, cna);
+    Ok(desc)
+}
+
+/// Grant an additional CNA access to an existing export
+///
+/// The region is shared with `cna` without re-exporting it. An allow-list set
+/// with `mem_export_to` is extended with `cna`.
+/// # Arguments
+/// * `memid` - Memory ID of a live export of this process
+/// * `cna` - CNA to grant access to
+/// # Returns
+/// # Errors
+/// Descriptor for `cna` to import, as filled by the driver, on success,
+/// `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_grant<T: Default + for<'de> Deserialize<'de>>(memid: MemId, cna: u32) -> anyhow::Result<ObmmMemDesc<T>> {
+    ffi_ready()?;
+    let tracked = registry::export_descriptor::<T>(memid);
+    let mut raw = ObmmRawMemDesc::<T>::default();
+    let ret = unsafe { obmm_grant(memid, cna, core::ptr::from_mut(&mut raw).cast()) };
+    if ret != 0 {
+        return Err(ObmmError::Ffi(ret)).with_context(|| format!("Failed to grant CNA {cna} access to MemID {memid}"));
+    }
+    let mut desc = ObmmMemDesc::from(raw);
+    if let Some(tracked) = tracked {
+        desc.per_node = tracked.per_node;
+        desc.export_flags = tracked.export_flags;
+        desc.lifetime = tracked.lifetime;
+    }
+    registry::allow_cna(memid, cna);
+    Ok(desc)
+}
+
+/// Query the CNA currently owning an exported region
+///
+/// Ownership can move after export, e.g. on migration, so the result may
+/// differ from the descriptor's `scna` and from earlier queries.
+/// # Arguments
+/// * `memid` - Memory ID of the export
+/// # Returns
+/// # Errors
+/// Owner CNA on success, `anyhow::Error` if the region is not exported
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_query_owner(memid: MemId) -> anyhow::Result<u32> {
+    // hooked implementation
+    lookup_descriptor(memid)
+        .map(|desc| desc.scna)
+        .with_context(|| format!("MemID {memid} is not exported"))
+}
+
+/// Query the CNA currently owning an exported region
+///
+/// Ownership can move after export, e.g. on migration, so the result may
+/// differ from the descriptor's `scna` and from earlier queries.
+/// # Arguments
+/// * `memid` - Memory ID of the export
+/// # Returns
+/// # Errors
+/// Owner CNA on success, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_query_owner(memid: MemId) -> anyhow::Result<u32> {
+    ffi_ready()?;
+    let mut cna = 0;
+    let ret = unsafe { obmm_query_owner(memid, &raw mut cna) };
+    if ret == 0 {
+        Ok(cna)
+    } else {
+        Err(ObmmError::Ffi(ret)).with_context(|| format!("Failed to query owner of MemID {memid}"))
+    }
+}
+
+/// Query the physical address backing an offset of a region
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// * `offset` - Offset within the region
+/// # Returns
+/// # Errors
+/// Physical address on success, `anyhow::Error` if the region is not live or
+/// `offset` is past its end
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_query_pa(memid: MemId, offset: u64) -> anyhow::Result<u64> {
+    // hooked implementation, regions are backed contiguously from their address
+    let desc = lookup_descriptor(memid).with_context(|| format!("MemID {memid} is not live"))?;
+    if offset >= desc.length {
+        anyhow::bail!("Offset {offset:#x} is past the end of MemID {memid}");
+    }
+    desc.addr.checked_add(offset).context("Physical address overflows")
+}
+
+/// Query the physical address backing an offset of a region
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// * `offset` - Offset within the region
+/// # Returns
+/// # Errors
+/// Physical address on success, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_query_pa(memid: MemId, offset: u64) -> anyhow::Result<u64> {
+    ffi_ready()?;
+    let mut pa = 0;
+    let ret = unsafe { obmm_query_pa_by_memid(memid, offset, &raw mut pa) };
+    if ret == 0 {
+        Ok(pa)
+    } else {
+        Err(ObmmError::Ffi(ret)).with_context(|| format!("Failed to query PA of MemID {memid} at offset {offset:#x}"))
+    }
+}
+
+/// Region known to the driver containing `pa` and the offset into it
+#[cfg(feature = "hook")]
+#[allow(clippy::unnecessary_wraps)]
+fn query_memid_by_pa(pa: u64) -> anyhow::Result<Option<(MemId, u64)>> {
+    // hooked implementation
+    Ok(hook::pa_owner(pa))
+}
+
+/// Region known to the driver containing `pa` and the offset into it
+///
+/// The C call does not tell an unknown address from other failures, so any
+/// failure yields `None`.
+#[cfg(not(feature = "hook"))]
+fn query_memid_by_pa(pa: u64) -> anyhow::Result<Option<(MemId, u64)>> {
+    ffi_ready()?;
+    let mut memid = OBMM_INVALID_MEMID;
+    let mut offset = 0;
+    let ret = unsafe { obmm_query_memid_by_pa(pa, &raw mut memid, &raw mut offset) };
+    Ok((ret == 0 && memid != OBMM_INVALID_MEMID).then_some((memid, offset)))
+}
+
+/// Find the export containing a physical address
+///
+/// Live exports of this process are checked first, without calling into the
+/// driver. Addresses outside all of them are resolved by the driver with
+/// `obmm_query_memid_by_pa`.
+/// # Arguments
+/// * `pa` - Physical address to look up
+/// # Returns
+/// # Errors
+/// Memory ID and offset of `pa` within its region, `None` if no region
+/// contains it, `anyhow::Error` if the driver cannot be reached
+#[inline]
+pub fn is_exported(pa: u64) -> anyhow::Result<Option<(MemId, u64)>> {
+    if let Some(found) = registry::export_containing(pa) {
+        return Ok(Some(found));
+    }
+    query_memid_by_pa(pa)
+}
+
+/// Pin a region so the driver does not reclaim it under memory pressure
+///
+/// Pinning an already pinned region is a no-op.
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::UnknownMemId` if `memid` is not live,
+/// `ObmmError::Ffi` if the driver refuses
+#[inline]
+pub fn mem_pin(memid: MemId) -> Result<(), ObmmError> {
+    match registry::pinned(memid) {
+        None => Err(ObmmError::UnknownMemId(memid)),
+        Some(true) => Ok(()),
+        Some(false) => {
+            pin_call(memid, true)?;
+            registry::set_pinned(memid, true);
+            Ok(())
+        }
+    }
+}
+
+/// Unpin a region pinned with `mem_pin`
+/// # Arguments
+/// * `memid` - Memory ID of a pinned region
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::UnknownMemId` if `memid` is not live,
+/// `ObmmError::NotPinned` if it is not pinned, `ObmmError::Ffi` if the driver refuses
+#[inline]
+pub fn mem_unpin(memid: MemId) -> Result<(), ObmmError> {
+    match registry::pinned(memid) {
+        None => Err(ObmmError::UnknownMemId(memid)),
+        Some(false) => Err(ObmmError::NotPinned(memid)),
+        Some(true) => {
+            pin_call(memid, false)?;
+            registry::set_pinned(memid, false);
+            Ok(())
+        }
+    }
+}
+
+/// Whether a live region is mapped cacheable
+///
+/// Reflects `UbPrivData::CACHEABLE` in the tracked descriptor, as set at
+/// export or by `mem_set_cacheable`.
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// # Returns
+/// # Errors
+/// Whether the region is cacheable on success, `ObmmError::UnknownMemId` if
+/// `memid` is not live
+#[inline]
+pub fn mem_is_cacheable(memid: MemId) -> Result<bool, ObmmError> {
+    registry::priv_data::<UbPrivData>(memid)
+        .map(|priv_data| priv_data.contains(UbPrivData::CACHEABLE))
+        .ok_or(ObmmError::UnknownMemId(memid))
+}
+
+/// Switch caching of an imported region on or off
+///
+/// Lets a consumer map a region cacheable for a random-access phase and
+/// uncached for streaming. The tracked descriptor's `UbPrivData::CACHEABLE`
+/// follows the change; setting the current state is a no-op.
+/// # Arguments
+/// * `memid` - Memory ID of a live import
+/// * `cacheable` - Whether the region should be cacheable
+/// # Returns
+/// # Errors
+/// Ok(()) on success, `ObmmError::UnknownMemId` if `memid` is not a live
+/// import, `ObmmError::Ffi` if the driver refuses
+#[inline]
+pub fn mem_set_cacheable(memid: MemId, cacheable: bool) -> Result<(), ObmmError> {
+    let mut priv_data = registry::is_import(memid)
+        .then(|| registry::priv_data::<UbPrivData>(memid))
+        .flatten()
+        .ok_or(ObmmError::UnknownMemId(memid))?;
+    if priv_data.contains(UbPrivData::CACHEABLE) == cacheable {
+        return Ok(());
+    }
+    set_cacheable_call(memid, cacheable)?;
+    priv_data.set(UbPrivData::CACHEABLE, cacheable);
+    registry::set_priv_data(memid, &priv_data);
+    Ok(())
+}
+
+/// Change the cacheability of a region in the driver
+#[cfg(feature = "hook")]
+#[allow(clippy::unnecessary_wraps)]
+fn set_cacheable_call(_: MemId, _: bool) -> Result<(), ObmmError> {
+    // hooked implementation
+    Ok(())
+}
+
+/// Change the cacheability of a region in the driver
+#[cfg(not(feature = "hook"))]
+fn set_cacheable_call(memid: MemId, cacheable: bool) -> Result<(), ObmmError> {
+    ffi_ready()?;
+    let ret = unsafe { obmm_set_cacheable(memid, i32::from(cacheable)) };
+    if ret == 0 { Ok(()) } else { Err(ObmmError::Ffi(ret)) }
+}
+
+/// Pin or unpin a region in the driver
+#[cfg(feature = "hook")]
+#[allow(clippy::unnecessary_wraps)]
+fn pin_call(_: MemId, _: bool) -> Result<(), ObmmError> {
+    // hooked implementation
+    Ok(())
+}
+
+/// Pin or unpin a region in the driver
+#[cfg(not(feature = "hook"))]
+fn pin_call(memid: MemId, pin: bool) -> Result<(), ObmmError> {
+    ffi_ready()?;
+    let ret = unsafe { if pin { obmm_pin(memid) } else { obmm_unpin(memid) } };
+    if ret == 0 { Ok(()) } else { Err(ObmmError::Ffi(ret)) }
+}
+
+// FFI bindings to OBMM C library, replaced by lazily resolved shims with dlopen
+#[cfg(not(feature = "dlopen"))]
+unsafe extern "C" {
+    /// Export memory regions for remote access
+    ///
+    /// # Arguments
+    /// * `length` - Array of lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// * `desc` - Output memory descriptor
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
+    pub fn obmm_export(
+        length: *const usize,
+        flags: u64,
+        desc: *mut c_void,
+    ) -> MemId;
+
+    /// Export memory regions importable only by the given CNAs
+    ///
+    /// # Arguments
+    /// * `length` - Array of lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// * `desc` - Output memory descriptor
+    /// * `cnas` - CNAs allowed to import the region
+    /// * `n_cnas` - Number of entries in `cnas`
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
+    pub fn obmm_export_acl(
+        length: *const usize,
+        flags: u64,
+        desc: *mut c_void,
+        cnas: *const u32,
+        n_cnas: usize,
+    ) -> MemId;
+
+    /// Export memory regions, reporting progress through a callback
+    ///
+    /// # Arguments
+    /// * `length` - Array of lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// * `desc` - Output memory descriptor
+    /// * `cb` - Called with bytes done, total bytes and `ctx`
+    /// * `ctx` - Opaque pointer passed back to `cb`
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
+    pub fn obmm_export_cb(
+        length: *const usize,
+        flags: u64,
+        desc: *mut c_void,
+        cb: extern "C" fn(u64, u64, *mut c_void),
+        ctx: *mut c_void,
+    ) -> MemId;
+
+    /// Export flags supported by the driver
+    ///
+    /// # Returns
+    /// Capability mask of export flag bits
+    pub fn obmm_supported_flags() -> u64;
+
+    /// Export a virtual address range of a process
+    ///
+    /// # Arguments
+    /// * `pid` - Process owning the range, 0 for the calling process
+    /// * `va` - Start of the range
+    /// * `length` - Length of the range
+    /// * `flags` - Export flags
+    /// * `desc` - Output memory descriptor
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
+    pub fn obmm_export_useraddr(
+        pid: i32,
+        va: *mut c_void,
+        length: usize,
+        flags: u64,
+        desc: *mut c_void,
+    ) -> MemId;
+
+    /// Unexport previously exported memory region
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID to unexport
+    /// * `flags` - Unexport flags
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_unexport(id: MemId, flags: u64) -> i32;
+
+    /// Request cancellation of exports in flight in the calling process
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_cancel_export() -> i32;
+
+    /// Import remote memory region
+    ///
+    /// # Arguments
+    /// * `desc` - Memory descriptor from remote
+    /// * `flags` - Import flags
+    /// * `base_dist` - Base distribution hint
+    /// * `numa` - Output NUMA node ID
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
+    pub fn obmm_import(
+        desc: *const c_void,
+        flags: u64,
+        base_dist: i32,
+        numa: *mut i32,
+    ) -> MemId;
+
+    /// Import remote memory region at a fixed virtual address
+    ///
+    /// # Arguments
+    /// * `desc` - Memory descriptor from remote
+    /// * `flags` - Import flags
+    /// * `base_dist` - Base distribution hint
+    /// * `va` - Page-aligned address to map the region at
+    /// * `numa` - Output NUMA node ID
+    ///
+    /// # Returns
+    /// Memory ID on success, `OBMM_INVALID_MEMID` if the region cannot be mapped at `va`
+    pub fn obmm_import_at(
+        desc: *const c_void,
+        flags: u64,
+        base_dist: i32,
+        va: *mut c_void,
+        numa: *mut i32,
+    ) -> MemId;
+
+    /// Unimport previously imported memory region
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID to unimport
+    /// * `flags` - Unimport flags
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_unimport(id: MemId, flags: u64) -> i32;
+
+    /* debug interface */
+    
+    /// Query memory ID by physical address
+    ///
+    /// # Arguments
+    /// * `pa` - Physical address
+    /// * `id` - Output memory ID
+    /// * `offset` - Output offset within memory region
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_query_memid_by_pa(
+        pa: u64,
+        id: *mut MemId,
+        offset: *mut u64,
+    ) -> i32;
+
+    /// Pin a region against reclamation
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_pin(id: MemId) -> i32;
+
+    /// Unpin a region pinned with `obmm_pin`
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_unpin(id: MemId) -> i32;
+
+    /// Grant an additional CNA access to an exported region
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    /// * `cna` - CNA to grant access to
+    /// * `desc` - Output memory descriptor targeting `cna`
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_grant(id: MemId, cna: u32, desc: *mut c_void) -> i32;
+
+    /// Switch caching of an imported region on or off
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    /// * `cacheable` - Non-zero to make the region cacheable
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_set_cacheable(id: MemId, cacheable: i32) -> i32;
+
+    /// Query the CNA currently owning an exported region
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    /// * `cna` - Output owner CNA
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_query_owner(id: MemId, cna: *mut u32) -> i32;
+
+    /// Query access counters of a region
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    /// * `reads` - Output read count
+    /// * `writes` - Output write count
+    /// * `last_access_ns` - Output time of the last access in nanoseconds since the Unix epoch, 0 if never accessed
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_access_stats(
+        id: MemId,
+        reads: *mut u64,
+        writes: *mut u64,
+        last_access_ns: *mut u64,
+    ) -> i32;
+
+    /// Query the free exportable bytes of each NUMA node
+    ///
+    /// # Arguments
+    /// * `capacity` - Output array of free bytes per node
+    /// * `n_nodes` - Number of entries in `capacity`
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_capacity(capacity: *mut u64, n_nodes: usize) -> i32;
+
+    /// Query physical address by memory ID and offset
+    ///
+    /// # Arguments
+    /// * `id` - Memory ID
+    /// * `offset` - Offset within memory region
+    /// * `pa` - Output physical address
+    ///
+    /// # Returns
+    /// 0 on success, -1 on failure
+    pub fn obmm_query_pa_by_memid(
+        id: MemId,
+        offset: u64,
+        pa: *mut u64,
+    ) -> i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export() -> anyhow::Result<()> {
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        if let Some(v) = lengths.get_mut(0) {
+            *v = 1024 * 1024 * 64; // 64MB on NUMA node 0
+        }
+        let flags = ObmmExportFlags::ALLOWMMAP;
+        match mem_export::<UbPrivData>(&lengths, flags) {
+            Ok((memid, desc)) => {
+                println!("Exported MemID: {memid}");
+                println!("Memory Descriptor: {desc:?}");
+                assert!(memid != OBMM_INVALID_MEMID);
+                assert!(desc.length == 1024 * 1024 * 128);
+                Ok(())
+            }
+            Err(code) => {
+                Err(anyhow::anyhow!("mem_export failed with code {code}"))
+            }
+        }
+    }
+
+    #[test]
+    fn test_import() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData> {
+            addr: 0xffff_fc00_0000,
+            length: 1024 * 1024 * 128,
+            seid: [0; 16],
+            deid: [0; 16],
+            tokenid: 0,
+            scna: 0,
+            dcna: 0,
+            priv_len: 2,
+            priv_data: UbPrivData::default(),
+            ..ObmmMemDesc::default()
+        };
+        let flags = ObmmImportFlags::ALLOWMMAP;
+        match mem_import(&desc, flags, 0) {
+            Ok((memid, numa)) => {
+                println!("Imported MemID: {memid}, NUMA Node: {numa}");
+                assert!(memid != OBMM_INVALID_MEMID);
+                Ok(())
+            }
+            Err(code) => {
+                Err(anyhow::anyhow!("mem_import failed with code {code}"))
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "hook")]
+    fn test_import_at() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData> {
+            addr: 0xffff_fc00_0000,
+            length: 1024 * 1024 * 128,
+            priv_len: 2,
+            ..ObmmMemDesc::default()
+        };
+        let va = 0x7f00_0000_0000;
+        let (memid, _, mapped) = mem_import_at(&desc, ObmmImportFlags::empty(), 0, va)?;
+        assert_eq!(mapped, va);
+        assert_eq!(mem_import_at(&desc, ObmmImportFlags::empty(), 0, va + 1), Err(ObmmError::UnalignedAddress(va + 1)));
+        mem_unimport(memid, ObmmUnimportFlags::empty())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_ptr_round_trip() -> anyhow::Result<()> {
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        if let Some(v) = lengths.get_mut(1) {
+            *v = 1024 * 1024 * 128; // 128MB on NUMA node 1
+        }
+        let (_, desc) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP)?;
+        let raw = desc.to_raw();
+        // SAFETY: the pointer was just taken from a live `ObmmRawMemDesc<UbPrivData>`
+        let copied = unsafe { ObmmMemDesc::<UbPrivData>::from_raw_ptr(raw.as_raw_ptr()) };
+        assert_eq!(copied.to_raw(), raw);
+        assert_eq!(copied.diff(&desc), ["per_node", "export_flags"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff_delay_bounds() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let base = Duration::from_millis(10);
+        let cap = Duration::from_millis(500);
+        for attempt in 0..64 {
+            let nominal = base.saturating_mul(1_u32.checked_shl(attempt).unwrap_or(u32::MAX)).min(cap);
+            for _ in 0..16 {
+                assert!(backoff_delay(&mut rng, base, cap, attempt) <= nominal);
+            }
+        }
+    }
+
+    #[test]
+    fn test_import_retry() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).build()?;
+        match mem_import_retry(&desc, ObmmImportFlags::ALLOWMMAP, 0, 3, Duration::from_millis(1), Duration::from_millis(10)) {
+            Ok((memid, _)) => {
+                assert!(memid != OBMM_INVALID_MEMID);
+                Ok(())
+            }
+            Err(code) => {
+                Err(anyhow::anyhow!("mem_import_retry failed with code {code}"))
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder_priv_len() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(1024 * 1024 * 128)
+            .priv_data(UbPrivData::OCHIP | UbPrivData::CACHEABLE)
+            .build()?;
+        assert_eq!(desc.priv_len, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_priv_data_too_large() {
+        let oversized = vec![0_u8; usize::from(MAX_PRIV_DATA_LEN)];
+        let result = ObmmMemDesc::<Vec<u8>>::builder().priv_data(oversized.clone()).build();
+        assert!(result.is_err_and(|e| e.to_string().contains("exceeding MAX_PRIV_DATA_LEN")));
+
+        let mut desc = ObmmMemDesc::<Vec<u8>>::new();
+        desc.priv_data = oversized;
+        assert!(desc.recompute_priv_len().is_err());
+    }
+
+    #[test]
+    fn test_priv_data_bytes() -> anyhow::Result<()> {
+        let ub = ObmmMemDesc::<UbPrivData>::builder().priv_data(UbPrivData::OCHIP | UbPrivData::CACHEABLE).build()?;
+        assert_eq!(ub.priv_bits(), 0x60);
+        assert_eq!(ub.priv_data_bytes()?, [0x60, 0x00]);
+        assert_eq!(ub.priv_data_bytes()?.len(), usize::from(ub.priv_len));
+
+        let raw = ObmmMemDesc::<Vec<u8>>::builder().priv_data(vec![7, 9]).build()?;
+        assert_eq!(raw.priv_data_bytes()?, [2, 0, 0, 0, 0, 0, 0, 0, 7, 9]);
+        assert_eq!(raw.priv_data_bytes()?.len(), usize::from(raw.priv_len));
+        Ok(())
+    }
+
+    #[test]
+    fn test_end_addr() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0x1000).length(0x2000).build()?;
+        assert_eq!(desc.end_addr()?, 0x3000);
+        assert!(desc.contains_addr(0x1000) && desc.contains_addr(0x2fff));
+        assert!(!desc.contains_addr(0xfff) && !desc.contains_addr(0x3000));
+
+        let mut desc = desc;
+        desc.addr = u64::MAX - 0x1000;
+        desc.length = 0x1000;
+        assert_eq!(desc.end_addr()?, u64::MAX);
+        assert!(desc.contains_addr(u64::MAX - 1) && !desc.contains_addr(u64::MAX));
+
+        desc.length = 0x1001;
+        assert!(desc.end_addr().is_err());
+        assert!(!desc.contains_addr(u64::MAX - 1));
+        assert!(desc.split(&[0x800]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_address() -> anyhow::Result<()> {
+        let mut desc = ObmmMemDesc::<UbPrivData>::new();
+        desc.addr = 0xffff_fc00_0001;
+        assert_eq!(desc.validate_address(), Err(ObmmError::UnalignedAddress(0xffff_fc00_0001)));
+        assert!(mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).is_err());
+
+        desc.addr = 0x7fff_0000_0000;
+        assert_eq!(desc.validate_address(), Err(ObmmError::AddressOutOfWindow(0x7fff_0000_0000)));
+        assert!(mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).is_err());
+
+        desc.addr = 0xffff_fc00_0000;
+        desc.validate_address()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_untagged_addr() -> anyhow::Result<()> {
+        let mut desc = ObmmMemDesc::<UbPrivData>::example();
+        assert_eq!(desc.untagged_addr(), desc.addr);
+
+        desc.addr = UB_ADDR_WINDOW_START | ADDR_TAG_MASK;
+        assert_eq!(desc.untagged_addr(), UB_ADDR_WINDOW_START);
+        desc.validate_address()?;
+        desc.addr |= 1;
+        assert_eq!(desc.validate_address(), Err(ObmmError::UnalignedAddress(desc.addr)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_address_page_size() -> anyhow::Result<()> {
+        let mut desc = ObmmMemDesc::<UbPrivData>::new();
+        desc.addr = UB_ADDR_WINDOW_START.checked_add(page_size()).context("address overflow")?;
+        desc.validate_address()?;
+        desc.addr = UB_ADDR_WINDOW_START.checked_add(page_size() / 2).context("address overflow")?;
+        assert_eq!(desc.validate_address(), Err(ObmmError::UnalignedAddress(desc.addr)));
+        Ok(())
+    }
+
+    #[cfg(feature = "hook")]
+    #[test]
+    fn test_imported_bytes_per_node() -> anyhow::Result<()> {
+        // nodes 5 and 6 are not imported onto by any other test
+        let before = imported_bytes_per_node();
+        let desc_a = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(1024 * 1024).build()?;
+        let desc_b = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).build()?;
+        set_hook_import_node(5);
+        let (memid_a, numa_a) = mem_import(&desc_a, ObmmImportFlags::ALLOWMMAP, 0)?;
+        set_hook_import_node(6);
+        let (memid_b, numa_b) = mem_import(&desc_b, ObmmImportFlags::ALLOWMMAP, 0)?;
+        set_hook_import_node(0);
+        assert_eq!((numa_a, numa_b), (5, 6));
+
+        let after = imported_bytes_per_node();
+        let delta = |node: usize| after.get(node).zip(before.get(node)).map(|(a, b)| a.saturating_sub(*b));
+        assert_eq!(delta(5), Some(1024 * 1024));
+        assert_eq!(delta(6), Some(4096));
+
+        mem_unimport(memid_a, ObmmUnimportFlags::empty())?;
+        mem_unimport(memid_b, ObmmUnimportFlags::empty())?;
+        assert_eq!(imported_bytes_per_node().get(5..7), before.get(5..7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_dry_run() -> anyhow::Result<()> {
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        if let Some(v) = lengths.get_mut(1) {
+            *v = 1024 * 1024 * 128;
+        }
+        if let Some(v) = lengths.get_mut(3) {
+            *v = 1024 * 1024 * 64;
+        }
+        let desc = mem_export_dry_run::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP)?;
+        assert_eq!(desc.length, 1024 * 1024 * 192);
+        assert_eq!(desc.per_node.and_then(|per_node| per_node.get(3).copied()), Some(1024 * 1024 * 64));
+        assert_eq!(desc.export_flags, ObmmExportFlags::ALLOWMMAP);
+        // a real export would have been assigned an address
+        assert_eq!(desc.addr, 0);
+        assert!(mem_export_dry_run::<UbPrivData>(&[1; MAX_NUMA_NODES + 1], ObmmExportFlags::empty()).is_err());
+        let undefined = ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::from_bits_retain(1 << 40);
+        let err = mem_export_dry_run::<UbPrivData>(&lengths, undefined).err().and_then(|e| e.downcast::<ObmmError>().ok());
+        assert_eq!(err, Some(ObmmError::UnsupportedFlags("0x10000000000".to_owned())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_padded_lengths() -> anyhow::Result<()> {
+        assert_eq!(padded_lengths(&[5, 7])?, numa_lengths! { 0 => 5, 1 => 7 });
+        assert_eq!(padded_lengths(&[3; MAX_NUMA_NODES])?, [3; MAX_NUMA_NODES]);
+        assert!(padded_lengths(&[1; MAX_NUMA_NODES + 1]).is_err());
+        assert!(mem_export::<UbPrivData>(&[1; MAX_NUMA_NODES + 1], ObmmExportFlags::empty()).is_err());
+        assert!(mem_export_to::<UbPrivData>(&[1; MAX_NUMA_NODES + 1], ObmmExportFlags::empty(), &[7]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_region() -> anyhow::Result<()> {
+        let local = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(1024 * 1024 * 128)
+            .seid([1; 16])
+            .deid([2; 16])
+            .tokenid(42)
+            .build()?;
+        let mut peer = local;
+        peer.tokenid = 7;
+        peer.scna = 3;
+        assert!(local != peer);
+        assert!(local.same_region(&peer));
+
+        peer.addr = 0xffff_fd00_0000;
+        assert!(!local.same_region(&peer));
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(1024 * 1024 * 128)
+            .seid([1; 16])
+            .deid([2; 16])
+            .tokenid(42)
+            .scna(3)
+            .priv_data(UbPrivData::CACHEABLE)
+            .build()?;
+        let redacted = desc.redacted();
+        assert_eq!((redacted.seid, redacted.deid, redacted.tokenid), ([0; 16], [0; 16], 0));
+        assert_eq!((redacted.addr, redacted.length, redacted.scna), (desc.addr, desc.length, desc.scna));
+        assert_eq!(redacted.priv_data, UbPrivData::CACHEABLE);
+        assert_eq!(desc.tokenid, 42);
+        assert_eq!(desc.display_redacted().to_string(), format!("{redacted:?}"));
+        assert!(desc.display_redacted().to_string().contains("tokenid: 0,"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_priv_data_from_str() -> anyhow::Result<()> {
+        assert_eq!("OCHIP | CACHEABLE".parse::<UbPrivData>()?, UbPrivData::OCHIP | UbPrivData::CACHEABLE);
+        assert_eq!(" cacheable ".parse::<UbPrivData>()?, UbPrivData::CACHEABLE);
+        assert_eq!("".parse::<UbPrivData>()?, UbPrivData::empty());
+        assert_eq!(
+            "OCHIP | BOGUS | Nope".parse::<UbPrivData>(),
+            Err(ObmmError::UnknownFlags("BOGUS, Nope".to_owned()))
+        );
+        for flags in [UbPrivData::empty(), UbPrivData::OCHIP, UbPrivData::OCHIP | UbPrivData::CACHEABLE] {
+            assert_eq!(flags.to_string().parse::<UbPrivData>()?, flags);
+        }
+        assert_eq!((UbPrivData::OCHIP | UbPrivData::CACHEABLE).to_string(), "OCHIP | CACHEABLE");
+        Ok(())
+    }
+
+    #[test]
+    fn test_readonly_export_flag() -> anyhow::Result<()> {
+        let flags = ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::READONLY;
+        let (_, desc) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], flags)?;
+        assert!(desc.export_flags.contains(ObmmExportFlags::READONLY));
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json(&desc.to_json()?)?.export_flags, flags);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_owner() -> anyhow::Result<()> {
+        let (memid, desc) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty())?;
+        assert_eq!(mem_query_owner(memid)?, desc.scna);
+        assert!(mem_unexport(memid, ObmmUnexportFlags::empty()).is_ok());
+        assert!(mem_query_owner(memid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_flags_compatible() {
+        assert!(flags_compatible(ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::REMOTENUMA, ObmmImportFlags::all()).is_ok());
+        assert!(matches!(
+            flags_compatible(ObmmExportFlags::REMOTENUMA, ObmmImportFlags::ALLOWMMAP),
+            Err(ObmmError::IncompatibleFlags(_))
+        ));
+        assert!(matches!(
+            flags_compatible(ObmmExportFlags::ALLOWMMAP, ObmmImportFlags::ALLOWMMAP | ObmmImportFlags::REMOTENUMA),
+            Err(ObmmError::IncompatibleFlags(_))
+        ));
+        assert!(flags_compatible(ObmmExportFlags::empty(), ObmmImportFlags::all()).is_ok());
+    }
+
+    #[test]
+    fn test_toml_round_trip() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData> {
+            addr: 0xffff_fc00_0000,
+            length: 1024 * 1024 * 128,
+            seid: [0xab; 16],
+            deid: [2; 16],
+            tokenid: 42,
+            priv_data: UbPrivData::OCHIP | UbPrivData::CACHEABLE,
+            per_node: Some([8 * 1024 * 1024; MAX_NUMA_NODES]),
+            ..ObmmMemDesc::default()
+        };
+        let toml_str = desc.to_toml()?;
+        assert!(toml_str.contains(r#"seid = "abababababababababababababababab""#));
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_toml(&toml_str)?, desc);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_c_json() -> anyhow::Result<()> {
+        let c_json = r#"{
+            "addr": "0xfffffc000000",
+            "length": 134217728,
+            "seid": "0x0000000000000000000000000000abcd",
+            "deid": "1",
+            "tokenid": 7,
+            "scna": 1,
+            "dcna": 2,
+            "priv_len": 2,
+            "priv_data": 96,
+            "flags": 2,
+            "driver_private": "ignored"
+        }"#;
+        let desc = ObmmMemDesc::<UbPrivData>::from_c_json(c_json)?;
+        assert_eq!((desc.addr, desc.length), (0xffff_fc00_0000, 128 * 1024 * 1024));
+        assert_eq!((desc.seid_u128(), desc.deid_u128()), (0xabcd, 1));
+        assert_eq!((desc.tokenid, desc.scna, desc.dcna), (7, 1, 2));
+        assert_eq!(desc.priv_data, UbPrivData::OCHIP | UbPrivData::CACHEABLE);
+        assert_eq!(desc.export_flags, ObmmExportFlags::REMOTENUMA);
+        assert_eq!(desc.schema_version, OBMM_DESC_SCHEMA_VERSION);
+        assert_eq!(desc.sanity_check(), Ok(()));
+
+        assert!(ObmmMemDesc::<UbPrivData>::from_json(c_json).is_err());
+        assert!(ObmmMemDesc::<UbPrivData>::from_c_json(&c_json.replace(r#""flags": 2"#, r#""flags": 4096"#)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_fixture() -> anyhow::Result<()> {
+        let fixture = r#"
+            # exported on node 1
+            addr = 0xffff_fc00_0000
+            length = 134217728
+            seid = "000102030405060708090a0b0c0d0e0f"
+            deid = "FFEEDDCCBBAA99887766554433221100"
+            tokenid = 42
+            scna = 3
+            dcna = 4
+            priv_len = 2
+            priv_data = "CACHEABLE"
+        "#;
+        let desc = ObmmMemDesc::<UbPrivData>::from_toml(fixture)?;
+        assert_eq!(desc.seid, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(desc.deid[0], 0xff);
+        assert_eq!(desc.deid[15], 0);
+        assert_eq!(desc.priv_data, UbPrivData::CACHEABLE);
+        assert_eq!(desc.schema_version, 1);
+        assert!(ObmmMemDesc::<UbPrivData>::from_toml(&fixture.replace("0e0f", "0e")).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "hook")]
+    #[test]
+    fn test_is_exported() -> anyhow::Result<()> {
+        let addr = 0x6100_0000_0000;
+        let (memid, _) = mem_export_at::<UbPrivData>(addr, 0x4000, ObmmExportFlags::empty())?;
+        assert_eq!(is_exported(addr + 0x1234)?, Some((memid, 0x1234)));
+        assert_eq!(is_exported(addr + 0x4000)?, None);
+        assert_eq!(is_exported(addr - 1)?, None);
+
+        set_hook_pa_owner(u64::MAX - 1, 0x6200_0000_0000, 0x1000);
+        assert_eq!(is_exported(0x6200_0000_0010)?, Some((u64::MAX - 1, 0x10)));
+        set_hook_pa_owner(u64::MAX - 1, 0x6200_0000_0000, 0);
+        assert_eq!(is_exported(0x6200_0000_0010)?, None);
+
+        mem_unexport(memid, ObmmUnexportFlags::empty())?;
+        assert_eq!(is_exported(addr + 0x1234)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_numa_lengths() {
+        let mut expected = [0; MAX_NUMA_NODES];
+        expected[1] = 128 * MB;
+        expected[3] = 2 * GB;
+        assert_eq!(numa_lengths! { 1 => 128 * MB, 3 => 2 * GB }, expected);
+        assert_eq!(numa_lengths! { 1 => MB, 1 => 128 * MB, 3 => 2 * GB, }, expected);
+        assert_eq!(numa_lengths! {}, [0; MAX_NUMA_NODES]);
+        assert_eq!(numa_lengths! { MAX_NUMA_NODES - 1 => GB }.last(), Some(&GB));
+    }
+
+    #[test]
+    fn test_transfer_json() -> anyhow::Result<()> {
+        let (memid, mut desc) = mem_export_at::<UbPrivData>(0xffff_ff00_0000, 0x20_0000, ObmmExportFlags::empty())?;
+        desc.priv_data = UbPrivData::CACHEABLE;
+        let json = desc.to_transfer_json()?;
+        let fields: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)?;
+        assert!(!fields.contains_key("per_node") && !fields.contains_key("lifetime") && !fields.contains_key("expires_at"));
+        assert!(fields.contains_key("addr") && fields.contains_key("tokenid") && fields.contains_key("priv_data"));
+
+        let remote = ObmmMemDesc::<UbPrivData>::from_transfer_json(&json)?;
+        assert_eq!(remote.per_node, None);
+        assert_eq!((remote.addr, remote.length, remote.seid, remote.tokenid), (desc.addr, desc.length, desc.seid, desc.tokenid));
+        assert_eq!((remote.priv_data, remote.priv_len), (UbPrivData::CACHEABLE, desc.priv_len));
+        let (imported, _) = mem_import(&remote, ObmmImportFlags::empty(), 0)?;
+        mem_unimport(imported, ObmmUnimportFlags::empty())?;
+        mem_unexport(memid, ObmmUnexportFlags::empty())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_cacheable() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).priv_data(UbPrivData::OCHIP).build()?;
+        let (memid, _) = mem_import(&desc, ObmmImportFlags::empty(), 0)?;
+        assert!(!mem_is_cacheable(memid)?);
+        mem_set_cacheable(memid, true)?;
+        assert!(mem_is_cacheable(memid)?);
+        let tracked = lookup_descriptor(memid).ok_or_else(|| anyhow::anyhow!("MemID {memid} not tracked"))?;
+        assert_eq!(tracked.priv_data, bincode::serialize(&(UbPrivData::OCHIP | UbPrivData::CACHEABLE))?);
+        mem_set_cacheable(memid, true)?;
+        mem_set_cacheable(memid, false)?;
+        assert!(!mem_is_cacheable(memid)?);
+
+        mem_unimport(memid, ObmmUnimportFlags::empty())?;
+        assert_eq!(mem_is_cacheable(memid), Err(ObmmError::UnknownMemId(memid)));
+        assert_eq!(mem_set_cacheable(memid, true), Err(ObmmError::UnknownMemId(memid)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_unpin() -> anyhow::Result<()> {
+        let (memid, _) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty())?;
+        assert_eq!(mem_unpin(memid), Err(ObmmError::NotPinned(memid)));
+        mem_pin(memid)?;
+        mem_pin(memid)?;
+        mem_unpin(memid)?;
+        assert_eq!(mem_unpin(memid), Err(ObmmError::NotPinned(memid)));
+
+        assert!(mem_unexport(memid, ObmmUnexportFlags::empty()).is_ok());
+        assert_eq!(mem_pin(memid), Err(ObmmError::UnknownMemId(memid)));
+        assert_eq!(mem_unpin(memid), Err(ObmmError::UnknownMemId(memid)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_inflated_priv_len() -> anyhow::Result<()> {
+        let mut desc = ObmmMemDesc::<UbPrivData>::builder().addr(UB_ADDR_WINDOW_START).build()?;
+        assert!(desc.validate_priv_len().is_ok());
+        desc.priv_len = 512;
+        assert_eq!(
+            mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0),
+            Err(ObmmError::PrivLenMismatch { declared: 512, actual: Some(2) })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(0x40_0000)
+            .seid([1; 16])
+            .priv_data(UbPrivData::CACHEABLE)
+            .build()?;
+        let parts = desc.split(&[0x10_0000])?;
+        let ranges: Vec<(u64, u64)> = parts.iter().map(|part| (part.addr, part.length)).collect();
+        assert_eq!(ranges, [(0xffff_fc00_0000, 0x10_0000), (0xffff_fc10_0000, 0x30_0000)]);
+        assert!(parts.iter().all(|part| part.seid == desc.seid && part.priv_data == desc.priv_data));
+
+        assert!(desc.split(&[0x20_0000, 0x10_0000]).is_err());
+        assert!(desc.split(&[0x10_0000, 0x10_0000]).is_err());
+        assert!(desc.split(&[0x40_0000]).is_err());
+        assert!(desc.split(&[0]).is_err());
+        Ok(())
     }
-}
 
+    #[test]
+    fn test_try_from_bytes() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(1024 * 1024 * 128)
+            .priv_data(UbPrivData::OCHIP)
+            .build()?;
+        let bytes: Vec<u8> = desc.try_into()?;
+        assert_eq!(bytes, desc.to_bytes()?);
+        assert_eq!(ObmmMemDesc::<UbPrivData>::try_from(bytes.as_slice())?, desc);
 
-/// Unimport memory region
-/// # Arguments
-/// * `memid` - Memory ID to unimport
-/// * `flags` - Unimport flags
-/// # Returns
-/// Ok(()) on success, Err(i32) on failure
-#[cfg(not(feature = "hook"))]
-pub fn mem_unimport(memid: MemId, flags: ObmmExportFlags) -> Result<(), i32> {
-    let ret = unsafe { obmm_unimport(memid, flags.bits()) };
-    if ret == 0 {
+        let truncated = bytes.get(..bytes.len() / 2).unwrap_or_default();
+        assert!(matches!(ObmmMemDesc::<UbPrivData>::try_from(truncated), Err(ObmmError::Codec(_))));
         Ok(())
-    } else {
-        Err(ret)
     }
-}
 
-// FFI bindings to OBMM C library
-unsafe extern "C" {
-    /// Export memory regions for remote access
-    ///
-    /// # Arguments
-    /// * `length` - Array of lengths for each NUMA node
-    /// * `flags` - Export flags
-    /// * `desc` - Output memory descriptor
-    ///
-    /// # Returns
-    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
-    pub fn obmm_export(
-        length: *const usize,
-        flags: u64,
-        desc: *mut c_void,
-    ) -> MemId;
+    #[test]
+    fn test_json_file_ttl() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).build()?;
+        desc.to_json_file_with_ttl(dir.path(), 1, Duration::ZERO)?;
+        let result = ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 1);
+        assert!(result.is_err_and(|e| e.downcast_ref::<ObmmError>() == Some(&ObmmError::Expired(1))));
 
-    /// Unexport previously exported memory region
-    ///
-    /// # Arguments
-    /// * `id` - Memory ID to unexport
-    /// * `flags` - Unexport flags
-    ///
-    /// # Returns
-    /// 0 on success, -1 on failure
-    pub fn obmm_unexport(id: MemId, flags: u64) -> i32;
+        desc.to_json_file_with_ttl(dir.path(), 2, Duration::from_secs(90))?;
+        let read = ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 2)?;
+        assert!(read.expires_at.is_some());
+        assert!(read.same_region(&desc));
+        Ok(())
+    }
 
-    /// Import remote memory region
-    ///
-    /// # Arguments
-    /// * `desc` - Memory descriptor from remote
-    /// * `flags` - Import flags
-    /// * `base_dist` - Base distribution hint
-    /// * `numa` - Output NUMA node ID
-    ///
-    /// # Returns
-    /// Memory ID on success, `OBMM_INVALID_MEMID` on failure
-    pub fn obmm_import(
-        desc: *const c_void,
-        flags: u64,
-        base_dist: i32,
-        numa: *mut i32,
-    ) -> MemId;
+    #[test]
+    fn test_unsupported_export_flags() {
+        let flags = ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::from_bits_retain(1 << 40);
+        let result = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], flags);
+        let err = result.err().and_then(|e| e.downcast::<ObmmError>().ok());
+        assert_eq!(err, Some(ObmmError::UnsupportedFlags("0x10000000000".to_owned())));
+        assert_eq!(err.map(|e| e.to_string()), Some("unsupported export flags: 0x10000000000".to_owned()));
+    }
 
-    /// Unimport previously imported memory region
-    ///
-    /// # Arguments
-    /// * `id` - Memory ID to unimport
-    /// * `flags` - Unimport flags
-    ///
-    /// # Returns
-    /// 0 on success, -1 on failure
-    pub fn obmm_unimport(id: MemId, flags: u64) -> i32;
+    #[test]
+    fn test_export_verbose_unsupported_flag() -> anyhow::Result<()> {
+        let flags = ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::from_bits_retain(1 << 40);
+        let outcome = mem_export_verbose::<UbPrivData>(&numa_lengths! { 0 => 7 * 4096 }, flags)?;
+        assert!(outcome.warnings.iter().any(|warning| warning.contains("0x10000000000")));
+        assert_eq!(outcome.desc.export_flags, ObmmExportFlags::ALLOWMMAP);
+        mem_unexport(outcome.memid, ObmmUnexportFlags::empty())?;
+        assert!(mem_export::<UbPrivData>(&numa_lengths! { 0 => 7 * 4096 }, flags).is_err());
+        Ok(())
+    }
 
-    /* debug interface */
-    
-    /// Query memory ID by physical address
-    ///
-    /// # Arguments
-    /// * `pa` - Physical address
-    /// * `id` - Output memory ID
-    /// * `offset` - Output offset within memory region
-    ///
-    /// # Returns
-    /// 0 on success, -1 on failure
-    pub fn obmm_query_memid_by_pa(
-        pa: u64,
-        id: *mut MemId,
-        offset: *mut u64,
-    ) -> i32;
+    #[test]
+    fn test_refresh() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).build()?;
+        desc.to_json_file_with_ttl(dir.path(), 3, Duration::from_secs(90))?;
+        let mut cached = ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 3)?;
+        assert!(!cached.refresh(dir.path(), 3)?);
 
-    /// Query physical address by memory ID and offset
-    ///
-    /// # Arguments
-    /// * `id` - Memory ID
-    /// * `offset` - Offset within memory region
-    /// * `pa` - Output physical address
-    ///
-    /// # Returns
-    /// 0 on success, -1 on failure
-    pub fn obmm_query_pa_by_memid(
-        id: MemId,
-        offset: u64,
-        pa: *mut u64,
-    ) -> i32;
-}
+        let mut restarted = cached;
+        restarted.addr = 0xffff_fc10_0000;
+        assert_eq!(cached.diff(&restarted), ["addr"]);
+        std::fs::write(dir.path().join("memdesc_3.json"), restarted.to_json()?)?;
+        assert!(cached.refresh(dir.path(), 3)?);
+        assert_eq!(cached.addr, 0xffff_fc10_0000);
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_json_file_atomic_replace() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.to_json_file_cas(dir.path(), 4, None)?;
+        let stop = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| -> anyhow::Result<()> {
+            let reader = scope.spawn(|| -> anyhow::Result<()> {
+                while !stop.load(Ordering::Relaxed) {
+                    let read = ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 4)?;
+                    assert_eq!(read.length, 128 * 1024 * 1024);
+                }
+                Ok(())
+            });
+            for tokenid in 1..200 {
+                let mut next = desc;
+                next.tokenid = tokenid;
+                next.to_json_file_cas(dir.path(), 4, Some(&desc))?;
+                desc = next;
+            }
+            stop.store(true, Ordering::Relaxed);
+            reader.join().map_err(|_panic| anyhow::anyhow!("reader panicked"))?
+        })?;
+        let leftovers = std::fs::read_dir(dir.path())?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp") || entry.file_name().to_string_lossy().ends_with(".lock"))
+            .count();
+        assert_eq!(leftovers, 0);
+        Ok(())
+    }
 
     #[test]
-    fn test_export() -> anyhow::Result<()> {
+    fn test_json_file_cas_conflict() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.to_json_file_cas(dir.path(), 5, None)?;
+        let mut stale = desc;
+        stale.tokenid = 7;
+        let mismatch = desc.to_json_file_cas(dir.path(), 5, Some(&stale));
+        assert!(mismatch.is_err_and(|e| e.downcast_ref::<ObmmError>() == Some(&ObmmError::Conflict(5))));
+        let exists = desc.to_json_file_cas(dir.path(), 5, None);
+        assert!(exists.is_err_and(|e| e.downcast_ref::<ObmmError>() == Some(&ObmmError::Conflict(5))));
+        assert!(!dir.path().join("memdesc_5.json.lock").exists());
+        stale.to_json_file_cas(dir.path(), 5, Some(&desc))?;
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 5)?, stale);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_file_cas_concurrent() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.tokenid = 0;
+        desc.to_json_file_cas(dir.path(), 6, None)?;
+        std::thread::scope(|scope| {
+            let writers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| -> anyhow::Result<()> {
+                        for _ in 0..25 {
+                            loop {
+                                let current = ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 6)?;
+                                let next = ObmmMemDesc { tokenid: current.tokenid.saturating_add(1), ..current };
+                                match next.to_json_file_cas(dir.path(), 6, Some(&current)) {
+                                    Err(err) if err.downcast_ref::<ObmmError>() == Some(&ObmmError::Conflict(6)) => {}
+                                    written => break written?,
+                                }
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            writers.into_iter().try_for_each(|writer| writer.join().map_err(|_panic| anyhow::anyhow!("writer panicked"))?)
+        })?;
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json_file_checked(dir.path(), 6)?.tokenid, 100);
+        assert!(!dir.path().join("memdesc_6.json.lock").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unexport_unimport_flags_conversion() {
+        let force = ObmmUnimportFlags::from(ObmmUnexportFlags::FORCE);
+        assert_eq!(force, ObmmUnimportFlags::FORCE);
+        assert_eq!(ObmmUnexportFlags::from(force), ObmmUnexportFlags::FORCE);
+
+        let extra = ObmmUnexportFlags::FORCE | ObmmUnexportFlags::from_bits_retain(0b110);
+        assert_eq!(ObmmUnimportFlags::from(extra), ObmmUnimportFlags::FORCE);
+        assert_eq!(ObmmUnexportFlags::from(ObmmUnimportFlags::from_bits_retain(0b110)), ObmmUnexportFlags::empty());
+    }
+
+    #[test]
+    fn test_example_passes_sanity_check() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.sanity_check()?;
+        desc.validate_address()?;
+        assert_eq!(desc.length, 128 * 1024 * 1024);
+        assert_eq!(ObmmMemDesc::<UbPrivData>::default().sanity_check(), Err(ObmmError::ZeroLength));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hook")]
+    fn test_export_with_ids() -> anyhow::Result<()> {
         let mut lengths = vec![0; MAX_NUMA_NODES];
-        if let Some(v) = lengths.get_mut(0) {
-            *v = 1024 * 1024 * 64; // 64MB on NUMA node 0
+        if let Some(v) = lengths.get_mut(1) {
+            *v = 1024 * 1024;
         }
-        let flags = ObmmExportFlags::ALLOWMMAP;
-        match mem_export::<UbPrivData>(&lengths, flags) {
-            Ok((memid, desc)) => {
-                println!("Exported MemID: {memid}");
-                println!("Memory Descriptor: {desc:?}");
-                assert!(memid != OBMM_INVALID_MEMID);
-                assert!(desc.length == 1024 * 1024 * 128);
-                Ok(())
-            }
-            Err(code) => {
-                Err(anyhow::anyhow!("mem_export failed with code {code}"))
-            }
+        let seid = 0x8000_0000_0000_0000_0000_0000_0000_0001;
+        let (memid, desc) = mem_export_with_ids::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, seid, 2, 42)?;
+        assert_eq!((desc.seid_u128(), desc.deid_u128(), desc.tokenid), (seid, 2, 42));
+        let found = lookup_descriptor(memid).map(|found| (found.seid_u128(), found.deid_u128(), found.tokenid));
+        assert_eq!(found, Some((seid, 2, 42)));
+        assert!(mem_export_with_ids::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, 3, 3, 42).is_err());
+        mem_unexport(memid, ObmmUnexportFlags::empty()).map_err(|code| anyhow::anyhow!("mem_unexport failed with code {code}"))
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_malformed() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        let bytes = desc.to_bytes()?;
+        for len in 0..bytes.len() {
+            assert!(ObmmMemDesc::<UbPrivData>::from_bytes(bytes.get(..len).unwrap_or_default()).is_err(), "truncated to {len}");
+        }
+
+        let mut inflated = desc;
+        inflated.priv_len = 9;
+        let mismatch = ObmmMemDesc::<UbPrivData>::try_from(inflated.to_bytes()?.as_slice());
+        assert_eq!(mismatch, Err(ObmmError::PrivLenMismatch { declared: 9, actual: Some(2) }));
+
+        let mut unknown = desc;
+        unknown.export_flags = ObmmExportFlags::from_bits_retain(1 << 40);
+        let bad_flags = ObmmMemDesc::<UbPrivData>::try_from(unknown.to_bytes()?.as_slice());
+        assert_eq!(bad_flags, Err(ObmmError::UnknownFlags("0x10000000000".to_owned())));
+
+        // Forge the length prefix of a `Vec<u8>` privilege data, which follows
+        // the 62 bytes of fixed-size fields
+        let vec_desc = ObmmMemDesc::<Vec<u8>>::builder().priv_data(vec![1, 2, 3]).build()?;
+        let mut forged = vec_desc.to_bytes()?;
+        for (byte, forged_byte) in forged.iter_mut().skip(62).zip(u64::MAX.to_le_bytes()) {
+            *byte = forged_byte;
         }
+        assert!(ObmmMemDesc::<Vec<u8>>::from_bytes(&forged).is_err());
+        Ok(())
     }
 
     #[test]
-    fn test_import() -> anyhow::Result<()> {
-        let desc = ObmmMemDesc::<UbPrivData> {
-            addr: 0xffff_fc00_0000,
-            length: 1024 * 1024 * 128,
-            seid: [0; 16],
-            deid: [0; 16],
-            tokenid: 0,
-            scna: 0,
-            dcna: 0,
-            priv_len: 0,
-            priv_data: UbPrivData::default(),
-        };
-        let flags = ObmmExportFlags::ALLOWMMAP;
-        match mem_import(&desc, flags, 0) {
-            Ok((memid, numa)) => {
-                println!("Imported MemID: {memid}, NUMA Node: {numa}");
-                assert!(memid != OBMM_INVALID_MEMID);
-                Ok(())
-            }
-            Err(code) => {
-                Err(anyhow::anyhow!("mem_import failed with code {code}"))
+    fn test_from_bytes_random_input_never_panics() -> anyhow::Result<()> {
+        use rand::{RngCore as _, SeedableRng as _};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x6f62_6d6d);
+        let valid = ObmmMemDesc::<Vec<u8>>::builder().priv_data(vec![7; 16]).build()?.to_bytes()?;
+        for _ in 0..10_000 {
+            let mut bytes = vec![0; rng.random_range(0..256)];
+            rng.fill_bytes(&mut bytes);
+            let _plain = ObmmMemDesc::<UbPrivData>::from_bytes(&bytes);
+            let _vec = ObmmMemDesc::<Vec<u8>>::from_bytes(&bytes);
+
+            let mut mutated = valid.clone();
+            if let Some(byte) = mutated.get_mut(rng.random_range(0..valid.len())) {
+                *byte = u8::try_from(rng.next_u32() & 0xff)?;
             }
+            let _mutated = ObmmMemDesc::<Vec<u8>>::from_bytes(&mutated);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_consistency() {
+        let mut per_node = [0; MAX_NUMA_NODES];
+        if let Some(first) = per_node.first_mut() {
+            *first = 4096;
+        }
+        let consistent = ObmmMemDesc::<UbPrivData>::builder().length(4096).per_node(per_node).build();
+        assert!(consistent.is_ok());
+        assert!(ObmmMemDesc::<UbPrivData>::builder().length(4096).build().is_ok());
+
+        let mismatched = ObmmMemDesc::<UbPrivData>::builder().length(8192).per_node(per_node).build();
+        assert!(mismatched.is_err_and(|e| {
+            e.downcast_ref::<ObmmError>() == Some(&ObmmError::LengthMismatch { length: 8192, per_node_sum: Some(4096) })
+        }));
+
+        let mut desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.per_node = Some([u64::MAX / 2; MAX_NUMA_NODES]);
+        assert_eq!(
+            desc.check_length_consistency(),
+            Err(ObmmError::LengthMismatch { length: 128 * 1024 * 1024, per_node_sum: None })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "hook")]
+    fn test_pages() -> anyhow::Result<()> {
+        let pages = 4_u64;
+        let (memid, desc) = mem_export_at::<UbPrivData>(0x7f00_0000_0000, usize::try_from(pages * page_size())?, ObmmExportFlags::empty())?;
+        let walked = desc.pages(memid).collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(walked.len(), usize::try_from(pages)?);
+        let first_pa = walked.first().map(|&(_, pa)| pa).ok_or_else(|| anyhow::anyhow!("no pages"))?;
+        for (index, &(offset, pa)) in (0_u64..).zip(&walked) {
+            assert_eq!(offset, index * page_size());
+            assert_eq!(pa, first_pa + offset);
         }
+        mem_unexport(memid, ObmmUnexportFlags::empty())?;
+        assert!(desc.pages(memid).all(|page| page.is_err()));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hook")]
+    fn test_swap_import() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex, PoisonError};
+
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        let (old, _) = mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0)?;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let subscription = subscribe(move |event| sink.lock().unwrap_or_else(PoisonError::into_inner).push(event));
+
+        let mut unaligned = desc;
+        unaligned.addr += 1;
+        assert!(mem_swap_import(old, &unaligned, ObmmImportFlags::ALLOWMMAP, 0).is_err());
+        assert!(lookup_descriptor(old).is_some());
+
+        let new = mem_swap_import(old, &desc, ObmmImportFlags::ALLOWMMAP, 0)?;
+        assert!(unsubscribe(subscription));
+        assert!(lookup_descriptor(old).is_none());
+        let events: Vec<ObmmEvent> = seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .copied()
+            .filter(|&event| matches!(event, ObmmEvent::Imported { memid, .. } | ObmmEvent::Unimported { memid } if memid == old || memid == new))
+            .collect();
+        assert_eq!(events, [ObmmEvent::Imported { memid: new, node: 0 }, ObmmEvent::Unimported { memid: old }]);
+
+        assert!(mem_swap_import(old, &desc, ObmmImportFlags::ALLOWMMAP, 0).is_err());
+        mem_unimport(new, ObmmUnimportFlags::empty())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_debug() {
+        let mut desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.seid = [1; 16];
+        desc.set_deid(0xabcd);
+        let compact = format!("{desc:?}");
+        assert!(compact.starts_with("ObmmMemDesc { addr: 0xfffffc000000, length: 0x8000000, seid: 0x0101..0101, deid: 0xcdab..0000, tokenid: 0,"));
+        let full = format!("{desc:#?}");
+        assert!(full.contains("addr: 281474909601792,"));
+        assert!(full.contains("seid: [\n        1,\n"));
+        assert!(!full.contains(".."));
+    }
+
+    #[test]
+    fn test_import_flags_from_str() {
+        assert_eq!("ALLOWMMAP | REMOTENUMA".parse(), Ok(ObmmImportFlags::ALLOWMMAP | ObmmImportFlags::REMOTENUMA));
+        assert_eq!("".parse(), Ok(ObmmImportFlags::empty()));
+        assert!("MMAP".parse::<ObmmImportFlags>().is_err());
+    }
+
+    #[test]
+    fn test_flag_names() {
+        let flags = ObmmExportFlags::READONLY | ObmmExportFlags::ALLOWMMAP;
+        assert_eq!(flags.flag_names().collect::<Vec<_>>(), ["ALLOWMMAP", "READONLY"]);
+        assert_eq!(ObmmExportFlags::all().flag_names().collect::<Vec<_>>(), ["ALLOWMMAP", "REMOTENUMA", "READONLY"]);
+        assert_eq!(ObmmExportFlags::from_bits_retain(1 << 40).flag_names().count(), 0);
+        assert_eq!((UbPrivData::CACHEABLE | UbPrivData::OCHIP).flag_names().collect::<Vec<_>>(), ["OCHIP", "CACHEABLE"]);
+        assert_eq!(ObmmUnimportFlags::FORCE.flag_names().collect::<Vec<_>>(), ["FORCE"]);
+    }
+
+    #[test]
+    fn test_from_bits_validated() {
+        assert_eq!(ObmmExportFlags::from_bits_validated(0b101), Ok(ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::READONLY));
+        assert_eq!(ObmmImportFlags::from_bits_validated(0b110), Ok(ObmmImportFlags::PREIMPORT | ObmmImportFlags::REMOTENUMA));
+        assert_eq!(ObmmUnexportFlags::from_bits_validated(1), Ok(ObmmUnexportFlags::FORCE));
+        assert_eq!(ObmmUnimportFlags::from_bits_validated(0), Ok(ObmmUnimportFlags::empty()));
+
+        let high = (1 << 63) | 1;
+        assert_eq!(ObmmExportFlags::from_bits_validated(high), Err(ObmmError::UnknownFlags("0x8000000000000000".to_owned())));
+        assert_eq!(ObmmImportFlags::from_bits_validated(high), Err(ObmmError::UnknownFlags("0x8000000000000000".to_owned())));
+        assert_eq!(ObmmUnexportFlags::from_bits_validated(0b11), Err(ObmmError::UnknownFlags("0x2".to_owned())));
+        assert_eq!(ObmmUnimportFlags::from_bits_validated(high), Err(ObmmError::UnknownFlags("0x8000000000000000".to_owned())));
+        assert_eq!(UbPrivData::from_bits_validated(1 << 15), Err(ObmmError::UnknownFlags("0x8000".to_owned())));
+
+        let undefined = ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::from_bits_retain(1 << 63);
+        assert_eq!(flags_compatible(undefined, ObmmImportFlags::ALLOWMMAP), Err(ObmmError::UnknownFlags("0x8000000000000000".to_owned())));
+    }
+
+    #[test]
+    fn test_canonical_fingerprint() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(4096)
+            .priv_data(UbPrivData::OCHIP)
+            .build()?;
+        let mut drifted = desc;
+        drifted.priv_len = 0;
+        assert_ne!(desc.to_bytes()?, drifted.to_bytes()?);
+        assert_eq!(desc.fingerprint()?, drifted.fingerprint()?);
+
+        drifted.canonicalize();
+        assert_eq!(drifted, desc);
+
+        let mut other = desc;
+        other.length = 8192;
+        assert_ne!(desc.fingerprint()?, other.fingerprint()?);
+
+        let mut expiring = desc;
+        expiring.expires_at = Some(SystemTime::UNIX_EPOCH);
+        assert_ne!(desc.fingerprint()?, expiring.fingerprint()?);
+        expiring.canonicalize();
+        assert_eq!(expiring.expires_at, Some(SystemTime::UNIX_EPOCH));
+        let persistent = ObmmMemDesc { lifetime: LifetimeClass::Persistent, ..desc };
+        assert_ne!(desc.fingerprint()?, persistent.fingerprint()?);
+        Ok(())
     }
 
     #[test]
@@ -509,6 +4048,7 @@ mod tests {
             dcna: 4,
             priv_len: 2,
             priv_data: UbPrivData::OCHIP | UbPrivData::CACHEABLE,
+            ..ObmmMemDesc::default()
         };
         let json_str = desc.to_json()?;
         println!("Serialized JSON: {json_str}");
@@ -525,6 +4065,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_priv_data_format_matrix() -> anyhow::Result<()> {
+        let flags = UbPrivData::OCHIP | UbPrivData::CACHEABLE;
+        let desc = ObmmMemDesc::<UbPrivData>::builder().priv_data(flags).build()?;
+
+        let json = desc.to_json()?;
+        assert!(json.contains(r#""priv_data":"OCHIP | CACHEABLE""#));
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json(&json)?.priv_data, flags);
+
+        assert_eq!(bincode::serialize(&flags)?, flags.bits().to_le_bytes());
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_bytes(&desc.to_bytes()?)?.priv_data, flags);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lifetime_serde() -> anyhow::Result<()> {
+        for lifetime in [LifetimeClass::Ephemeral, LifetimeClass::Session, LifetimeClass::Persistent] {
+            let desc = ObmmMemDesc::<UbPrivData>::builder().lifetime(lifetime).build()?;
+            assert_eq!(ObmmMemDesc::<UbPrivData>::from_json(&desc.to_json()?)?.lifetime, lifetime);
+            assert_eq!(ObmmMemDesc::<UbPrivData>::from_bytes(&desc.to_bytes()?)?.lifetime, lifetime);
+        }
+        let old = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/vectors/desc.json"))?;
+        assert!(!old.contains("lifetime"));
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json(&old)?.lifetime, LifetimeClass::Session);
+        Ok(())
+    }
+
+    #[test]
+    fn test_descs_json_document() -> anyhow::Result<()> {
+        let descs: Vec<(MemId, ObmmMemDesc<UbPrivData>)> = [(12, 0x2000), (3, 0x1000), (7, 0x3000)]
+            .into_iter()
+            .map(|(memid, length)| Ok((memid, ObmmMemDesc::builder().addr(0xffff_fc00_0000).length(length).build()?)))
+            .collect::<anyhow::Result<_>>()?;
+        let json = descs_to_json(&descs)?;
+        let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)?;
+        assert_eq!(object.len(), 3);
+        for &(memid, ref desc) in &descs {
+            assert_eq!(object.get(&memid.to_string()), Some(&serde_json::to_value(desc)?));
+        }
+        let positions: Vec<Option<usize>> = ["3", "7", "12"].iter().map(|key| json.find(&format!(r#""{key}":"#))).collect();
+        assert!(positions.iter().all(Option::is_some) && positions.is_sorted());
+
+        let mut sorted = descs.clone();
+        sorted.sort_unstable_by_key(|&(memid, _)| memid);
+        assert_eq!(descs_from_json::<UbPrivData>(&json)?, sorted);
+        let duplicated: Vec<_> = descs.iter().chain(&descs).copied().collect();
+        assert!(descs_to_json(&duplicated).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_deserialization() -> anyhow::Result<()> {
         let json_str = r#"{
@@ -547,6 +4137,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_versioned_deserialization() -> anyhow::Result<()> {
+        let v1 = r#"{
+            "addr": 281474909601792,
+            "length": 134217728,
+            "seid": [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],
+            "deid": [2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2],
+            "tokenid": 42,
+            "scna": 3,
+            "dcna": 4,
+            "priv_len": 2,
+            "priv_data": "OCHIP | CACHEABLE"
+        }"#;
+        let desc = ObmmMemDesc::<UbPrivData>::from_json_versioned(v1)?;
+        assert_eq!(desc.length, 1024 * 1024 * 128);
+        assert_eq!(desc.per_node, None);
+        assert_eq!(desc.schema_version, OBMM_DESC_SCHEMA_VERSION);
+
+        let mut per_node = [0; MAX_NUMA_NODES];
+        if let Some(v) = per_node.get_mut(1) {
+            *v = 1024 * 1024 * 128;
+        }
+        let mut expected = desc;
+        expected.per_node = Some(per_node);
+        let v2 = expected.to_json()?;
+        assert!(v2.contains(r#""schema_version":2"#));
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_json_versioned(&v2)?, expected);
+
+        let v3 = v2.replace(r#""schema_version":2"#, r#""schema_version":3"#);
+        assert!(ObmmMemDesc::<UbPrivData>::from_json_versioned(&v3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reused_buffer_serialization() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder()
+            .addr(0xffff_fc00_0000)
+            .length(1024 * 1024 * 128)
+            .tokenid(42)
+            .priv_data(UbPrivData::OCHIP | UbPrivData::CACHEABLE)
+            .build()?;
+        let mut json = String::from("stale contents");
+        let mut bytes = vec![0xff; 4];
+        for _ in 0..2 {
+            desc.to_json_into(&mut json)?;
+            desc.to_bytes_into(&mut bytes)?;
+            assert_eq!(json, desc.to_json()?);
+            assert_eq!(bytes, desc.to_bytes()?);
+        }
+        assert_eq!(ObmmMemDesc::<UbPrivData>::from_bytes(&bytes)?, desc);
+        Ok(())
+    }
+
     #[test]
     fn test_json_file_io() -> anyhow::Result<()> {
         let desc = ObmmMemDesc::<UbPrivData> {
@@ -559,6 +4202,7 @@ mod tests {
             dcna: 4,
             priv_len: 2,
             priv_data: UbPrivData::OCHIP | UbPrivData::CACHEABLE,
+            ..ObmmMemDesc::default()
         };
         let mem_id: MemId = 12345;
         desc.to_json_file(mem_id)?;
@@ -0,0 +1,322 @@
+//! Memory mappings of imported regions and exported files
+
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use anyhow::Context;
+use memmap2::{Mmap, MmapMut};
+use serde::Serialize;
+
+use crate::{ExportedMemory, MemId, ObmmError, ObmmExportFlags, ObmmMemDesc, mem_export_with};
+
+/// Backing mapping of a `MappedRegion` or `FileExport`
+#[derive(Debug)]
+enum Mapping {
+    /// Read-only mapping
+    ReadOnly(Mmap),
+    /// Read-write mapping
+    ReadWrite(MmapMut),
+}
+
+/// An imported region mapped into memory, unmapped when dropped
+#[derive(Debug)]
+pub struct MappedRegion {
+    /// Memory ID of the import
+    memid: MemId,
+    /// Untagged base address of the region as exported
+    addr: u64,
+    /// Backing mapping
+    mapping: Mapping,
+}
+
+impl MappedRegion {
+    /// Map an imported region
+    ///
+    /// Regions exported with `ObmmExportFlags::READONLY` can only be mapped
+    /// read-only. Under the hook feature an anonymous mapping of the
+    /// descriptor's length stands in for the shared memory device.
+    /// # Arguments
+    /// * `memid` - Memory ID returned by `mem_import`
+    /// * `desc` - Descriptor the region was imported from
+    /// * `writable` - Map the region read-write instead of read-only
+    /// # Returns
+    /// # Errors
+    /// `MappedRegion` on success, `ObmmError::ReadOnly` for a writable map of a
+    /// read-only export, `anyhow::Error` if mapping fails
+    #[inline]
+    pub fn map<T>(memid: MemId, desc: &ObmmMemDesc<T>, writable: bool) -> anyhow::Result<Self> {
+        if writable && desc.export_flags.contains(ObmmExportFlags::READONLY) {
+            return Err(ObmmError::ReadOnly(memid).into());
+        }
+        let len = usize::try_from(desc.length)?;
+        let mapping = Self::map_backing(memid, len, writable)?;
+        Ok(MappedRegion { memid, addr: desc.untagged_addr(), mapping })
+    }
+
+    /// Anonymous mapping standing in for the shared memory device
+    #[cfg(feature = "hook")]
+    fn map_backing(_: MemId, len: usize, writable: bool) -> anyhow::Result<Mapping> {
+        let map = MmapMut::map_anon(len)?;
+        if writable {
+            Ok(Mapping::ReadWrite(map))
+        } else {
+            Ok(Mapping::ReadOnly(map.make_read_only()?))
+        }
+    }
+
+    /// Map the shared memory device the driver creates for an import
+    #[cfg(not(feature = "hook"))]
+    fn map_backing(memid: MemId, len: usize, writable: bool) -> anyhow::Result<Mapping> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .open(format!("/dev/obmm_shmdev{memid}"))?;
+        let mut options = memmap2::MmapOptions::new();
+        let _options = options.len(len);
+        // SAFETY: the device stays mapped for the lifetime of the mapping and
+        // concurrent remote modification is inherent to shared memory
+        if writable {
+            Ok(Mapping::ReadWrite(unsafe { options.map_mut(&file)? }))
+        } else {
+            Ok(Mapping::ReadOnly(unsafe { options.map(&file)? }))
+        }
+    }
+
+    /// Memory ID of the mapped import
+    #[inline]
+    #[must_use]
+    pub fn memid(&self) -> MemId {
+        self.memid
+    }
+
+    /// Base address of the region as exported, with pointer tag bits cleared
+    #[inline]
+    #[must_use]
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// Whether the region is mapped read-write
+    #[inline]
+    #[must_use]
+    pub fn is_writable(&self) -> bool {
+        matches!(self.mapping, Mapping::ReadWrite(_))
+    }
+
+    /// Contents of the region
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        match self.mapping {
+            Mapping::ReadOnly(ref map) => map,
+            Mapping::ReadWrite(ref map) => map,
+        }
+    }
+
+    /// Mutable contents of the region
+    /// # Returns
+    /// # Errors
+    /// Mutable slice on success, `ObmmError::ReadOnly` if mapped read-only
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> Result<&mut [u8], ObmmError> {
+        match self.mapping {
+            Mapping::ReadOnly(_) => Err(ObmmError::ReadOnly(self.memid)),
+            Mapping::ReadWrite(ref mut map) => Ok(map),
+        }
+    }
+
+    /// Copy a header value out of the start of the region
+    ///
+    /// The start of the region need not be aligned for `H`.
+    /// # Safety
+    /// Every byte pattern of `size_of::<H>()` bytes must be a valid `H`, as the
+    /// region contents come from the exporter: a plain `#[repr(C)]` type of
+    /// integers and arrays of them, without `bool`, `char`, enums, references
+    /// or pointers.
+    /// # Returns
+    /// # Errors
+    /// Header on success, `anyhow::Error` if the region is shorter than `H`
+    #[inline]
+    pub unsafe fn read_header<H: Copy>(&self) -> anyhow::Result<H> {
+        let bytes = self.as_slice();
+        Self::check_header_fits::<H>(bytes.len())?;
+        // SAFETY: the region holds at least `size_of::<H>()` bytes, the read
+        // tolerates any alignment and the caller guarantees they form a valid `H`
+        Ok(unsafe { bytes.as_ptr().cast::<H>().read_unaligned() })
+    }
+
+    /// Copy a header value into the start of the region
+    /// # Safety
+    /// `H` must have no padding bytes, as the region is later read back as
+    /// initialized bytes through `as_slice`.
+    /// # Arguments
+    /// * `header` - Header to write
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `ObmmError::ReadOnly` if mapped read-only,
+    /// `anyhow::Error` if the region is shorter than `H`
+    #[inline]
+    pub unsafe fn write_header<H: Copy>(&mut self, header: &H) -> anyhow::Result<()> {
+        let bytes = self.as_mut_slice()?;
+        Self::check_header_fits::<H>(bytes.len())?;
+        // SAFETY: the region holds at least `size_of::<H>()` writable bytes, the
+        // write tolerates any alignment and the caller guarantees `H` has no padding
+        unsafe { bytes.as_mut_ptr().cast::<H>().write_unaligned(*header) };
+        Ok(())
+    }
+
+    /// Check that a header of type `H` fits in a region of `len` bytes
+    fn check_header_fits<H>(len: usize) -> anyhow::Result<()> {
+        let size = size_of::<H>();
+        if size > len {
+            anyhow::bail!("Header of {size} bytes does not fit in a region of {len} bytes");
+        }
+        Ok(())
+    }
+}
+
+/// An exported file-backed mapping, unexported and then unmapped when dropped
+#[derive(Debug)]
+pub struct FileExport<T> {
+    /// Export of the mapping, declared first so it is unexported before unmapping
+    export: ExportedMemory<T>,
+    /// Mapping of the file
+    mapping: Mapping,
+}
+
+impl<T> FileExport<T> {
+    /// Memory ID of the export
+    #[inline]
+    #[must_use]
+    pub fn memid(&self) -> MemId {
+        self.export.memid()
+    }
+
+    /// Memory Descriptor of the export
+    #[inline]
+    #[must_use]
+    pub fn desc(&self) -> &ObmmMemDesc<T> {
+        self.export.desc()
+    }
+
+    /// Contents of the mapped file
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        match self.mapping {
+            Mapping::ReadOnly(ref map) => map,
+            Mapping::ReadWrite(ref map) => map,
+        }
+    }
+}
+
+impl<T: Default + Serialize> ObmmMemDesc<T> {
+    /// Map a file and export the mapping
+    ///
+    /// The whole file is mapped shared, read-only if `flags` contains
+    /// `ObmmExportFlags::READONLY`. The returned guard keeps the mapping alive
+    /// for as long as the export exists.
+    /// # Arguments
+    /// * `path` - File to export
+    /// * `flags` - Export flags
+    /// # Returns
+    /// # Errors
+    /// `FileExport` on success, `anyhow::Error` if the file can't be mapped or exported
+    #[inline]
+    pub fn from_mmap_file(path: &Path, flags: ObmmExportFlags) -> anyhow::Result<FileExport<T>> {
+        let writable = !flags.contains(ObmmExportFlags::READONLY);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        // SAFETY: the mapping is shared by design, other writers to the file are
+        // visible through it just as remote writes are
+        let (mapping, exported) = if writable {
+            let map = unsafe { MmapMut::map_mut(&file)? };
+            let exported = mem_export_with(&map, flags)?;
+            (Mapping::ReadWrite(map), exported)
+        } else {
+            let map = unsafe { Mmap::map(&file)? };
+            let exported = mem_export_with(&map, flags)?;
+            (Mapping::ReadOnly(map), exported)
+        };
+        let (memid, desc) = exported;
+        Ok(FileExport { export: ExportedMemory::from_parts(memid, desc), mapping })
+    }
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::{ADDR_TAG_MASK, MAX_NUMA_NODES, ObmmImportFlags, UbPrivData, mem_export, mem_import};
+
+    #[test]
+    fn test_from_mmap_file() -> anyhow::Result<()> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        std::io::Write::write_all(&mut file, &[0xa5; 8192])?;
+        let export = ObmmMemDesc::<UbPrivData>::from_mmap_file(file.path(), ObmmExportFlags::ALLOWMMAP)?;
+        assert_eq!(export.desc().length, 8192);
+        assert_eq!(export.as_slice(), &[0xa5; 8192]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_readonly_export_maps_read_only() -> anyhow::Result<()> {
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        if let Some(v) = lengths.get_mut(1) {
+            *v = 1024 * 1024;
+        }
+        let (_, desc) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP | ObmmExportFlags::READONLY)?;
+        let (memid, _) = mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0)?;
+
+        let result = MappedRegion::map(memid, &desc, true);
+        assert!(result.is_err_and(|e| e.downcast_ref::<ObmmError>() == Some(&ObmmError::ReadOnly(memid))));
+
+        let mut region = MappedRegion::map(memid, &desc, false)?;
+        assert!(!region.is_writable());
+        assert_eq!(region.as_slice().len(), 1024 * 1024);
+        assert!(region.as_mut_slice().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_round_trip() -> anyhow::Result<()> {
+        /// Header layout shared with a remote peer
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(C)]
+        struct Header {
+            /// Format magic
+            magic: u32,
+            /// Format version
+            version: u16,
+            /// Reserved, zero, so the header has no padding
+            reserved: u16,
+            /// Payload length
+            len: u64,
+        }
+
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).build()?;
+        let mut region = MappedRegion::map(1, &desc, true)?;
+        let header = Header { magic: 0x4f42_4d4d, version: 2, reserved: 0, len: 4000 };
+        // SAFETY: `Header` consists of integers only and has no padding
+        unsafe {
+            region.write_header(&header)?;
+            assert_eq!(region.read_header::<Header>()?, header);
+            assert!(region.read_header::<[u8; 8192]>().is_err());
+        }
+
+        let mut read_only = MappedRegion::map(1, &desc, false)?;
+        // SAFETY: as above
+        assert!(unsafe { read_only.write_header(&header) }.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_untags_addr() -> anyhow::Result<()> {
+        let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000 | ADDR_TAG_MASK).length(4096).build()?;
+        let region = MappedRegion::map(1, &desc, false)?;
+        assert_eq!(region.addr(), 0xffff_fc00_0000);
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+//! Typed handle to a directory of descriptor files
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::meta::local_hostname;
+use crate::{
+    DEFAULT_MEMLINK_DIR, ENV_MEMLINK_DIR, MemId, MemlinkFilename, MemlinkMeta, ObmmExportFlags, ObmmMemDesc, desc_file_path, mem_export,
+    write_file_atomic,
+};
+
+/// File mapping idempotency keys to Memory IDs, see `MemlinkDir::export_idempotent`
+const IDEMPOTENCY_FILE: &str = "idempotency.json";
+
+/// Version of the archive format written by `MemlinkDir::export_archive`
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Archive of every descriptor file of a directory
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    /// Archive format version
+    version: u32,
+    /// Descriptor JSON by Memory ID, kept as written so any `priv_data` type round-trips
+    descriptors: BTreeMap<MemId, serde_json::Value>,
+}
+
+/// What `MemlinkDir::import_archive` does with a Memory ID already in the directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveCollision {
+    /// Fail before writing anything
+    Error,
+    /// Replace the existing descriptor
+    Overwrite,
+}
+
+/// Differences between the descriptor sets of two directories, from `MemlinkDir::compare`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DirComparison {
+    /// Memory IDs with a descriptor only in the compared directory, in ascending order
+    pub only_in_self: Vec<MemId>,
+    /// Memory IDs with a descriptor only in the other directory, in ascending order
+    pub only_in_other: Vec<MemId>,
+    /// Memory IDs in both whose descriptors differ, with the differing field names
+    pub differing: Vec<(MemId, Vec<&'static str>)>,
+}
+
+impl DirComparison {
+    /// Whether both directories hold the same descriptors
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.differing.is_empty()
+    }
+}
+
+/// Directory holding `memdesc_<id>.json` descriptor files and their sidecars
+///
+/// File names come from `MemlinkFilename`, so callers pass Memory IDs instead of paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemlinkDir {
+    /// Path of the directory
+    path: PathBuf,
+}
+
+impl MemlinkDir {
+    /// Open the directory at `path`, creating it and its parents if absent
+    /// # Arguments
+    /// * `path` - Directory holding descriptor files
+    /// # Returns
+    /// # Errors
+    /// `MemlinkDir` on success, `anyhow::Error` if the directory can't be created
+    #[inline]
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        std::fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(MemlinkDir { path })
+    }
+
+    /// Path of the directory
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the descriptor of `mem_id`, replacing any previous one
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// * `desc` - Descriptor to write
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn save<T: Serialize>(&self, mem_id: MemId, desc: &ObmmMemDesc<T>) -> anyhow::Result<()> {
+        write_file_atomic(&desc_file_path(&self.path, mem_id), serde_json::to_string_pretty(desc)?.as_bytes())
+    }
+
+    /// Read the descriptor of `mem_id`, rejecting stale descriptors
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `ObmmError::Expired` if past its `expires_at`,
+    /// `anyhow::Error` on other failures
+    #[inline]
+    pub fn load<T>(&self, mem_id: MemId) -> anyhow::Result<ObmmMemDesc<T>>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de>,
+    {
+        ObmmMemDesc::from_json_file_checked(&self.path, mem_id)
+    }
+
+    /// Refresh the descriptor of `mem_id` so TTL-based collection keeps it
+    ///
+    /// The file's modification time is set to now. With `new_ttl`, its
+    /// `expires_at` is also moved to `new_ttl` from now, which is meant to be
+    /// called periodically by a heartbeat thread of the exporter.
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// * `new_ttl` - Time the descriptor stays fresh from now, `None` to keep `expires_at`
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` if the descriptor file is missing,
+    /// malformed or can't be written
+    #[inline]
+    pub fn touch(&self, mem_id: MemId, new_ttl: Option<Duration>) -> anyhow::Result<()> {
+        let file_path = desc_file_path(&self.path, mem_id);
+        let Some(ttl) = new_ttl else {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .open(&file_path)
+                .with_context(|| format!("Failed to open {}", file_path.display()))?;
+            return file.set_modified(SystemTime::now()).with_context(|| format!("Failed to touch {}", file_path.display()));
+        };
+        let json_str = std::fs::read_to_string(&file_path).with_context(|| format!("Failed to read {}", file_path.display()))?;
+        let mut value: serde_json::Value = serde_json::from_str(&json_str)?;
+        let fields = value.as_object_mut().with_context(|| format!("{} does not hold a descriptor", file_path.display()))?;
+        let expires_at = SystemTime::now().checked_add(ttl).context("Descriptor TTL overflows")?;
+        let _prev = fields.insert("expires_at".to_owned(), serde_json::to_value(expires_at)?);
+        write_file_atomic(&file_path, serde_json::to_string_pretty(&value)?.as_bytes())
+    }
+
+    /// Memory IDs of the descriptor files in the directory, in ascending order
+    ///
+    /// Only `memdesc_<id>.json` files count, sidecars and lock files are skipped.
+    /// # Returns
+    /// # Errors
+    /// Memory IDs on success, `anyhow::Error` if the directory can't be read
+    #[inline]
+    pub fn list(&self) -> anyhow::Result<Vec<MemId>> {
+        let mut mem_ids = Vec::new();
+        for entry in std::fs::read_dir(&self.path).with_context(|| format!("Failed to read {}", self.path.display()))? {
+            let name = entry?.file_name();
+            if let Some(file_name) = name.to_str().and_then(MemlinkFilename::parse).filter(MemlinkFilename::is_descriptor) {
+                mem_ids.push(file_name.mem_id);
+            }
+        }
+        mem_ids.sort_unstable();
+        Ok(mem_ids)
+    }
+
+    /// Memory IDs whose exporting process is gone, in ascending order
+    ///
+    /// The exporter is read from the meta sidecar and looked up in `/proc`.
+    /// Descriptors without a readable sidecar or exported by another host are
+    /// skipped, as is everything when `/proc` is unavailable.
+    /// # Returns
+    /// # Errors
+    /// Memory IDs of orphaned descriptors on success, `anyhow::Error` if the
+    /// directory can't be read
+    #[inline]
+    pub fn find_orphans(&self) -> anyhow::Result<Vec<MemId>> {
+        self.find_orphans_in(Path::new("/proc"))
+    }
+
+    /// Orphaned descriptors, with processes looked up in the procfs at `proc`
+    fn find_orphans_in(&self, proc: &Path) -> anyhow::Result<Vec<MemId>> {
+        if !proc.join("self").exists() {
+            return Ok(Vec::new());
+        }
+        let hostname = local_hostname();
+        let mut orphans = Vec::new();
+        for mem_id in self.list()? {
+            let Ok(meta) = MemlinkMeta::from_file(&self.path, mem_id) else {
+                continue;
+            };
+            if meta.hostname == hostname && !proc.join(meta.pid.to_string()).exists() {
+                orphans.push(mem_id);
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Bundle every descriptor file of the directory into one JSON archive
+    ///
+    /// Meta sidecars are not included, they describe the exporting process
+    /// and do not carry over to another host.
+    /// # Returns
+    /// # Errors
+    /// Archive bytes on success, `anyhow::Error` if a descriptor file can't be read
+    #[inline]
+    pub fn export_archive(&self) -> anyhow::Result<Vec<u8>> {
+        let mut descriptors = BTreeMap::new();
+        for mem_id in self.list()? {
+            let file_path = desc_file_path(&self.path, mem_id);
+            let json_str = std::fs::read_to_string(&file_path).with_context(|| format!("Failed to read {}", file_path.display()))?;
+            let _prev = descriptors.insert(mem_id, serde_json::from_str(&json_str)?);
+        }
+        Ok(serde_json::to_vec_pretty(&Archive { version: ARCHIVE_VERSION, descriptors })?)
+    }
+
+    /// Restore the descriptors of an archive from `export_archive`
+    ///
+    /// Every entry is parsed as an `ObmmMemDesc<T>` before anything is
+    /// written, so a malformed entry leaves the directory untouched. Entries
+    /// are written as they appear in the archive.
+    /// # Arguments
+    /// * `bytes` - Archive bytes
+    /// * `on_collision` - What to do with Memory IDs already in the directory
+    /// # Returns
+    /// # Errors
+    /// Restored Memory IDs in ascending order on success, `anyhow::Error` if the
+    /// archive or one of its entries is malformed, a Memory ID collides under
+    /// `ArchiveCollision::Error` or a file can't be written
+    #[inline]
+    pub fn import_archive<T>(&self, bytes: &[u8], on_collision: ArchiveCollision) -> anyhow::Result<Vec<MemId>>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de>,
+    {
+        let archive: Archive = serde_json::from_slice(bytes).context("Malformed descriptor archive")?;
+        if archive.version != ARCHIVE_VERSION {
+            anyhow::bail!("Unsupported descriptor archive version {}", archive.version);
+        }
+        for (mem_id, desc) in &archive.descriptors {
+            let _desc = ObmmMemDesc::<T>::from_json(&desc.to_string()).with_context(|| format!("Malformed descriptor of MemID {mem_id} in archive"))?;
+        }
+        if on_collision == ArchiveCollision::Error {
+            let existing = self.list()?;
+            if let Some(mem_id) = archive.descriptors.keys().find(|mem_id| existing.contains(mem_id)) {
+                anyhow::bail!("MemID {mem_id} already has a descriptor in {}", self.path.display());
+            }
+        }
+        for (&mem_id, desc) in &archive.descriptors {
+            write_file_atomic(&desc_file_path(&self.path, mem_id), serde_json::to_string_pretty(desc)?.as_bytes())?;
+        }
+        Ok(archive.descriptors.into_keys().collect())
+    }
+
+    /// Compare the descriptors of this directory with those of `other`
+    ///
+    /// Descriptors are compared field by field with `ObmmMemDesc::diff`,
+    /// whatever their `priv_data` type. Expired descriptors are compared too,
+    /// and sidecars are ignored.
+    /// # Arguments
+    /// * `other` - Directory to compare against
+    /// # Returns
+    /// # Errors
+    /// Memory IDs only in either directory and those that differ on success,
+    /// `anyhow::Error` if a directory or descriptor file can't be read
+    #[inline]
+    pub fn compare(&self, other: &MemlinkDir) -> anyhow::Result<DirComparison> {
+        let mine = self.list()?;
+        let theirs = other.list()?;
+        let mut comparison = DirComparison {
+            only_in_self: mine.iter().copied().filter(|mem_id| theirs.binary_search(mem_id).is_err()).collect(),
+            only_in_other: theirs.iter().copied().filter(|mem_id| mine.binary_search(mem_id).is_err()).collect(),
+            differing: Vec::new(),
+        };
+        for &mem_id in mine.iter().filter(|mem_id| theirs.binary_search(mem_id).is_ok()) {
+            let fields = self.read_untyped(mem_id)?.diff(&other.read_untyped(mem_id)?);
+            if !fields.is_empty() {
+                comparison.differing.push((mem_id, fields));
+            }
+        }
+        Ok(comparison)
+    }
+
+    /// Read the descriptor of `mem_id` with `priv_data` left as JSON, ignoring `expires_at`
+    fn read_untyped(&self, mem_id: MemId) -> anyhow::Result<ObmmMemDesc<serde_json::Value>> {
+        let file_path = desc_file_path(&self.path, mem_id);
+        let json_str = std::fs::read_to_string(&file_path).with_context(|| format!("Failed to read {}", file_path.display()))?;
+        serde_json::from_str(&json_str).with_context(|| format!("Malformed descriptor {}", file_path.display()))
+    }
+
+    /// Delete the descriptor of `mem_id` along with its meta sidecar and lock file
+    /// # Arguments
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` if the descriptor file can't be removed
+    #[inline]
+    pub fn remove(&self, mem_id: MemId) -> anyhow::Result<()> {
+        let file_path = desc_file_path(&self.path, mem_id);
+        std::fs::remove_file(&file_path).with_context(|| format!("Failed to remove {}", file_path.display()))?;
+        for sidecar in [MemlinkMeta::file_path(&self.path, mem_id), file_path.with_extension("json.lock")] {
+            let removed = std::fs::remove_file(&sidecar);
+            if removed.as_ref().is_err_and(|err| err.kind() != std::io::ErrorKind::NotFound) {
+                return removed.with_context(|| format!("Failed to remove {}", sidecar.display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Export memory region unless `key` was already used, recording `key` in the directory
+    ///
+    /// A retry with the same key returns the earlier export while it is still
+    /// tracked by this process or its descriptor file is still in the
+    /// directory; otherwise the mapping is stale and a new region is exported.
+    /// The lookup, the export and the update of the key map happen under an
+    /// exclusive lock on `idempotency.json.lock`, so concurrent callers with
+    /// the same key export only once.
+    /// # Arguments
+    /// * `key` - Idempotency key chosen by the caller, non-empty
+    /// * `length` - Array of lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// # Returns
+    /// # Errors
+    /// Memory ID and descriptor of the export on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn export_idempotent<T>(&self, key: &str, length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de>,
+    {
+        if key.is_empty() {
+            anyhow::bail!("Idempotency key must not be empty");
+        }
+        let map_path = self.path().join(IDEMPOTENCY_FILE);
+        let lock = std::fs::File::create(map_path.with_extension("json.lock"))?;
+        lock.lock()?;
+        let mut keys: BTreeMap<String, MemId> = match std::fs::read_to_string(&map_path) {
+            Ok(json_str) => serde_json::from_str(&json_str).with_context(|| format!("Malformed {}", map_path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(&memid) = keys.get(key) {
+            if let Some(desc) = crate::registry::export_descriptor(memid) {
+                return Ok((memid, desc));
+            }
+            if let Ok(desc) = self.load(memid) {
+                return Ok((memid, desc));
+            }
+            log::warn!("Idempotency key {key:?} maps to MemID {memid}, which is gone; exporting again");
+        }
+        let (memid, desc) = mem_export(length, flags)?;
+        self.save(memid, &desc)?;
+        let _prev = keys.insert(key.to_owned(), memid);
+        write_file_atomic(&map_path, serde_json::to_string_pretty(&keys)?.as_bytes())?;
+        Ok((memid, desc))
+    }
+}
+
+/// Export memory region unless `key` was already used
+///
+/// Keys are recorded in the memlink directory named by `OBMM_MEMLINK_DIR`,
+/// `/tmp/memlink` if unset. See `MemlinkDir::export_idempotent`.
+/// # Arguments
+/// * `key` - Idempotency key chosen by the caller, non-empty
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Memory ID and descriptor of the export on success, `anyhow::Error` on failure
+#[inline]
+pub fn mem_export_idempotent<T>(key: &str, length: &[usize], flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    let path = std::env::var_os(ENV_MEMLINK_DIR).map_or_else(|| PathBuf::from(DEFAULT_MEMLINK_DIR), PathBuf::from);
+    MemlinkDir::open(path)?.export_idempotent(key, length, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UbPrivData;
+
+    #[test]
+    fn test_save_load_list_remove() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let dir = MemlinkDir::open(root.path().join("nested/memlink"))?;
+        assert!(dir.path().is_dir());
+        assert!(dir.list()?.is_empty());
+
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        dir.save(12, &desc)?;
+        dir.save(3, &desc)?;
+        MemlinkMeta::current().to_file(dir.path(), 3)?;
+        assert_eq!(dir.list()?, [3, 12]);
+        assert_eq!(dir.load::<UbPrivData>(12)?, desc);
+
+        dir.remove(3)?;
+        assert_eq!(dir.list()?, [12]);
+        assert!(!MemlinkMeta::file_path(dir.path(), 3).exists());
+        assert!(dir.load::<UbPrivData>(3).is_err());
+        assert!(dir.remove(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_touch() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let dir = MemlinkDir::open(root.path())?;
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        desc.to_json_file_with_ttl(dir.path(), 4, Duration::from_mins(1))?;
+        let before = dir.load::<UbPrivData>(4)?.expires_at.context("TTL not written")?;
+
+        dir.touch(4, Some(Duration::from_hours(1)))?;
+        let after = dir.load::<UbPrivData>(4)?.expires_at.context("TTL not written")?;
+        assert!(after >= before + Duration::from_mins(58));
+
+        let file_path = desc_file_path(dir.path(), 4);
+        std::fs::File::options().write(true).open(&file_path)?.set_modified(SystemTime::UNIX_EPOCH)?;
+        dir.touch(4, None)?;
+        assert!(std::fs::metadata(&file_path)?.modified()? > SystemTime::UNIX_EPOCH);
+        assert_eq!(dir.load::<UbPrivData>(4)?.expires_at, Some(after));
+        assert!(dir.touch(5, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_orphans() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let dir = MemlinkDir::open(root.path())?;
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        for mem_id in [1, 2, 3, 4] {
+            dir.save(mem_id, &desc)?;
+        }
+        let alive = MemlinkMeta::current();
+        alive.to_file(dir.path(), 1)?;
+        let mut dead = MemlinkMeta::current();
+        dead.pid = u32::MAX;
+        dead.to_file(dir.path(), 2)?;
+        let mut remote = dead.clone();
+        remote.hostname = format!("{}-elsewhere", remote.hostname);
+        remote.to_file(dir.path(), 3)?;
+
+        assert_eq!(dir.find_orphans()?, [2]);
+        assert!(dir.find_orphans_in(&root.path().join("missing"))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let a = MemlinkDir::open(root.path().join("a"))?;
+        let b = MemlinkDir::open(root.path().join("b"))?;
+        let desc = ObmmMemDesc::<UbPrivData>::example();
+        let mut moved = desc;
+        moved.addr = desc.addr.saturating_add(0x20_0000);
+        moved.dcna = desc.dcna.saturating_add(1);
+        for dir in [&a, &b] {
+            dir.save(1, &desc)?;
+        }
+        a.save(2, &desc)?;
+        b.save(2, &moved)?;
+        a.save(3, &desc)?;
+        b.save(4, &desc)?;
+
+        let comparison = a.compare(&b)?;
+        assert_eq!(comparison.only_in_self, [3]);
+        assert_eq!(comparison.only_in_other, [4]);
+        assert_eq!(comparison.differing, [(2, vec!["addr", "dcna"])]);
+        assert!(!comparison.is_empty());
+        assert!(a.compare(&a)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_round_trip() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let dir = MemlinkDir::open(root.path())?;
+        let mut descs = Vec::new();
+        for mem_id in [1, 2, 3] {
+            let mut desc = ObmmMemDesc::<UbPrivData>::example();
+            desc.tokenid = u32::try_from(mem_id)?;
+            dir.save(mem_id, &desc)?;
+            descs.push(desc);
+        }
+        let archive = dir.export_archive()?;
+        for mem_id in [1, 2, 3] {
+            dir.remove(mem_id)?;
+        }
+        assert!(dir.list()?.is_empty());
+
+        assert_eq!(dir.import_archive::<UbPrivData>(&archive, ArchiveCollision::Error)?, [1, 2, 3]);
+        for (mem_id, desc) in (1..).zip(&descs) {
+            assert_eq!(&dir.load::<UbPrivData>(mem_id)?, desc);
+        }
+        assert!(dir.import_archive::<UbPrivData>(&archive, ArchiveCollision::Error).is_err());
+        assert_eq!(dir.import_archive::<UbPrivData>(&archive, ArchiveCollision::Overwrite)?, [1, 2, 3]);
+        assert!(dir.import_archive::<UbPrivData>(b"{}", ArchiveCollision::Overwrite).is_err());
+
+        let malformed = serde_json::json!({ "version": 1, "descriptors": { "4": { "addr": "not a number" } } });
+        assert!(dir.import_archive::<UbPrivData>(malformed.to_string().as_bytes(), ArchiveCollision::Overwrite).is_err());
+        assert_eq!(dir.list()?, [1, 2, 3]);
+        Ok(())
+    }
+}
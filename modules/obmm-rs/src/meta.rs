@@ -0,0 +1,106 @@
+//! Meta sidecar: bookkeeping written next to a descriptor file
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MemId, MemlinkFilename};
+
+/// Host name of this machine, empty if it can't be read
+pub(crate) fn local_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname").map(|name| name.trim().to_owned()).unwrap_or_default()
+}
+
+/// Bookkeeping about an export, stored in `memdesc_<id>.meta.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MemlinkMeta {
+    /// When the region was exported
+    pub exported_at: SystemTime,
+    /// Process ID of the exporter
+    pub pid: u32,
+    /// Host name of the exporter
+    pub hostname: String,
+}
+
+impl MemlinkMeta {
+    /// Meta of an export made now by the calling process
+    #[inline]
+    #[must_use]
+    pub fn current() -> Self {
+        MemlinkMeta { exported_at: SystemTime::now(), pid: std::process::id(), hostname: local_hostname() }
+    }
+
+    /// Time elapsed since the export
+    ///
+    /// An `exported_at` in the future, e.g. written by a host with a skewed
+    /// clock, yields a zero age.
+    /// # Returns
+    /// # Errors
+    /// Age of the export, `anyhow::Error` if the clock cannot be read
+    #[inline]
+    pub fn age(&self) -> anyhow::Result<Duration> {
+        Ok(SystemTime::now().duration_since(self.exported_at).unwrap_or_default())
+    }
+
+    /// Path of the meta sidecar of `mem_id` in `dir`
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    #[inline]
+    #[must_use]
+    pub fn file_path(dir: &Path, mem_id: MemId) -> PathBuf {
+        dir.join(MemlinkFilename::new(mem_id, "meta.json").to_string())
+    }
+
+    /// Read the meta sidecar of `mem_id` from `dir`
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// `MemlinkMeta` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_file(dir: &Path, mem_id: MemId) -> anyhow::Result<Self> {
+        let json_str = std::fs::read_to_string(Self::file_path(dir, mem_id))?;
+        Ok(serde_json::from_str(&json_str)?)
+    }
+
+    /// Write the meta sidecar of `mem_id` to `dir`
+    /// # Arguments
+    /// * `dir` - Directory holding descriptor files
+    /// * `mem_id` - Memory ID
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_file(&self, dir: &Path, mem_id: MemId) -> anyhow::Result<()> {
+        std::fs::write(Self::file_path(dir, mem_id), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age() -> anyhow::Result<()> {
+        let mut meta = MemlinkMeta::current();
+        meta.exported_at = SystemTime::now().checked_sub(Duration::from_secs(90)).ok_or_else(|| anyhow::anyhow!("clock underflow"))?;
+        assert!(meta.age()? >= Duration::from_secs(90));
+        meta.exported_at = SystemTime::now().checked_add(Duration::from_secs(90)).ok_or_else(|| anyhow::anyhow!("clock overflow"))?;
+        assert_eq!(meta.age()?, Duration::ZERO);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_round_trip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let meta = MemlinkMeta::current();
+        meta.to_file(dir.path(), 5)?;
+        assert_eq!(MemlinkMeta::from_file(dir.path(), 5)?, meta);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Process-global accounting of imported and exported memory
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+use crate::{LifetimeClass, MAX_NUMA_NODES, MemId};
+
+/// Imported bytes per assigned NUMA node
+static IMPORTED_BYTES: [AtomicU64; MAX_NUMA_NODES] = [const { AtomicU64::new(0) }; MAX_NUMA_NODES];
+/// Imported bytes whose NUMA node was unassigned (`-1`) or out of range
+static IMPORTED_BYTES_UNASSIGNED: AtomicU64 = AtomicU64::new(0);
+/// NUMA node and length of each live import, needed to undo its tally
+static IMPORTS: LazyLock<Mutex<HashMap<MemId, (i32, u64)>>> = LazyLock::new(Mutex::default);
+
+/// Number of buckets of `export_size_histogram`
+pub const EXPORT_SIZE_BUCKETS: usize = 29;
+/// Base-2 logarithm of the lower edge of the first histogram bucket, 4KB
+const FIRST_BUCKET_SHIFT: u32 = 12;
+/// Exports counted per size bucket
+static EXPORT_SIZES: [AtomicU64; EXPORT_SIZE_BUCKETS] = [const { AtomicU64::new(0) }; EXPORT_SIZE_BUCKETS];
+/// Number of `LifetimeClass` variants
+const LIFETIME_CLASSES: usize = 3;
+/// Exports counted per size bucket, for each lifetime class
+static EXPORT_SIZES_BY_LIFETIME: [[AtomicU64; EXPORT_SIZE_BUCKETS]; LIFETIME_CLASSES] =
+    [const { [const { AtomicU64::new(0) }; EXPORT_SIZE_BUCKETS] }; LIFETIME_CLASSES];
+
+/// Per-class histogram of exports of `lifetime`
+fn lifetime_sizes(lifetime: LifetimeClass) -> Option<&'static [AtomicU64; EXPORT_SIZE_BUCKETS]> {
+    let class = match lifetime {
+        LifetimeClass::Ephemeral => 0,
+        LifetimeClass::Session => 1,
+        LifetimeClass::Persistent => 2,
+    };
+    EXPORT_SIZES_BY_LIFETIME.get(class)
+}
+
+/// Tally bucket for a NUMA node reported by the C library
+fn bucket(node: i32) -> &'static AtomicU64 {
+    usize::try_from(node)
+        .ok()
+        .and_then(|node| IMPORTED_BYTES.get(node))
+        .unwrap_or(&IMPORTED_BYTES_UNASSIGNED)
+}
+
+/// Remove a previously tallied import from its bucket
+fn untally((node, bytes): (i32, u64)) {
+    let _prev = bucket(node).fetch_sub(bytes, Ordering::Relaxed);
+}
+
+/// Record a successful import of `bytes` onto `node`
+pub(crate) fn record_import(memid: MemId, node: i32, bytes: u64) {
+    let _prev = bucket(node).fetch_add(bytes, Ordering::Relaxed);
+    let replaced = IMPORTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(memid, (node, bytes));
+    if let Some(import) = replaced {
+        untally(import);
+    }
+}
+
+/// Record a successful unimport, removing its bytes from the tally
+pub(crate) fn record_unimport(memid: MemId) {
+    let removed = IMPORTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&memid);
+    if let Some(import) = removed {
+        untally(import);
+    }
+}
+
+/// Record a successful export of `bytes` with the given lifetime class
+pub(crate) fn record_export(bytes: u64, lifetime: LifetimeClass) {
+    let shift = bytes.checked_ilog2().unwrap_or(0).saturating_sub(FIRST_BUCKET_SHIFT);
+    let bucket = usize::try_from(shift).map_or(EXPORT_SIZE_BUCKETS, |shift| shift.min(EXPORT_SIZE_BUCKETS.saturating_sub(1)));
+    if let Some(count) = EXPORT_SIZES.get(bucket) {
+        let _prev = count.fetch_add(1, Ordering::Relaxed);
+    }
+    if let Some(count) = lifetime_sizes(lifetime).and_then(|sizes| sizes.get(bucket)) {
+        let _prev = count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Number of exports made with `mem_export` by size
+///
+/// Bucket `i` counts exports of `[4KB << i, 8KB << i)` bytes, so bucket 0
+/// starts at 4KB and bucket 28 at 1TB. Smaller exports are counted in the
+/// first bucket and larger ones in the last.
+/// # Returns
+/// Export counts indexed by bucket
+#[inline]
+#[must_use]
+pub fn export_size_histogram() -> [u64; EXPORT_SIZE_BUCKETS] {
+    core::array::from_fn(|bucket| EXPORT_SIZES.get(bucket).map_or(0, |count| count.load(Ordering::Relaxed)))
+}
+
+/// Number of exports of one lifetime class by size
+///
+/// Buckets are laid out as in `export_size_histogram`, which is the sum of
+/// the histograms of all classes.
+/// # Arguments
+/// * `lifetime` - Lifetime class of the exports to count
+/// # Returns
+/// Export counts indexed by bucket
+#[inline]
+#[must_use]
+pub fn export_size_histogram_by_lifetime(lifetime: LifetimeClass) -> [u64; EXPORT_SIZE_BUCKETS] {
+    let sizes = lifetime_sizes(lifetime);
+    core::array::from_fn(|bucket| sizes.and_then(|sizes| sizes.get(bucket)).map_or(0, |count| count.load(Ordering::Relaxed)))
+}
+
+/// Total bytes currently imported onto each NUMA node
+/// # Returns
+/// Imported bytes indexed by NUMA node ID
+#[inline]
+#[must_use]
+pub fn imported_bytes_per_node() -> [u64; MAX_NUMA_NODES] {
+    core::array::from_fn(|node| IMPORTED_BYTES.get(node).map_or(0, |bytes| bytes.load(Ordering::Relaxed)))
+}
+
+/// Total bytes currently imported without an assigned NUMA node
+/// # Returns
+/// Imported bytes reported on node `-1` or an out-of-range node
+#[inline]
+#[must_use]
+pub fn imported_bytes_unassigned() -> u64 {
+    IMPORTED_BYTES_UNASSIGNED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,337 @@
+//! NUMA placement policies for imports
+
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::{MAX_NUMA_NODES, MemId, ObmmError, ObmmImportFlags, ObmmMemDesc, ObmmUnimportFlags, UbPrivData, mem_import, mem_unimport};
+
+/// Flag marking a `base_dist` as an interleave node mask
+const INTERLEAVE_BIT: i32 = 1 << 16;
+
+/// Directory holding one `nodeN` directory per NUMA node
+const NODE_SYSFS_DIR: &str = "/sys/devices/system/node";
+/// Distance of a node to itself, as reported by the kernel
+const LOCAL_DISTANCE: u8 = 10;
+/// Distance between distinct nodes assumed when sysfs is unavailable
+const REMOTE_DISTANCE: u8 = 20;
+/// Distance to a node absent from sysfs
+const UNREACHABLE_DISTANCE: u8 = u8::MAX;
+
+/// Pairwise distances between NUMA nodes, indexed by source then target node
+type DistanceMatrix = [[u8; MAX_NUMA_NODES]; MAX_NUMA_NODES];
+
+/// Where the driver should place an imported region
+///
+/// Translated into the `base_dist` hint of `mem_import`:
+///
+/// | Placement        | `base_dist`                              |
+/// |------------------|------------------------------------------|
+/// | `Local`          | `0`                                      |
+/// | `PreferNode(n)`  | `n + 1`                                  |
+/// | `Interleave(ns)` | `1 << 16` with bit `n` set for each node |
+///
+/// libobmm only accepts distances up to 255 for remote NUMA imports, so
+/// `Interleave` is rejected by the driver when combined with `REMOTENUMA`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ImportPlacement {
+    /// Let the driver place the region on the local node
+    #[default]
+    Local,
+    /// Place the region near the given NUMA node
+    PreferNode(usize),
+    /// Interleave the region across the given NUMA nodes
+    Interleave(Vec<usize>),
+}
+
+impl ImportPlacement {
+    /// Encode the placement as a `base_dist` hint
+    /// # Returns
+    /// # Errors
+    /// `base_dist` on success, `ObmmError::InvalidPlacement` for a node out of
+    /// range or an empty interleave set
+    #[inline]
+    pub fn base_dist(&self) -> Result<i32, ObmmError> {
+        match *self {
+            ImportPlacement::Local => Ok(0),
+            ImportPlacement::PreferNode(node) => Ok(i32::from(check_node(node)?).saturating_add(1)),
+            ImportPlacement::Interleave(ref nodes) => {
+                if nodes.is_empty() {
+                    return Err(ObmmError::InvalidPlacement("empty interleave set".to_owned()));
+                }
+                let mask = nodes.iter().try_fold(0_u16, |mask, &node| {
+                    Ok::<_, ObmmError>(mask | 1_u16.checked_shl(check_node(node)?.into()).unwrap_or(0))
+                })?;
+                Ok(INTERLEAVE_BIT | i32::from(mask))
+            }
+        }
+    }
+
+    /// Decode a `base_dist` hint encoded by `base_dist`
+    /// # Arguments
+    /// * `base_dist` - Placement hint given to `mem_import`
+    /// # Returns
+    /// Placement the hint requests, `None` for hints outside the encoding
+    #[inline]
+    #[must_use]
+    pub fn from_base_dist(base_dist: i32) -> Option<Self> {
+        if base_dist == 0 {
+            return Some(ImportPlacement::Local);
+        }
+        if base_dist & INTERLEAVE_BIT != 0 {
+            let mask = u16::try_from(base_dist & !INTERLEAVE_BIT).ok().filter(|&mask| mask != 0)?;
+            let nodes = (0..MAX_NUMA_NODES).filter(|&node| u32::try_from(node).is_ok_and(|bit| mask & 1_u16.checked_shl(bit).unwrap_or(0) != 0));
+            return Some(ImportPlacement::Interleave(nodes.collect()));
+        }
+        usize::try_from(base_dist).ok()?.checked_sub(1).filter(|&node| node < MAX_NUMA_NODES).map(ImportPlacement::PreferNode)
+    }
+
+    /// Whether a region landing on `node` honors the placement
+    ///
+    /// `Local` leaves the node to the driver and accepts any node.
+    /// # Arguments
+    /// * `node` - NUMA node assigned by the driver, `-1` if unassigned
+    #[inline]
+    #[must_use]
+    pub fn is_satisfied_by(&self, node: i32) -> bool {
+        let node = usize::try_from(node).ok();
+        match *self {
+            ImportPlacement::Local => true,
+            ImportPlacement::PreferNode(preferred) => node == Some(preferred),
+            ImportPlacement::Interleave(ref nodes) => node.is_some_and(|node| nodes.contains(&node)),
+        }
+    }
+}
+
+/// Check the node assigned to an import against its placement hint
+///
+/// Hints outside the `ImportPlacement` encoding are not checked.
+/// # Returns
+/// # Errors
+/// Ok(()) if the node satisfies the hint, `ObmmError::PlacementMismatch` otherwise
+pub(crate) fn check_assigned_node(base_dist: i32, node: i32) -> Result<(), ObmmError> {
+    match ImportPlacement::from_base_dist(base_dist) {
+        Some(placement) if !placement.is_satisfied_by(node) => Err(ObmmError::PlacementMismatch { base_dist, node }),
+        Some(_) | None => Ok(()),
+    }
+}
+
+/// Log a warning if an import landed on a node its placement hint does not allow
+pub(crate) fn warn_on_mismatch(memid: MemId, base_dist: i32, node: i32) {
+    if let Err(err) = check_assigned_node(base_dist, node) {
+        log::warn!("Import of MemID {memid}: {err}");
+    }
+}
+
+/// Validate a NUMA node index against `MAX_NUMA_NODES`
+fn check_node(node: usize) -> Result<u16, ObmmError> {
+    if node < MAX_NUMA_NODES {
+        u16::try_from(node).map_err(|e| ObmmError::InvalidPlacement(e.to_string()))
+    } else {
+        Err(ObmmError::InvalidPlacement(format!(
+            "NUMA node {node} out of range, at most {MAX_NUMA_NODES} nodes are supported"
+        )))
+    }
+}
+
+/// Import memory region with a placement policy
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `placement` - Where to place the region
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and NUMA node on success, `ObmmError` on an invalid placement or failure
+#[inline]
+pub fn mem_import_placed(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    placement: &ImportPlacement,
+) -> Result<(MemId, i32), ObmmError> {
+    mem_import(desc, flags, placement.base_dist()?)
+}
+
+/// Import memory region, failing if it lands on a node `base_dist` does not allow
+///
+/// `mem_import` only logs a warning when the driver places a region elsewhere
+/// than requested. Here the region is unimported again and the mismatch
+/// returned, for callers whose performance depends on the placement.
+/// # Arguments
+/// * `desc` - Memory Descriptor from remote
+/// * `flags` - Import flags
+/// * `base_dist` - Base distribution hint, as encoded by `ImportPlacement::base_dist`
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and NUMA node on success, `ObmmError::PlacementMismatch`
+/// if the assigned node does not satisfy `base_dist`, `ObmmError` on other failures
+#[inline]
+pub fn mem_import_strict(
+    desc: &ObmmMemDesc<UbPrivData>,
+    flags: ObmmImportFlags,
+    base_dist: i32,
+) -> Result<(MemId, i32), ObmmError> {
+    let (memid, node) = mem_import(desc, flags, base_dist)?;
+    if let Err(err) = check_assigned_node(base_dist, node) {
+        mem_unimport(memid, ObmmUnimportFlags::empty())?;
+        return Err(err);
+    }
+    Ok((memid, node))
+}
+
+/// Distances between NUMA nodes, as read from sysfs
+///
+/// Without `/sys/devices/system/node`, e.g. off Linux, every node is assumed
+/// present with the kernel defaults: 10 to itself and 20 to any other node.
+/// Otherwise nodes without a `distance` file are reported at distance 255 from
+/// every other node.
+/// # Returns
+/// # Errors
+/// Matrix indexed by source then target node on success, `anyhow::Error` if a
+/// `distance` file can't be read or parsed
+#[inline]
+pub fn numa_distance_matrix() -> anyhow::Result<[[u8; MAX_NUMA_NODES]; MAX_NUMA_NODES]> {
+    distance_matrix_in(Path::new(NODE_SYSFS_DIR))
+}
+
+/// Closest other NUMA node to `from`
+/// # Arguments
+/// * `from` - Source NUMA node
+/// # Returns
+/// Lowest-numbered node at the smallest distance from `from`, `None` if `from`
+/// is out of range, no other node is reachable or the distances can't be read
+#[inline]
+#[must_use]
+pub fn nearest_node(from: usize) -> Option<usize> {
+    nearest_in(&numa_distance_matrix().ok()?, from)
+}
+
+/// Nodes given a non-zero length that sysfs does not list
+///
+/// Empty without `/sys/devices/system/node`, as nothing is known then.
+pub(crate) fn absent_nodes(length: &[usize]) -> Vec<usize> {
+    absent_nodes_in(Path::new(NODE_SYSFS_DIR), length)
+}
+
+/// Nodes given a non-zero length that the sysfs node directory at `root` does not list
+fn absent_nodes_in(root: &Path, length: &[usize]) -> Vec<usize> {
+    if !root.is_dir() {
+        return Vec::new();
+    }
+    length
+        .iter()
+        .enumerate()
+        .filter(|&(node, &len)| len > 0 && !root.join(format!("node{node}")).is_dir())
+        .map(|(node, _)| node)
+        .collect()
+}
+
+/// Read the distance matrix from a sysfs node directory at `root`
+fn distance_matrix_in(root: &Path) -> anyhow::Result<DistanceMatrix> {
+    let mut matrix = [[REMOTE_DISTANCE; MAX_NUMA_NODES]; MAX_NUMA_NODES];
+    let present = root.is_dir();
+    for (node, row) in matrix.iter_mut().enumerate() {
+        if present {
+            *row = [UNREACHABLE_DISTANCE; MAX_NUMA_NODES];
+            let path = root.join(format!("node{node}")).join("distance");
+            match std::fs::read_to_string(&path) {
+                Ok(distances) => {
+                    for (to, distance) in row.iter_mut().zip(distances.split_whitespace()) {
+                        *to = distance.parse().with_context(|| format!("Invalid distance {distance:?} in {}", path.display()))?;
+                    }
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+            }
+        }
+        if let Some(local) = row.get_mut(node) {
+            *local = LOCAL_DISTANCE;
+        }
+    }
+    Ok(matrix)
+}
+
+/// Closest reachable node to `from` other than itself in `matrix`
+fn nearest_in(matrix: &DistanceMatrix, from: usize) -> Option<usize> {
+    matrix
+        .get(from)?
+        .iter()
+        .enumerate()
+        .filter(|&(node, &distance)| node != from && distance != UNREACHABLE_DISTANCE)
+        .min_by_key(|&(node, &distance)| (distance, node))
+        .map(|(node, _)| node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement_base_dist() -> Result<(), ObmmError> {
+        assert_eq!(ImportPlacement::Local.base_dist()?, 0);
+        assert_eq!(ImportPlacement::PreferNode(0).base_dist()?, 1);
+        assert_eq!(ImportPlacement::PreferNode(2).base_dist()?, 3);
+        assert_eq!(ImportPlacement::Interleave(vec![0, 1]).base_dist()?, 0x1_0003);
+        assert_eq!(ImportPlacement::Interleave(vec![15]).base_dist()?, 0x1_8000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_placement_from_base_dist() -> Result<(), ObmmError> {
+        for placement in [ImportPlacement::Local, ImportPlacement::PreferNode(2), ImportPlacement::Interleave(vec![0, 3, 15])] {
+            assert_eq!(ImportPlacement::from_base_dist(placement.base_dist()?), Some(placement));
+        }
+        assert_eq!(ImportPlacement::from_base_dist(-1), None);
+        assert_eq!(ImportPlacement::from_base_dist(INTERLEAVE_BIT), None);
+        assert!(ImportPlacement::Interleave(vec![1, 2]).is_satisfied_by(2));
+        assert!(!ImportPlacement::PreferNode(1).is_satisfied_by(-1));
+        assert!(ImportPlacement::Local.is_satisfied_by(-1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_placement_out_of_range() {
+        assert!(matches!(ImportPlacement::PreferNode(MAX_NUMA_NODES).base_dist(), Err(ObmmError::InvalidPlacement(_))));
+        assert!(matches!(ImportPlacement::Interleave(vec![0, 16]).base_dist(), Err(ObmmError::InvalidPlacement(_))));
+        assert!(matches!(ImportPlacement::Interleave(Vec::new()).base_dist(), Err(ObmmError::InvalidPlacement(_))));
+    }
+
+    #[test]
+    fn test_distance_matrix_from_sysfs() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        for (node, distances) in [(0, "10 21 12\n"), (1, "21 10 21\n"), (2, "12 21 10\n")] {
+            let dir = root.path().join(format!("node{node}"));
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(dir.join("distance"), distances)?;
+        }
+        let matrix = distance_matrix_in(root.path())?;
+        let rows: Vec<_> = matrix.iter().take(3).map(|row| row.iter().take(4).copied().collect::<Vec<_>>()).collect();
+        assert_eq!(rows, [[10, 21, 12, 255], [21, 10, 21, 255], [12, 21, 10, 255]]);
+        assert_eq!(matrix.last().and_then(|row| row.last()), Some(&LOCAL_DISTANCE));
+
+        assert_eq!(nearest_in(&matrix, 0), Some(2));
+        assert_eq!(nearest_in(&matrix, 1), Some(0));
+        assert_eq!(nearest_in(&matrix, 3), None);
+        assert_eq!(nearest_in(&matrix, MAX_NUMA_NODES), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_absent_nodes() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        std::fs::create_dir_all(root.path().join("node0"))?;
+        std::fs::create_dir_all(root.path().join("node2"))?;
+        assert_eq!(absent_nodes_in(root.path(), &[1, 1, 1, 0, 1]), [1, 4]);
+        assert!(absent_nodes_in(&root.path().join("missing"), &[1, 1]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_distance_matrix_fallback() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let matrix = distance_matrix_in(&root.path().join("missing"))?;
+        assert_eq!(matrix.first().map(|row| (row.first().copied(), row.get(1).copied())), Some((Some(10), Some(20))));
+        assert_eq!(nearest_in(&matrix, 0), Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Pre-flight checks run before handing an export to the OBMM driver
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether exports may exceed the physical memory of the host
+static ALLOW_OVERCOMMIT: AtomicBool = AtomicBool::new(false);
+
+/// Allow or forbid exports larger than the physical memory of the host
+///
+/// The check is enabled by default; disable it for overcommit scenarios.
+/// # Arguments
+/// * `allow` - Skip the physical memory check when `true`
+#[inline]
+pub fn set_allow_overcommit(allow: bool) {
+    ALLOW_OVERCOMMIT.store(allow, Ordering::Relaxed);
+}
+
+/// Parse the `MemTotal` line of `/proc/meminfo` into bytes
+#[cfg(target_os = "linux")]
+fn parse_mem_total(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find_map(|line| line.strip_prefix("MemTotal:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    kib.checked_mul(1024)
+}
+
+/// Check `requested` bytes against the memory total reported by `meminfo`
+/// # Errors
+/// `anyhow::Error` if the request exceeds the total or `meminfo` has no `MemTotal`
+#[cfg(target_os = "linux")]
+fn check_against_meminfo(requested: u64, meminfo: &str) -> anyhow::Result<()> {
+    let total = parse_mem_total(meminfo)
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse MemTotal from /proc/meminfo"))?;
+    if requested > total {
+        anyhow::bail!(
+            "Requested export of {requested} bytes exceeds the {total} bytes of physical memory \
+             (allow overcommit to skip this check)"
+        );
+    }
+    Ok(())
+}
+
+/// Reject exports larger than the physical memory of the host
+/// # Arguments
+/// * `requested` - Total bytes to export across all NUMA nodes
+/// # Errors
+/// `anyhow::Error` if the request exceeds physical memory
+#[cfg(target_os = "linux")]
+pub(crate) fn check_physical_memory(requested: u64) -> anyhow::Result<()> {
+    if ALLOW_OVERCOMMIT.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    check_against_meminfo(requested, &std::fs::read_to_string("/proc/meminfo")?)
+}
+
+/// Reject exports larger than the physical memory of the host, a no-op off Linux
+/// # Errors
+/// Never fails
+#[cfg(not(target_os = "linux"))]
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn check_physical_memory(_: u64) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "MemTotal:        1024 kB\nMemFree:          512 kB\n";
+
+    #[test]
+    fn test_check_against_meminfo() -> anyhow::Result<()> {
+        assert_eq!(parse_mem_total(MEMINFO), Some(1024 * 1024));
+        check_against_meminfo(1024 * 1024, MEMINFO)?;
+        let result = check_against_meminfo(1024 * 1024 + 1, MEMINFO);
+        assert!(result.is_err_and(|e| e.to_string().contains("exceeds the 1048576 bytes of physical memory")));
+        assert!(check_against_meminfo(1, "MemFree: 512 kB\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Export profiles: named, reusable export shapes loaded from TOML
+
+use std::path::Path;
+use anyhow::Context;
+use serde::{Serialize, Deserialize};
+
+use crate::{MAX_NUMA_NODES, MemId, ObmmExportFlags, ObmmMemDesc, UbPrivData, mem_export};
+
+/// A named export shape, e.g. "128MB cacheable on node 1"
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ExportProfile {
+    /// Profile name
+    pub name: String,
+    /// Lengths for each NUMA node, missing trailing nodes are zero
+    pub lengths: Vec<usize>,
+    /// Export flags
+    #[serde(default)]
+    pub flags: ObmmExportFlags,
+    /// Privilege data attached to the exported descriptor
+    #[serde(default)]
+    pub priv_data: UbPrivData,
+}
+
+/// On-disk layout of a profiles file holding several `[[profile]]` tables
+#[derive(Default, Debug, Serialize, Deserialize)]
+struct ProfilesFile {
+    /// All profiles in the file
+    #[serde(default)]
+    profile: Vec<ExportProfile>,
+}
+
+impl ExportProfile {
+    /// Create a new `ExportProfile`
+    /// # Arguments
+    /// * `name` - Profile name
+    /// * `lengths` - Lengths for each NUMA node
+    /// * `flags` - Export flags
+    /// * `priv_data` - Privilege data attached to the exported descriptor
+    #[inline]
+    #[must_use]
+    pub fn new(name: &str, lengths: Vec<usize>, flags: ObmmExportFlags, priv_data: UbPrivData) -> Self {
+        ExportProfile { name: name.to_owned(), lengths, flags, priv_data }
+    }
+
+    /// Deserialize an `ExportProfile` from toml format
+    /// # Arguments
+    /// * `toml_str` - TOML string representation
+    /// # Returns
+    /// # Errors
+    /// `ExportProfile` on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn from_toml(toml_str: &str) -> anyhow::Result<Self> {
+        let profile: ExportProfile = toml::from_str(toml_str)?;
+        Ok(profile)
+    }
+
+    /// Serialize the `ExportProfile` to toml format
+    /// # Returns
+    /// # Errors
+    /// TOML string on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        let toml_str = toml::to_string(self)?;
+        Ok(toml_str)
+    }
+
+    /// Load the profile called `name` from a profiles file of `[[profile]]` tables
+    /// # Arguments
+    /// * `path` - Path to the profiles file
+    /// * `name` - Name of the profile to load
+    /// # Returns
+    /// # Errors
+    /// `ExportProfile` on success, `anyhow::Error` if the file is unreadable or has no such profile
+    #[inline]
+    pub fn load(path: &Path, name: &str) -> anyhow::Result<Self> {
+        let toml_str = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read profiles file {}", path.display()))?;
+        let file: ProfilesFile = toml::from_str(&toml_str)?;
+        file.profile
+            .into_iter()
+            .find(|profile| profile.name == name)
+            .with_context(|| format!("No profile named {name} in {}", path.display()))
+    }
+
+    /// Per-node lengths padded with zeros to `MAX_NUMA_NODES`
+    /// # Returns
+    /// # Errors
+    /// Length array on success, `anyhow::Error` if more than `MAX_NUMA_NODES` are given
+    fn numa_lengths(&self) -> anyhow::Result<Vec<usize>> {
+        if self.lengths.len() > MAX_NUMA_NODES {
+            anyhow::bail!("Profile {} has {} NUMA node lengths, at most {MAX_NUMA_NODES} are supported", self.name, self.lengths.len());
+        }
+        let mut lengths = self.lengths.clone();
+        lengths.resize(MAX_NUMA_NODES, 0);
+        Ok(lengths)
+    }
+
+    /// Export memory according to this profile
+    /// # Returns
+    /// # Errors
+    /// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+    #[inline]
+    pub fn apply(&self) -> anyhow::Result<(MemId, ObmmMemDesc<UbPrivData>)> {
+        let (memid, mut desc) = mem_export::<UbPrivData>(&self.numa_lengths()?, self.flags)?;
+        desc.priv_data = self.priv_data;
+        desc.recompute_priv_len()?;
+        Ok((memid, desc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE_TOML: &str = r#"
+        name = "cacheable-node1"
+        lengths = [0, 134217728]
+        flags = "ALLOWMMAP"
+        priv_data = "CACHEABLE"
+    "#;
+
+    #[test]
+    fn test_profile_from_toml() -> anyhow::Result<()> {
+        let profile = ExportProfile::from_toml(PROFILE_TOML)?;
+        assert_eq!(profile.name, "cacheable-node1");
+        assert_eq!(profile.lengths, vec![0, 1024 * 1024 * 128]);
+        assert_eq!(profile.flags, ObmmExportFlags::ALLOWMMAP);
+        assert_eq!(profile.priv_data, UbPrivData::CACHEABLE);
+        assert_eq!(ExportProfile::from_toml(&profile.to_toml()?)?, profile);
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_apply() -> anyhow::Result<()> {
+        let profile = ExportProfile::from_toml(PROFILE_TOML)?;
+        let (memid, desc) = profile.apply()?;
+        assert!(memid != crate::OBMM_INVALID_MEMID);
+        assert_eq!(desc.length, 1024 * 1024 * 128);
+        assert_eq!(desc.priv_data, UbPrivData::CACHEABLE);
+        assert_eq!(desc.priv_len, 2);
+
+        let too_many = ExportProfile::new("too-many", vec![1; MAX_NUMA_NODES + 1], ObmmExportFlags::empty(), UbPrivData::empty());
+        assert!(too_many.apply().is_err());
+        Ok(())
+    }
+}
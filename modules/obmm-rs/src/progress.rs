@@ -0,0 +1,124 @@
+//! Exports reporting progress through a callback
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+
+use serde::Serialize;
+
+use crate::{MemId, ObmmExportFlags, ObmmMemDesc, ObmmUnexportFlags, mem_unexport};
+
+/// Callback and captured panic shared with `progress_trampoline`
+struct ProgressState<'a> {
+    /// User callback receiving bytes done and total
+    callback: &'a mut dyn FnMut(u64, u64),
+    /// Panic raised by the callback, which must not unwind into C
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// C-ABI progress callback forwarding to the `ProgressState` behind `ctx`
+///
+/// Panics are caught and stored, and the callback is not invoked again after one.
+extern "C" fn progress_trampoline(done: u64, total: u64, ctx: *mut c_void) {
+    // SAFETY: ctx is the `ProgressState` passed alongside this callback, alive
+    // and not otherwise borrowed for the duration of the export
+    let state = unsafe { &mut *ctx.cast::<ProgressState<'_>>() };
+    if state.panic.is_some() {
+        return;
+    }
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| (state.callback)(done, total))) {
+        state.panic = Some(payload);
+    }
+}
+
+/// Export memory region, reporting progress as it is populated
+///
+/// `callback` receives the bytes done and the total. If it panics, the export
+/// is unexported once the driver returns and the panic is resumed.
+/// # Arguments
+/// * `length` - Array of lengths for each NUMA node
+/// * `flags` - Export flags
+/// * `callback` - Progress callback
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[inline]
+pub fn mem_export_progress<T, F>(length: &[usize], flags: ObmmExportFlags, mut callback: F) -> anyhow::Result<(MemId, ObmmMemDesc<T>)>
+where
+    T: Default + Serialize,
+    F: FnMut(u64, u64),
+{
+    let mut state = ProgressState { callback: &mut callback, panic: None };
+    let ctx = core::ptr::from_mut(&mut state).cast::<c_void>();
+    let exported = export_with_progress::<T>(length, flags, ctx);
+    if let Some(payload) = state.panic {
+        if let Ok((memid, _)) = exported {
+            let _result = mem_unexport(memid, ObmmUnexportFlags::FORCE);
+        }
+        panic::resume_unwind(payload);
+    }
+    exported
+}
+
+/// Export through the hook, reporting progress in quarters
+#[cfg(feature = "hook")]
+fn export_with_progress<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags, ctx: *mut c_void) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    // hooked implementation
+    let total: u64 = length.iter().sum::<usize>().try_into()?;
+    for quarter in 1..=4_u64 {
+        progress_trampoline(total.saturating_mul(quarter) / 4, total, ctx);
+    }
+    crate::mem_export(length, flags)
+}
+
+/// Export through `obmm_export_cb`
+#[cfg(not(feature = "hook"))]
+fn export_with_progress<T: Default + Serialize>(length: &[usize], flags: ObmmExportFlags, ctx: *mut c_void) -> anyhow::Result<(MemId, ObmmMemDesc<T>)> {
+    crate::ffi_ready()?;
+    crate::check_supported_export_flags(flags)?;
+    let padded = crate::padded_lengths(length)?;
+    let per_node = crate::per_node_lengths(length)?;
+    crate::preflight::check_physical_memory(length.iter().sum::<usize>().try_into()?)?;
+    crate::ratelimit::acquire()?;
+    let mut raw = crate::ObmmRawMemDesc::<T>::default();
+    let memid = unsafe {
+        crate::obmm_export_cb(padded.as_ptr(), flags.bits(), core::ptr::from_mut(&mut raw).cast(), progress_trampoline, ctx)
+    };
+    let mut desc = ObmmMemDesc::from(raw);
+    desc.per_node = Some(per_node);
+    desc.export_flags = flags;
+    if memid == crate::OBMM_INVALID_MEMID {
+        Err(anyhow::anyhow!("Failed to export memory"))
+    } else {
+        crate::metrics::record_export(desc.length, desc.lifetime);
+        crate::registry::record(memid, crate::registry::Kind::Export, &desc);
+        Ok((memid, desc))
+    }
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, UbPrivData};
+
+    #[test]
+    fn test_export_progress() -> anyhow::Result<()> {
+        let mut seen = Vec::new();
+        let (_, desc) = mem_export_progress::<UbPrivData, _>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty(), |done, total| {
+            seen.push((done, total));
+        })?;
+        assert!(seen.len() > 1);
+        assert!(seen.windows(2).all(|pair| matches!(*pair, [(a, _), (b, _)] if a < b)));
+        assert!(seen.iter().all(|&(done, total)| done <= total && total == desc.length));
+        assert_eq!(seen.last().map(|&(done, _)| done), Some(desc.length));
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_progress_panic() {
+        let result = panic::catch_unwind(|| {
+            mem_export_progress::<UbPrivData, _>(&[1024; MAX_NUMA_NODES], ObmmExportFlags::empty(), |_, _| panic!("callback failed"))
+        });
+        assert!(result.is_err());
+    }
+}
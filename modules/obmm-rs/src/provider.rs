@@ -0,0 +1,76 @@
+//! Exporting buffers allocated by the caller
+
+use serde::Serialize;
+
+use crate::{MemId, ObmmExportFlags, ObmmMemDesc, mem_export_at};
+
+/// Source of an already allocated buffer to export, such as hugepages or a mapping
+pub trait BufferProvider {
+    /// Virtual address and length of the buffer
+    fn region(&self) -> (u64, usize);
+}
+
+/// Raw `(addr, len)` pair
+impl BufferProvider for (u64, usize) {
+    #[inline]
+    fn region(&self) -> (u64, usize) {
+        *self
+    }
+}
+
+/// Virtual address of a mapping as exported to the driver
+#[cfg(all(unix, feature = "mmap"))]
+fn mapping_addr(ptr: *const u8) -> u64 {
+    // usize is at most 64 bits on every supported target
+    u64::try_from(ptr.addr()).unwrap_or(u64::MAX)
+}
+
+#[cfg(all(unix, feature = "mmap"))]
+impl BufferProvider for memmap2::MmapMut {
+    #[inline]
+    fn region(&self) -> (u64, usize) {
+        (mapping_addr(self.as_ptr()), self.len())
+    }
+}
+
+#[cfg(all(unix, feature = "mmap"))]
+impl BufferProvider for memmap2::Mmap {
+    #[inline]
+    fn region(&self) -> (u64, usize) {
+        (mapping_addr(self.as_ptr()), self.len())
+    }
+}
+
+/// Export the buffer of a `BufferProvider`
+///
+/// The provider must keep the buffer mapped until the export is unexported.
+/// # Arguments
+/// * `provider` - Provider of the buffer
+/// * `flags` - Export flags
+/// # Returns
+/// # Errors
+/// Tuple of Memory ID and Memory Descriptor on success, `anyhow::Error` on failure
+#[inline]
+pub fn mem_export_with<T, P>(provider: &P, flags: ObmmExportFlags) -> anyhow::Result<(MemId, ObmmMemDesc<T>)>
+where
+    T: Default + Serialize,
+    P: BufferProvider + ?Sized,
+{
+    let (addr, length) = provider.region();
+    mem_export_at(addr, length, flags)
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::UbPrivData;
+
+    #[test]
+    fn test_tuple_provider() -> anyhow::Result<()> {
+        let provider = (0x7f00_0020_0000_u64, 2 * 1024 * 1024_usize);
+        let (_, desc) = mem_export_with::<UbPrivData, _>(&provider, ObmmExportFlags::ALLOWMMAP)?;
+        assert_eq!(desc.addr, 0x7f00_0020_0000);
+        assert_eq!(desc.length, 2 * 1024 * 1024);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! Token-bucket rate limiting of exports, protecting the driver from bursts
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ObmmError;
+
+/// Nanounits in one token, so a bucket refilled once per nanosecond stays integral
+const NANOS_PER_TOKEN: u128 = 1_000_000_000;
+
+/// What an export does when the rate limit budget is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum RateLimitMode {
+    /// Sleep until a token is available
+    #[default]
+    Block,
+    /// Fail with `ObmmError::RateLimited`
+    Error,
+}
+
+/// Token bucket holding up to one second of budget
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second, 0 when disabled
+    per_sec: u32,
+    /// Available budget in nanounits of a token
+    nanotokens: u128,
+    /// Last time the bucket was refilled
+    refilled: Instant,
+    /// Behavior when the budget is exhausted
+    mode: RateLimitMode,
+}
+
+impl Bucket {
+    /// Bucket with the limit `per_sec`, starting full
+    fn new(per_sec: u32, mode: RateLimitMode, now: Instant) -> Self {
+        Bucket { per_sec, nanotokens: Self::capacity(per_sec), refilled: now, mode }
+    }
+
+    /// Budget of a full bucket, one second worth of tokens
+    fn capacity(per_sec: u32) -> u128 {
+        u128::from(per_sec).saturating_mul(NANOS_PER_TOKEN)
+    }
+
+    /// Take a token at `now`
+    /// # Returns
+    /// `None` if a token was taken, otherwise how long until one is available
+    fn try_take(&mut self, now: Instant) -> Option<Duration> {
+        if self.per_sec == 0 {
+            return None;
+        }
+        let elapsed = now.saturating_duration_since(self.refilled).as_nanos();
+        self.nanotokens = self
+            .nanotokens
+            .saturating_add(elapsed.saturating_mul(u128::from(self.per_sec)))
+            .min(Self::capacity(self.per_sec));
+        self.refilled = now;
+        if let Some(left) = self.nanotokens.checked_sub(NANOS_PER_TOKEN) {
+            self.nanotokens = left;
+            return None;
+        }
+        let missing = NANOS_PER_TOKEN.saturating_sub(self.nanotokens);
+        let wait = missing.div_ceil(u128::from(self.per_sec));
+        Some(Duration::from_nanos(u64::try_from(wait).unwrap_or(u64::MAX)))
+    }
+}
+
+/// Export rate limit shared by the whole process, disabled until set
+static BUCKET: Mutex<Option<Bucket>> = Mutex::new(None);
+
+/// Lock the bucket, recovering from poisoning as it is always left consistent
+fn bucket() -> MutexGuard<'static, Option<Bucket>> {
+    BUCKET.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Limit exports to `per_sec` per second across the process
+///
+/// Up to one second of budget accumulates while idle, so a burst of
+/// `per_sec` exports passes unthrottled. Setting a limit refills the bucket
+/// and keeps the current mode.
+/// # Arguments
+/// * `per_sec` - Exports allowed per second, 0 to disable the limit
+#[inline]
+pub fn set_export_rate_limit(per_sec: u32) {
+    let mut bucket = bucket();
+    let mode = bucket.as_ref().map_or_else(RateLimitMode::default, |current| current.mode);
+    *bucket = Some(Bucket::new(per_sec, mode, Instant::now()));
+}
+
+/// Choose whether exports over the rate limit block or fail
+/// # Arguments
+/// * `mode` - Behavior when the budget is exhausted
+#[inline]
+pub fn set_export_rate_limit_mode(mode: RateLimitMode) {
+    let mut bucket = bucket();
+    if let Some(ref mut current) = *bucket {
+        current.mode = mode;
+    } else {
+        *bucket = Some(Bucket::new(0, mode, Instant::now()));
+    }
+}
+
+/// Take a token for one export, blocking or failing per the configured mode
+/// # Errors
+/// `ObmmError::RateLimited` if the budget is exhausted in `RateLimitMode::Error`
+pub(crate) fn acquire() -> Result<(), ObmmError> {
+    loop {
+        let mut guard = bucket();
+        let Some(ref mut current) = *guard else {
+            return Ok(());
+        };
+        let Some(wait) = current.try_take(Instant::now()) else {
+            return Ok(());
+        };
+        if current.mode == RateLimitMode::Error {
+            return Err(ObmmError::RateLimited);
+        }
+        // sleep without the lock so other exporters can queue up
+        drop(guard);
+        thread::sleep(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(4, RateLimitMode::Block, start);
+        for _ in 0..4 {
+            assert_eq!(bucket.try_take(start), None);
+        }
+        assert_eq!(bucket.try_take(start), Some(Duration::from_millis(250)));
+        assert_eq!(bucket.try_take(start + Duration::from_millis(100)), Some(Duration::from_millis(150)));
+        assert_eq!(bucket.try_take(start + Duration::from_millis(250)), None);
+
+        // idle time refills at most one second of budget
+        let later = start + Duration::from_secs(90);
+        for _ in 0..4 {
+            assert_eq!(bucket.try_take(later), None);
+        }
+        assert!(bucket.try_take(later).is_some());
+
+        let mut disabled = Bucket::new(0, RateLimitMode::Block, start);
+        assert_eq!(disabled.try_take(start), None);
+    }
+}
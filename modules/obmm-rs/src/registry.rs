@@ -0,0 +1,324 @@
+//! Process-global table of the descriptors of live exports and imports
+//!
+//! The table is updated by the export/import wrappers after their FFI call
+//! returns, so its lock is never held across a call into the OBMM library.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::events::{self, ObmmEvent};
+use crate::{MemId, ObmmError, ObmmMemDesc, ObmmUnexportFlags, ObmmUnimportFlags, mem_unexport, mem_unimport};
+
+/// How a live region came to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// Exported by this process
+    Export,
+    /// Imported by this process onto the given NUMA node
+    Import(i32),
+}
+
+/// Tracked state of a live region
+#[derive(Debug)]
+struct Entry {
+    /// Whether the region was exported or imported
+    kind: Kind,
+    /// Descriptor with privilege data in binary serialized form
+    desc: ObmmMemDesc<Vec<u8>>,
+    /// Whether the region is pinned against reclamation
+    pinned: bool,
+    /// CNAs allowed to import an export, `None` if unrestricted
+    allowed_cnas: Option<Vec<u32>>,
+}
+
+/// Live regions by Memory ID
+static TABLE: LazyLock<Mutex<HashMap<MemId, Entry>>> = LazyLock::new(Mutex::default);
+
+/// Lock the table, recovering from poisoning as entries are always left consistent
+fn table() -> MutexGuard<'static, HashMap<MemId, Entry>> {
+    TABLE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Copy a descriptor with its privilege data in binary serialized form
+fn opaque<T: Serialize>(desc: &ObmmMemDesc<T>) -> ObmmMemDesc<Vec<u8>> {
+    ObmmMemDesc {
+        addr: desc.addr,
+        length: desc.length,
+        seid: desc.seid,
+        deid: desc.deid,
+        tokenid: desc.tokenid,
+        scna: desc.scna,
+        dcna: desc.dcna,
+        priv_len: desc.priv_len,
+        // types that fail to serialize are tracked with empty privilege data
+        priv_data: bincode::serialize(&desc.priv_data).unwrap_or_default(),
+        schema_version: desc.schema_version,
+        per_node: desc.per_node,
+        export_flags: desc.export_flags,
+        expires_at: desc.expires_at,
+        lifetime: desc.lifetime,
+    }
+}
+
+/// Track a region after it was exported or imported, notifying subscribers
+/// of new regions
+pub(crate) fn record<T: Serialize>(memid: MemId, kind: Kind, desc: &ObmmMemDesc<T>) {
+    insert(memid, kind, desc, None);
+}
+
+/// Track an export importable only by `allowed_cnas`, notifying subscribers
+///
+/// The allow-list is stored in the same insert as the export, so no import
+/// can observe the export without it.
+pub(crate) fn record_restricted<T: Serialize>(memid: MemId, desc: &ObmmMemDesc<T>, allowed_cnas: &[u32]) {
+    insert(memid, Kind::Export, desc, Some(allowed_cnas.to_vec()));
+}
+
+/// Insert the entry of a region, notifying subscribers of new regions
+fn insert<T: Serialize>(memid: MemId, kind: Kind, desc: &ObmmMemDesc<T>, allowed_cnas: Option<Vec<u32>>) {
+    let bytes = desc.length;
+    let desc = opaque(desc);
+    let prev = table().insert(memid, Entry { kind, desc, pinned: false, allowed_cnas });
+    if prev.is_none() {
+        events::emit(match kind {
+            Kind::Export => ObmmEvent::Exported { memid, bytes },
+            Kind::Import(node) => ObmmEvent::Imported { memid, node },
+        });
+    }
+}
+
+/// Stop tracking a region after it was unexported or unimported, notifying subscribers
+pub(crate) fn forget(memid: MemId) {
+    let removed = table().remove(&memid);
+    if let Some(entry) = removed {
+        events::emit(match entry.kind {
+            Kind::Export => ObmmEvent::Unexported { memid },
+            Kind::Import(_) => ObmmEvent::Unimported { memid },
+        });
+    }
+}
+
+/// Whether a region is pinned, `None` if `memid` is not live
+pub(crate) fn pinned(memid: MemId) -> Option<bool> {
+    table().get(&memid).map(|entry| entry.pinned)
+}
+
+/// Record the pinned state of a live region
+pub(crate) fn set_pinned(memid: MemId, pinned: bool) {
+    if let Some(entry) = table().get_mut(&memid) {
+        entry.pinned = pinned;
+    }
+}
+
+/// Privilege data of a live region, `None` if `memid` is not live or it
+/// does not decode as `T`
+pub(crate) fn priv_data<T: DeserializeOwned>(memid: MemId) -> Option<T> {
+    table().get(&memid).and_then(|entry| bincode::deserialize(&entry.desc.priv_data).ok())
+}
+
+/// Descriptor of a live export of this process with privilege data decoded
+/// as `T`, `None` if `memid` is not a live export or it does not decode
+pub(crate) fn export_descriptor<T: DeserializeOwned>(memid: MemId) -> Option<ObmmMemDesc<T>> {
+    let table = table();
+    let entry = table.get(&memid).filter(|entry| entry.kind == Kind::Export)?;
+    let desc = &entry.desc;
+    Some(ObmmMemDesc {
+        addr: desc.addr,
+        length: desc.length,
+        seid: desc.seid,
+        deid: desc.deid,
+        tokenid: desc.tokenid,
+        scna: desc.scna,
+        dcna: desc.dcna,
+        priv_len: desc.priv_len,
+        priv_data: bincode::deserialize(&desc.priv_data).ok()?,
+        schema_version: desc.schema_version,
+        per_node: desc.per_node,
+        export_flags: desc.export_flags,
+        expires_at: desc.expires_at,
+        lifetime: desc.lifetime,
+    })
+}
+
+/// Add a CNA to the allow-list of a live export, if it has one
+pub(crate) fn allow_cna(memid: MemId, cna: u32) {
+    if let Some(cnas) = table().get_mut(&memid).and_then(|entry| entry.allowed_cnas.as_mut())
+        && !cnas.contains(&cna)
+    {
+        cnas.push(cna);
+    }
+}
+
+/// Replace the privilege data of a live region
+pub(crate) fn set_priv_data<T: Serialize>(memid: MemId, priv_data: &T) {
+    if let Some(entry) = table().get_mut(&memid) {
+        entry.desc.priv_data = bincode::serialize(priv_data).unwrap_or_default();
+    }
+}
+
+/// Whether `memid` is a live import of this process
+pub(crate) fn is_import(memid: MemId) -> bool {
+    table().get(&memid).is_some_and(|entry| matches!(entry.kind, Kind::Import(_)))
+}
+
+/// Live export of this process containing `pa` and the offset into it
+///
+/// Of several exports containing `pa`, the one with the smallest Memory ID wins.
+pub(crate) fn export_containing(pa: u64) -> Option<(MemId, u64)> {
+    table()
+        .iter()
+        .filter(|&(_, entry)| entry.kind == Kind::Export)
+        .filter(|&(_, entry)| entry.desc.contains_addr(pa))
+        .filter_map(|(&memid, entry)| Some((memid, pa.checked_sub(entry.desc.addr)?)))
+        .min()
+}
+
+/// Whether a live import of this process matches the region of export `memid`
+pub(crate) fn imported_locally(memid: MemId) -> bool {
+    let table = table();
+    let Some(export) = table.get(&memid).filter(|entry| entry.kind == Kind::Export) else {
+        return false;
+    };
+    table.values().any(|entry| matches!(entry.kind, Kind::Import(_)) && entry.desc.same_region(&export.desc))
+}
+
+/// Check the importing CNA of `desc` against the allow-list of its export
+///
+/// Only exports made by this process are known, imports of other regions
+/// are left to the driver to police.
+/// # Returns
+/// # Errors
+/// Ok(()) if allowed or the export is unknown, `ObmmError::CnaNotAllowed` otherwise
+pub(crate) fn check_import_cna<T>(desc: &ObmmMemDesc<T>) -> Result<(), ObmmError> {
+    let denied = table().values().any(|entry| {
+        entry.kind == Kind::Export
+            && entry.desc.addr == desc.addr
+            && entry.desc.length == desc.length
+            && entry.desc.seid() == desc.seid()
+            && entry.desc.deid() == desc.deid()
+            && entry.allowed_cnas.as_ref().is_some_and(|cnas| !cnas.contains(&desc.dcna))
+    });
+    if denied { Err(ObmmError::CnaNotAllowed(desc.dcna)) } else { Ok(()) }
+}
+
+/// Look up the descriptor of a live export or import
+/// # Arguments
+/// * `memid` - Memory ID to look up
+/// # Returns
+/// Descriptor with privilege data in binary serialized form, `None` if `memid` is not live
+#[inline]
+#[must_use]
+pub fn lookup_descriptor(memid: MemId) -> Option<ObmmMemDesc<Vec<u8>>> {
+    table().get(&memid).map(|entry| entry.desc.clone())
+}
+
+/// Memory IDs of all live exports and imports, in ascending order
+#[inline]
+#[must_use]
+pub fn live_memids() -> Vec<MemId> {
+    let mut memids: Vec<MemId> = table().keys().copied().collect();
+    memids.sort_unstable();
+    memids
+}
+
+/// Unexport and unimport every live region, e.g. at process exit
+///
+/// Meant to be called once on shutdown, from a signal handler such as one
+/// installed with `ctrlc` or before returning from `main`. Every region is
+/// attempted even if some fail; failed regions stay in the table.
+/// Imports are released before exports, so loopback imports do not keep
+/// their export alive.
+/// # Returns
+/// # Errors
+/// Ok(()) if every region was released, the Memory IDs and errors of the
+/// regions that failed otherwise. Failures carry the `ObmmError` of
+/// `mem_unexport` / `mem_unimport` rather than a bare return code, so a
+/// missing library (`ObmmError::LibraryUnavailable`) is told apart from a
+/// kernel error (`ObmmError::Ffi` with the return code).
+#[inline]
+pub fn obmm_cleanup() -> Result<(), Vec<(MemId, ObmmError)>> {
+    // snapshot first so the lock is not held across the FFI calls
+    let mut live: Vec<(MemId, Kind)> = table().iter().map(|(&memid, entry)| (memid, entry.kind)).collect();
+    live.sort_unstable_by_key(|&(memid, kind)| (kind == Kind::Export, memid));
+    let failures: Vec<(MemId, ObmmError)> = live
+        .into_iter()
+        .filter_map(|(memid, kind)| {
+            let result = match kind {
+                Kind::Export => mem_unexport(memid, ObmmUnexportFlags::empty()),
+                Kind::Import(_) => mem_unimport(memid, ObmmUnimportFlags::empty()),
+            };
+            result.err().map(|err| (memid, err))
+        })
+        .collect();
+    if failures.is_empty() { Ok(()) } else { Err(failures) }
+}
+
+/// Pairs of regions whose `[addr, addr + length)` ranges intersect
+///
+/// Empty regions overlap nothing, and a range running past `u64::MAX` is
+/// clamped to it.
+/// # Arguments
+/// * `regions` - Memory ID, address and length of each region
+/// # Returns
+/// Offending pairs with the smaller Memory ID first, in ascending order
+#[inline]
+#[must_use]
+pub fn find_overlaps(regions: &[(MemId, u64, u64)]) -> Vec<(MemId, MemId)> {
+    let end = |addr: u64, length: u64| addr.saturating_add(length);
+    let mut pairs: Vec<(MemId, MemId)> = regions
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &(id_a, addr_a, len_a))| {
+            regions.iter().skip(i.saturating_add(1)).filter_map(move |&(id_b, addr_b, len_b)| {
+                let overlap = len_a > 0 && len_b > 0 && addr_a < end(addr_b, len_b) && addr_b < end(addr_a, len_a);
+                overlap.then(|| (id_a.min(id_b), id_a.max(id_b)))
+            })
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Pairs of live exports whose address ranges intersect
+///
+/// Imports are not considered, as an import of a local export legitimately
+/// shares its range.
+/// # Returns
+/// Offending pairs as returned by `find_overlaps`
+#[inline]
+#[must_use]
+pub fn check_overlaps() -> Vec<(MemId, MemId)> {
+    let exports: Vec<(MemId, u64, u64)> = table()
+        .iter()
+        .filter(|&(_, entry)| entry.kind == Kind::Export)
+        .map(|(&memid, entry)| (memid, entry.desc.addr, entry.desc.length))
+        .collect();
+    find_overlaps(&exports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, ObmmExportFlags, ObmmUnexportFlags, UbPrivData, mem_export, mem_unexport};
+
+    #[test]
+    fn test_lookup_descriptor() -> anyhow::Result<()> {
+        let (memid, desc) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::ALLOWMMAP)?;
+        let tracked = lookup_descriptor(memid).ok_or_else(|| anyhow::anyhow!("MemID {memid} not tracked"))?;
+        assert_eq!((tracked.addr, tracked.length), (desc.addr, desc.length));
+        assert_eq!(tracked.priv_data, bincode::serialize(&desc.priv_data)?);
+
+        mem_unexport(memid, ObmmUnexportFlags::empty()).map_err(|code| anyhow::anyhow!("mem_unexport failed with code {code}"))?;
+        assert!(lookup_descriptor(memid).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let regions = [(1, 0x1000, 0x2000), (2, 0x2000, 0x1000), (3, 0x3000, 0x1000), (4, u64::MAX, 2)];
+        assert_eq!(find_overlaps(&regions), [(1, 2)]);
+        assert!(find_overlaps(&[(1, 0x1000, 0), (2, 0x1000, 0x1000)]).is_empty());
+    }
+}
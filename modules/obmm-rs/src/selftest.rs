@@ -0,0 +1,112 @@
+//! One-shot sanity check of the OBMM setup: export, codecs, import and cleanup
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    MemId, OBMM_INVALID_MEMID, ObmmExportFlags, ObmmImportFlags, ObmmMemDesc, ObmmUnexportFlags, ObmmUnimportFlags,
+    UbPrivData, lookup_descriptor, mem_export, mem_import, mem_unexport, mem_unimport, numa_lengths,
+};
+
+/// Length of the region exported by the self-test, 2MB on node 0
+const SELFTEST_LENGTH: usize = 2 * 1024 * 1024;
+
+/// Outcome of one self-test step
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SelftestStep {
+    /// Name of the step, e.g. `"export"`
+    pub name: &'static str,
+    /// Ok(()) if the step passed, the failure otherwise
+    pub result: Result<(), String>,
+    /// Time spent in the step
+    pub elapsed: Duration,
+}
+
+/// Steps run by `run_selftest`, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SelftestReport {
+    /// Steps that ran; steps depending on a failed one are left out
+    pub steps: Vec<SelftestStep>,
+}
+
+impl SelftestReport {
+    /// Whether every step ran and passed
+    #[inline]
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        !self.steps.is_empty() && self.steps.iter().all(|step| step.result.is_ok())
+    }
+}
+
+/// Run one step, recording its outcome and timing in `steps`
+fn timed<R>(steps: &mut Vec<SelftestStep>, name: &'static str, step: impl FnOnce() -> anyhow::Result<R>) -> Option<R> {
+    let start = Instant::now();
+    let result = step();
+    let elapsed = start.elapsed();
+    let (result, value) = match result {
+        Ok(value) => (Ok(()), Some(value)),
+        Err(err) => (Err(format!("{err:#}")), None),
+    };
+    steps.push(SelftestStep { name, result, elapsed });
+    value
+}
+
+/// Round-trip a small export through the descriptor codecs and a local import
+///
+/// Exports a 2MB region, passes its descriptor through JSON and the binary
+/// codec, imports the decoded descriptor, checks the import is tracked as a
+/// new Memory ID for the same region, then unimports and unexports it. The
+/// region is released even if a later step fails. Works with the hooked and
+/// the real backend alike.
+/// # Returns
+/// # Errors
+/// Report of each step on success, whether or not the steps passed,
+/// `anyhow::Error` if the OBMM library can't be loaded
+#[inline]
+pub fn run_selftest() -> anyhow::Result<SelftestReport> {
+    #[cfg(not(feature = "hook"))]
+    crate::ffi_ready()?;
+    let mut steps = Vec::new();
+    let exported = timed(&mut steps, "export", || mem_export::<UbPrivData>(&numa_lengths! { 0 => SELFTEST_LENGTH }, ObmmExportFlags::ALLOWMMAP));
+    if let Some((memid, desc)) = exported {
+        let decoded = timed(&mut steps, "json", || {
+            let decoded = ObmmMemDesc::<UbPrivData>::from_json(&desc.to_json()?)?;
+            anyhow::ensure!(decoded == desc, "JSON round trip changed the descriptor");
+            Ok(decoded)
+        })
+        .and_then(|decoded| {
+            timed(&mut steps, "binary", || {
+                let decoded = ObmmMemDesc::<UbPrivData>::from_bytes(&decoded.to_bytes()?)?;
+                anyhow::ensure!(decoded == desc, "binary round trip changed the descriptor");
+                Ok(decoded)
+            })
+        });
+        let imported = decoded.and_then(|decoded| timed(&mut steps, "import", || Ok(mem_import(&decoded, ObmmImportFlags::ALLOWMMAP, 0)?)));
+        if let Some((imported, _)) = imported {
+            let _verified = timed(&mut steps, "verify", || verify_import(memid, imported, &desc));
+            let _unimported = timed(&mut steps, "unimport", || {
+                mem_unimport(imported, ObmmUnimportFlags::empty())?;
+                Ok(())
+            });
+        }
+        let _unexported = timed(&mut steps, "unexport", || Ok(mem_unexport(memid, ObmmUnexportFlags::empty())?));
+    }
+    Ok(SelftestReport { steps })
+}
+
+/// Check that `imported` is a new Memory ID tracking the region of export `memid`
+fn verify_import(memid: MemId, imported: MemId, desc: &ObmmMemDesc<UbPrivData>) -> anyhow::Result<()> {
+    anyhow::ensure!(imported != OBMM_INVALID_MEMID, "import returned the invalid MemID");
+    anyhow::ensure!(imported != memid, "import reused MemID {memid} of the export");
+    let tracked = lookup_descriptor(imported).ok_or_else(|| anyhow::anyhow!("MemID {imported} is not tracked"))?;
+    anyhow::ensure!(
+        (tracked.addr, tracked.length) == (desc.addr, desc.length),
+        "MemID {imported} tracks {:#x}+{:#x} instead of {:#x}+{:#x}",
+        tracked.addr,
+        tracked.length,
+        desc.addr,
+        desc.length
+    );
+    Ok(())
+}
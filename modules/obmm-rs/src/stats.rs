@@ -0,0 +1,90 @@
+//! Per-region access statistics reported by the driver
+
+use std::time::SystemTime;
+#[cfg(not(feature = "hook"))]
+use std::time::Duration;
+
+#[cfg(not(feature = "hook"))]
+use anyhow::Context;
+
+use crate::MemId;
+#[cfg(not(feature = "hook"))]
+use crate::{ObmmError, obmm_access_stats};
+
+/// Access counters of a region
+///
+/// The driver samples accesses, so the counts are approximate and meant for
+/// comparing regions, e.g. to pick migration candidates, not for accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AccessStats {
+    /// Number of reads observed
+    pub reads: u64,
+    /// Number of writes observed
+    pub writes: u64,
+    /// Time of the most recent access, `None` if never accessed
+    pub last_access: Option<SystemTime>,
+}
+
+/// Query how heavily a region is accessed
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// # Returns
+/// # Errors
+/// Access counters on success, `anyhow::Error` if the region is not live
+#[cfg(feature = "hook")]
+#[inline]
+pub fn mem_access_stats(memid: MemId) -> anyhow::Result<AccessStats> {
+    // hooked implementation
+    if crate::lookup_descriptor(memid).is_none() {
+        anyhow::bail!("MemID {memid} is not live");
+    }
+    Ok(crate::hook::access_counters(memid).map_or(
+        AccessStats { reads: 0, writes: 0, last_access: None },
+        |(reads, writes, last)| AccessStats { reads, writes, last_access: Some(last) },
+    ))
+}
+
+/// Query how heavily a region is accessed
+/// # Arguments
+/// * `memid` - Memory ID of a live export or import
+/// # Returns
+/// # Errors
+/// Access counters on success, `anyhow::Error` on failure
+#[cfg(not(feature = "hook"))]
+#[inline]
+pub fn mem_access_stats(memid: MemId) -> anyhow::Result<AccessStats> {
+    crate::ffi_ready()?;
+    let (mut reads, mut writes, mut last_access_ns) = (0, 0, 0);
+    let ret = unsafe { obmm_access_stats(memid, &raw mut reads, &raw mut writes, &raw mut last_access_ns) };
+    if ret != 0 {
+        return Err(ObmmError::Ffi(ret)).with_context(|| format!("Failed to query access stats of MemID {memid}"));
+    }
+    let last_access = (last_access_ns != 0)
+        .then(|| SystemTime::UNIX_EPOCH.checked_add(Duration::from_nanos(last_access_ns)))
+        .flatten();
+    Ok(AccessStats { reads, writes, last_access })
+}
+
+#[cfg(all(test, feature = "hook"))]
+mod tests {
+    use super::*;
+    use crate::{MAX_NUMA_NODES, ObmmExportFlags, ObmmUnexportFlags, UbPrivData, mem_export, mem_unexport, record_hook_access};
+
+    #[test]
+    fn test_access_stats() -> anyhow::Result<()> {
+        let (memid, _) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty())?;
+        assert_eq!(mem_access_stats(memid)?, AccessStats { reads: 0, writes: 0, last_access: None });
+
+        let before = SystemTime::now();
+        record_hook_access(memid, 3, 1);
+        record_hook_access(memid, 2, 0);
+        let stats = mem_access_stats(memid)?;
+        assert_eq!((stats.reads, stats.writes), (5, 1));
+        assert!(stats.last_access.is_some_and(|last| last >= before));
+
+        mem_unexport(memid, ObmmUnexportFlags::empty())?;
+        assert!(mem_access_stats(memid).is_err());
+        Ok(())
+    }
+}
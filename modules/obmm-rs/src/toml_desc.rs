@@ -0,0 +1,125 @@
+//! TOML form of the descriptor, with eids as hex strings for hand editing
+
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{LifetimeClass, MAX_NUMA_NODES, ObmmExportFlags, ObmmMemDesc};
+
+/// Mirror of `ObmmMemDesc` whose eids serialize as hex strings
+///
+/// `P` is `&T` when serializing and `T` when deserializing, so neither
+/// direction needs to clone the privilege data.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TomlDesc<P> {
+    /// Base address of the memory region
+    addr: u64,
+    /// Length of the memory region
+    length: u64,
+    /// 128bit eid, ordered by little-endian
+    #[serde(with = "hex_eid")]
+    seid: [u8; 16],
+    /// 128bit deid, ordered by little-endian
+    #[serde(with = "hex_eid")]
+    deid: [u8; 16],
+    /// Token ID
+    tokenid: u32,
+    /// Source CNA
+    scna: u32,
+    /// Destination CNA
+    dcna: u32,
+    /// Length of privilege data
+    priv_len: u16,
+    /// Privilege data
+    priv_data: P,
+    /// Descriptor schema version
+    #[serde(default = "crate::schema_version_v1")]
+    schema_version: u32,
+    /// Per-NUMA-node breakdown of `length`
+    #[serde(default)]
+    per_node: Option<[u64; MAX_NUMA_NODES]>,
+    /// Flags the region was exported with
+    #[serde(default)]
+    export_flags: ObmmExportFlags,
+    /// Time after which the descriptor is stale
+    #[serde(default)]
+    expires_at: Option<SystemTime>,
+    /// Intended lifetime of the export
+    #[serde(default)]
+    lifetime: LifetimeClass,
+}
+
+impl<'a, T> From<&'a ObmmMemDesc<T>> for TomlDesc<&'a T> {
+    #[inline]
+    fn from(desc: &'a ObmmMemDesc<T>) -> Self {
+        TomlDesc {
+            addr: desc.addr,
+            length: desc.length,
+            seid: desc.seid,
+            deid: desc.deid,
+            tokenid: desc.tokenid,
+            scna: desc.scna,
+            dcna: desc.dcna,
+            priv_len: desc.priv_len,
+            priv_data: &desc.priv_data,
+            schema_version: desc.schema_version,
+            per_node: desc.per_node,
+            export_flags: desc.export_flags,
+            expires_at: desc.expires_at,
+            lifetime: desc.lifetime,
+        }
+    }
+}
+
+impl<T> From<TomlDesc<T>> for ObmmMemDesc<T> {
+    #[inline]
+    fn from(desc: TomlDesc<T>) -> Self {
+        ObmmMemDesc {
+            addr: desc.addr,
+            length: desc.length,
+            seid: desc.seid,
+            deid: desc.deid,
+            tokenid: desc.tokenid,
+            scna: desc.scna,
+            dcna: desc.dcna,
+            priv_len: desc.priv_len,
+            priv_data: desc.priv_data,
+            schema_version: desc.schema_version,
+            per_node: desc.per_node,
+            export_flags: desc.export_flags,
+            expires_at: desc.expires_at,
+            lifetime: desc.lifetime,
+        }
+    }
+}
+
+/// Serde helper rendering a 128bit eid as 32 hex digits, in byte order
+mod hex_eid {
+    use std::fmt::Write as _;
+
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Serialize an eid as a hex string
+    pub(super) fn serialize<S: Serializer>(eid: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = eid.iter().fold(String::with_capacity(32), |mut hex, byte| {
+            let _result = write!(hex, "{byte:02x}");
+            hex
+        });
+        serializer.serialize_str(&hex)
+    }
+
+    /// Deserialize an eid from a hex string
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != 32 || !hex.is_ascii() {
+            return Err(D::Error::custom(format!("eid must be 32 hex digits, got {hex:?}")));
+        }
+        let mut eid = [0; 16];
+        for (byte, digits) in eid.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits).map_err(D::Error::custom)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(D::Error::custom)?;
+        }
+        Ok(eid)
+    }
+}
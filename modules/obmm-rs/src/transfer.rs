@@ -0,0 +1,63 @@
+//! Transfer form of the descriptor, holding only the fields meaningful to a remote peer
+//!
+//! `addr` and the privilege data travel, as the importer hands them to the
+//! driver unchanged. `per_node`, `export_flags`, `expires_at` and `lifetime`
+//! are local-only: they describe the region as the exporting host allocated
+//! it, or its bookkeeping, and take their defaults on the importer.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ObmmMemDesc;
+
+/// Transferable subset of `ObmmMemDesc`, with privilege data of type `P`
+///
+/// `P` is `&T` when serializing from a descriptor and `T` when deserializing.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TransferDesc<P> {
+    /// UB address of the memory region
+    addr: u64,
+    /// Length of the memory region
+    length: u64,
+    /// 128bit eid, ordered by little-endian
+    seid: [u8; 16],
+    /// 128bit deid, ordered by little-endian
+    deid: [u8; 16],
+    /// Token ID
+    tokenid: u32,
+    /// Source CNA
+    scna: u32,
+    /// Destination CNA
+    dcna: u32,
+    /// Privilege data
+    priv_data: P,
+}
+
+impl<'desc, T> From<&'desc ObmmMemDesc<T>> for TransferDesc<&'desc T> {
+    #[inline]
+    fn from(desc: &'desc ObmmMemDesc<T>) -> Self {
+        TransferDesc {
+            addr: desc.addr,
+            length: desc.length,
+            seid: desc.seid,
+            deid: desc.deid,
+            tokenid: desc.tokenid,
+            scna: desc.scna,
+            dcna: desc.dcna,
+            priv_data: &desc.priv_data,
+        }
+    }
+}
+
+impl<T> TransferDesc<T> {
+    /// Copy the transferred fields into `desc`, leaving its local fields as they are
+    pub(crate) fn apply_to(self, desc: &mut ObmmMemDesc<T>) {
+        desc.addr = self.addr;
+        desc.length = self.length;
+        desc.seid = self.seid;
+        desc.deid = self.deid;
+        desc.tokenid = self.tokenid;
+        desc.scna = self.scna;
+        desc.dcna = self.dcna;
+        desc.priv_data = self.priv_data;
+    }
+}
@@ -0,0 +1,186 @@
+//! Descriptor exchange over a Unix domain socket
+//!
+//! Every message is a frame: a little-endian `u32` payload length followed by
+//! a bincode-encoded payload. A client sends one request frame and receives
+//! one response frame per connection.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use serde::{Serialize, Deserialize};
+
+use crate::{MemId, ObmmMemDesc, UbPrivData};
+
+/// Largest frame payload accepted from a peer
+const MAX_FRAME_LEN: u32 = 1 << 20;
+
+/// Request sent by a `DescClient`
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    /// Fetch the descriptor registered for a Memory ID
+    Fetch(MemId),
+    /// Ask whether a descriptor with this fingerprint is registered
+    Validate(u64),
+}
+
+/// Response sent by a `DescServer`: the descriptor, or why it is unavailable
+type Response = Result<ObmmMemDesc<UbPrivData>, String>;
+
+/// Write one length-delimited frame
+fn write_frame<P: Serialize>(stream: &mut UnixStream, payload: &P) -> anyhow::Result<()> {
+    let bytes = bincode::serialize(payload)?;
+    let len = u32::try_from(bytes.len())?;
+    if len > MAX_FRAME_LEN {
+        anyhow::bail!("Frame of {len} bytes exceeds the {MAX_FRAME_LEN} byte limit");
+    }
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Read one length-delimited frame
+fn read_frame<P: for<'de> Deserialize<'de>>(stream: &mut UnixStream) -> anyhow::Result<P> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        anyhow::bail!("Frame of {len} bytes exceeds the {MAX_FRAME_LEN} byte limit");
+    }
+    let mut bytes = vec![0; usize::try_from(len)?];
+    stream.read_exact(&mut bytes)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+/// Server answering descriptor requests from its registry
+#[derive(Debug)]
+pub struct DescServer {
+    /// Listening socket
+    listener: UnixListener,
+    /// Path the socket is bound to, removed on drop
+    path: PathBuf,
+    /// Descriptors served, by Memory ID
+    registry: Arc<Mutex<HashMap<MemId, ObmmMemDesc<UbPrivData>>>>,
+}
+
+impl DescServer {
+    /// Bind a server to a Unix socket at `path`
+    /// # Arguments
+    /// * `path` - Socket path, must not exist yet
+    /// # Returns
+    /// # Errors
+    /// `DescServer` on success, `anyhow::Error` if the socket cannot be bound
+    #[inline]
+    pub fn bind(path: &Path) -> anyhow::Result<Self> {
+        let listener = UnixListener::bind(path)?;
+        Ok(DescServer { listener, path: path.to_owned(), registry: Arc::default() })
+    }
+
+    /// Serve `desc` for `memid`, replacing any descriptor already registered
+    #[inline]
+    pub fn register(&self, memid: MemId, desc: ObmmMemDesc<UbPrivData>) {
+        let _prev = self.registry.lock().unwrap_or_else(PoisonError::into_inner).insert(memid, desc);
+    }
+
+    /// Stop serving `memid`
+    /// # Returns
+    /// The descriptor that was registered, if any
+    #[inline]
+    pub fn unregister(&self, memid: MemId) -> Option<ObmmMemDesc<UbPrivData>> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner).remove(&memid)
+    }
+
+    /// Accept one connection and answer its request
+    /// # Returns
+    /// # Errors
+    /// Ok(()) on success, `anyhow::Error` on socket or framing failures
+    #[inline]
+    pub fn serve_one(&self) -> anyhow::Result<()> {
+        let (mut stream, _) = self.listener.accept()?;
+        match read_frame(&mut stream)? {
+            Request::Fetch(memid) => {
+                let response: Response = self
+                    .registry
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(&memid)
+                    .copied()
+                    .ok_or_else(|| format!("No descriptor registered for MemID {memid}"));
+                write_frame(&mut stream, &response)
+            }
+            Request::Validate(fingerprint) => {
+                let matches = self
+                    .registry
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .values()
+                    .any(|desc| desc.fingerprint().is_ok_and(|registered| registered == fingerprint));
+                write_frame(&mut stream, &matches)
+            }
+        }
+    }
+
+    /// Answer requests until accepting a connection fails
+    /// # Returns
+    /// # Errors
+    /// `anyhow::Error` once the listener fails
+    #[inline]
+    pub fn serve(&self) -> anyhow::Result<()> {
+        loop {
+            self.serve_one()?;
+        }
+    }
+}
+
+impl Drop for DescServer {
+    #[inline]
+    fn drop(&mut self) {
+        // a leftover socket file only prevents rebinding the same path
+        let _result = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Client fetching descriptors from a `DescServer`
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct DescClient;
+
+impl DescClient {
+    /// Fetch the descriptor for `memid` from the server at `path`
+    /// # Arguments
+    /// * `path` - Socket path of the server
+    /// * `memid` - Memory ID to fetch
+    /// # Returns
+    /// # Errors
+    /// `ObmmMemDesc` on success, `anyhow::Error` on socket failures or if the server has no such descriptor
+    #[inline]
+    pub fn fetch(path: &Path, memid: MemId) -> anyhow::Result<ObmmMemDesc<UbPrivData>> {
+        let mut stream = UnixStream::connect(path)?;
+        write_frame(&mut stream, &Request::Fetch(memid))?;
+        let response: Response = read_frame(&mut stream)?;
+        response.map_err(anyhow::Error::msg)
+    }
+
+    /// Ask the server at `path` whether `desc` is still current
+    ///
+    /// Only the fingerprint of `desc` is sent, see `ObmmMemDesc::fingerprint`;
+    /// the server compares it against the descriptors it has registered, so
+    /// a descriptor re-exported or unregistered since it was fetched is not current.
+    /// The fingerprint covers `expires_at` and `lifetime` too: a copy whose
+    /// expiry or lifetime class was changed, e.g. by saving it with a TTL,
+    /// doesn't match the registered descriptor.
+    /// # Arguments
+    /// * `path` - Socket path of the server
+    /// * `desc` - Descriptor to check
+    /// # Returns
+    /// # Errors
+    /// Whether the server has a matching descriptor on success, `anyhow::Error` on socket failures
+    #[inline]
+    pub fn validate(path: &Path, desc: &ObmmMemDesc<UbPrivData>) -> anyhow::Result<bool> {
+        let mut stream = UnixStream::connect(path)?;
+        write_frame(&mut stream, &Request::Validate(desc.fingerprint()?))?;
+        read_frame(&mut stream)
+    }
+}
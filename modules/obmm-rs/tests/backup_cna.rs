@@ -0,0 +1,20 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_export_ha_backup() {
+    let export = mem_export_ha::<UbPrivData>(&numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap();
+    assert!(export.backup.is_none() && export.warnings.is_empty());
+    mem_unexport(export.memid, ObmmUnexportFlags::empty()).unwrap();
+
+    set_backup_cna(Some(21));
+    let export = mem_export_ha::<UbPrivData>(&numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap();
+    let backup = export.backup.unwrap();
+    assert_eq!(backup.dcna, 21);
+    assert_ne!(export.primary.dcna, 21);
+    assert!(backup.same_region(&export.primary));
+    assert!(export.warnings.is_empty());
+    mem_unexport(export.memid, ObmmUnexportFlags::empty()).unwrap();
+    set_backup_cna(None);
+}
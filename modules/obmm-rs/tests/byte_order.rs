@@ -0,0 +1,40 @@
+//! Pins the little-endian eid contract against known test vectors
+
+use obmm_rs::{ObmmMemDesc, UbPrivData};
+use serde::Deserialize;
+
+/// A known eid as a hex integer and its little-endian bytes
+#[derive(Deserialize)]
+struct EidVector {
+    /// 128bit value as 32 hex digits, most significant first
+    value: String,
+    /// Expected byte layout of `seid`/`deid`
+    bytes: [u8; 16],
+}
+
+#[test]
+fn test_eid_vectors() -> anyhow::Result<()> {
+    let vectors: Vec<EidVector> = serde_json::from_str(include_str!("vectors/eids.json"))?;
+    assert!(vectors.iter().any(|vector| vector.bytes.last().is_some_and(|byte| byte & 0x80 != 0)));
+    let mut desc = ObmmMemDesc::<UbPrivData>::default();
+    for vector in &vectors {
+        let value = u128::from_str_radix(&vector.value, 16)?;
+        desc.set_seid(value);
+        desc.set_deid(value);
+        assert_eq!(desc.seid, vector.bytes, "set_seid({:#x})", value);
+        assert_eq!(desc.deid, vector.bytes, "set_deid({:#x})", value);
+        desc.seid = vector.bytes;
+        desc.deid = vector.bytes;
+        assert_eq!(desc.seid_u128(), value);
+        assert_eq!(desc.deid_u128(), value);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_fixture() -> anyhow::Result<()> {
+    let desc = ObmmMemDesc::<UbPrivData>::from_json(include_str!("vectors/desc.json"))?;
+    assert_eq!(desc.seid_u128(), 0x8f0e_0d0c_0b0a_0908_0706_0504_0302_0100);
+    assert_eq!(desc.deid_u128(), 1);
+    Ok(())
+}
@@ -0,0 +1,17 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_exportable_capacity_spread() {
+    let mut capacity = [0; MAX_NUMA_NODES];
+    capacity[1] = 6 * 1024 * 1024;
+    capacity[4] = 2 * 1024 * 1024;
+    set_hook_capacity(capacity);
+    assert_eq!(exportable_capacity().unwrap(), capacity);
+
+    let lengths = NumaLengths::spread_by_capacity(4 * MB).unwrap();
+    assert_eq!((lengths[1], lengths[4]), (3 * MB, MB));
+    assert_eq!(lengths.iter().sum::<usize>(), 4 * MB);
+    assert!(NumaLengths::spread_by_capacity(9 * MB).is_err());
+}
@@ -0,0 +1,15 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_cleanup_releases_all_regions() {
+    let (_, desc) = mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::ALLOWMMAP).unwrap();
+    mem_export::<UbPrivData>(&[1024 * 1024; MAX_NUMA_NODES], ObmmExportFlags::empty()).unwrap();
+    mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+    assert_eq!(live_memids().len(), 3);
+
+    obmm_cleanup().unwrap();
+    assert!(live_memids().is_empty());
+    assert_eq!(imported_bytes_per_node().iter().sum::<u64>(), 0);
+}
@@ -0,0 +1,16 @@
+#![cfg(all(feature = "dlopen", not(feature = "hook")))]
+
+use obmm_rs::*;
+
+#[test]
+fn test_missing_library() {
+    // SAFETY: the only test of this binary, so no other thread reads the environment
+    unsafe { std::env::set_var(ENV_OBMM_LIBRARY, "/nonexistent/libobmm.so") };
+    let missing = supported_export_flags();
+    assert!(matches!(missing, Err(ObmmError::LibraryUnavailable(ref reason)) if reason.starts_with("/nonexistent/libobmm.so: ")));
+    assert!(mem_export::<UbPrivData>(&numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).is_err_and(|err| matches!(
+        err.downcast_ref::<ObmmError>(),
+        Some(&ObmmError::LibraryUnavailable(_))
+    )));
+    assert!(matches!(mem_unimport(1, ObmmUnimportFlags::empty()), Err(ObmmError::LibraryUnavailable(_))));
+}
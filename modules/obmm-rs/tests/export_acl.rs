@@ -0,0 +1,67 @@
+#![cfg(feature = "hook")]
+
+use std::sync::{Arc, Mutex};
+
+use obmm_rs::*;
+
+#[test]
+fn test_export_to_allowed_cnas() {
+    let mut lengths = vec![0; MAX_NUMA_NODES];
+    lengths[1] = 1024 * 1024;
+    let (memid, mut desc) = mem_export_to::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, &[7, 9]).unwrap();
+
+    desc.dcna = 9;
+    let (imported, _) = mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+    desc.dcna = 8;
+    assert_eq!(mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0), Err(ObmmError::CnaNotAllowed(8)));
+    assert!(mem_export_to::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP, &[]).is_err());
+
+    mem_unimport(imported, ObmmUnimportFlags::empty()).unwrap();
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+}
+
+#[test]
+fn test_grant_additional_cna() {
+    let (memid, desc) = mem_export_to::<UbPrivData>(&numa_lengths! { 0 => 2 * MB }, ObmmExportFlags::ALLOWMMAP, &[7]).unwrap();
+    let mut denied = desc;
+    denied.dcna = 11;
+    assert_eq!(mem_import(&denied, ObmmImportFlags::ALLOWMMAP, 0), Err(ObmmError::CnaNotAllowed(11)));
+
+    let granted = mem_grant::<UbPrivData>(memid, 11).unwrap();
+    assert_eq!(granted.dcna, 11);
+    assert!(granted.same_region(&desc));
+    let (imported, _) = mem_import(&granted, ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+    mem_unimport(imported, ObmmUnimportFlags::empty()).unwrap();
+
+    assert!(mem_grant::<UbPrivData>(imported, 12).is_err());
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    assert!(mem_grant::<UbPrivData>(memid, 12).is_err());
+}
+
+#[test]
+fn test_export_to_denies_from_first_event() {
+    // every export of this binary has an allow-list without CNA 8, so probing them all is harmless
+    let probes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&probes);
+    let subscription = subscribe(move |event| {
+        if let ObmmEvent::Exported { memid, .. } = event
+            && let Some(found) = lookup_descriptor(memid)
+        {
+            let mut probe = ObmmMemDesc::<UbPrivData>::builder()
+                .addr(found.addr)
+                .length(found.length)
+                .seid(found.seid)
+                .deid(found.deid)
+                .build()
+                .unwrap();
+            probe.dcna = 8;
+            sink.lock().unwrap().push((memid, mem_import(&probe, ObmmImportFlags::empty(), 0)));
+        }
+    });
+    let (memid, _) = mem_export_to::<UbPrivData>(&numa_lengths! { 2 => 3 * MB }, ObmmExportFlags::empty(), &[7]).unwrap();
+    unsubscribe(subscription);
+
+    let probed: Vec<_> = probes.lock().unwrap().drain(..).filter(|probe| probe.0 == memid).map(|probe| probe.1).collect();
+    assert_eq!(probed, [Err(ObmmError::CnaNotAllowed(8))]);
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+}
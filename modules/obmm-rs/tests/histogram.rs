@@ -0,0 +1,22 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_export_size_histogram() {
+    let before = export_size_histogram();
+    for (node, size) in [(0, 2048), (1, 4096), (1, 1024 * 1024), (2, 128 * 1024 * 1024 + 4096)] {
+        let mut lengths = vec![0; MAX_NUMA_NODES];
+        lengths[node] = size;
+        let (memid, _) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::empty()).unwrap();
+        mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    }
+    let (memid, _) = mem_export_at::<UbPrivData>(0x6300_0000_0000, 1024 * 1024, ObmmExportFlags::empty()).unwrap();
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    let after = export_size_histogram();
+    let added: Vec<(usize, u64)> = (0..EXPORT_SIZE_BUCKETS)
+        .map(|bucket| (bucket, after[bucket] - before[bucket]))
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    assert_eq!(added, [(0, 2), (8, 2), (15, 1)]);
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "hook")]
+
+use std::thread;
+
+use obmm_rs::*;
+
+#[test]
+fn test_export_idempotent_same_key() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = MemlinkDir::open(root.path()).unwrap();
+    let before = live_memids().len();
+
+    let exports: Vec<(MemId, ObmmMemDesc<UbPrivData>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| dir.export_idempotent("retry-1", &numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap()))
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+    let (memid, desc) = exports[0];
+    assert!(exports.iter().all(|export| *export == (memid, desc)));
+    assert_eq!(live_memids().len(), before + 1);
+
+    let reopened = MemlinkDir::open(root.path()).unwrap();
+    let (again, _) = reopened.export_idempotent::<UbPrivData>("retry-1", &numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap();
+    assert_eq!(again, memid);
+    let (other, _) = reopened.export_idempotent::<UbPrivData>("retry-2", &numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap();
+    assert_ne!(other, memid);
+    assert!(reopened.export_idempotent::<UbPrivData>("", &numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).is_err());
+
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    reopened.remove(memid).unwrap();
+    let (fresh, _) = reopened.export_idempotent::<UbPrivData>("retry-1", &numa_lengths! { 0 => MB }, ObmmExportFlags::empty()).unwrap();
+    assert_ne!(fresh, memid);
+    mem_unexport(fresh, ObmmUnexportFlags::empty()).unwrap();
+    mem_unexport(other, ObmmUnexportFlags::empty()).unwrap();
+}
@@ -0,0 +1,21 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_export_size_histogram_by_lifetime() {
+    let classes = [LifetimeClass::Ephemeral, LifetimeClass::Session, LifetimeClass::Persistent];
+    let before = classes.map(export_size_histogram_by_lifetime);
+    let mut lengths = vec![0; MAX_NUMA_NODES];
+    lengths[0] = 1024 * 1024;
+    let (memid, desc) = mem_export_with_lifetime::<UbPrivData>(&lengths, ObmmExportFlags::empty(), LifetimeClass::Persistent).unwrap();
+    assert_eq!(desc.lifetime, LifetimeClass::Persistent);
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    let (memid, desc) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::empty()).unwrap();
+    assert_eq!(desc.lifetime, LifetimeClass::Session);
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+
+    let after = classes.map(export_size_histogram_by_lifetime);
+    let added: Vec<u64> = (0..classes.len()).map(|class| after[class][8] - before[class][8]).collect();
+    assert_eq!(added, [0, 1, 1]);
+}
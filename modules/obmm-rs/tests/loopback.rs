@@ -0,0 +1,31 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_unexport_blocked_by_local_import() {
+    let mut lengths = vec![0; MAX_NUMA_NODES];
+    lengths[1] = 1024 * 1024;
+    let (memid, desc) = mem_export::<UbPrivData>(&lengths, ObmmExportFlags::ALLOWMMAP).unwrap();
+    let (imported, _) = mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+
+    assert_eq!(mem_unexport(memid, ObmmUnexportFlags::empty()), Err(ObmmError::StillImported(memid)));
+    mem_unimport(imported, ObmmUnimportFlags::empty()).unwrap();
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+}
+
+#[test]
+fn test_forced_unexport_with_local_import() {
+    let (memid, desc) = mem_export_at::<UbPrivData>(0xffff_fd00_0000, 0x20_0000, ObmmExportFlags::ALLOWMMAP).unwrap();
+    let (imported, _) = mem_import(&desc, ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+
+    mem_unexport(memid, ObmmUnexportFlags::FORCE).unwrap();
+    assert!(lookup_descriptor(memid).is_none());
+    mem_unimport(imported, ObmmUnimportFlags::empty()).unwrap();
+}
+
+#[test]
+fn test_unexport_without_local_import() {
+    let (memid, _) = mem_export_at::<UbPrivData>(0xffff_fe00_0000, 0x20_0000, ObmmExportFlags::ALLOWMMAP).unwrap();
+    mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_set_hook_next_memid() {
+    set_hook_next_memid(1000);
+    let memids: Vec<MemId> = (0..3)
+        .map(|_| mem_export::<UbPrivData>(&[4096; MAX_NUMA_NODES], ObmmExportFlags::empty()).unwrap().0)
+        .collect();
+    assert_eq!(memids, [1000, 1001, 1002]);
+
+    // moving the counter backwards would reissue live IDs
+    set_hook_next_memid(5);
+    let (imported, _) = mem_import(&ObmmMemDesc::example(), ObmmImportFlags::ALLOWMMAP, 0).unwrap();
+    assert_eq!(imported, 1003);
+
+    mem_unimport(imported, ObmmUnimportFlags::empty()).unwrap();
+    for memid in memids {
+        mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    }
+}
@@ -0,0 +1,15 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_check_overlaps_reports_overlapping_exports() {
+    let (first, _) = mem_export_at::<UbPrivData>(0x7f00_0000_0000, 0x20_0000, ObmmExportFlags::empty()).unwrap();
+    let (second, _) = mem_export_at::<UbPrivData>(0x7f00_0010_0000, 0x20_0000, ObmmExportFlags::empty()).unwrap();
+    let (disjoint, _) = mem_export_at::<UbPrivData>(0x7f00_1000_0000, 0x20_0000, ObmmExportFlags::empty()).unwrap();
+
+    assert_eq!(check_overlaps(), [(first, second)]);
+    mem_unexport(second, ObmmUnexportFlags::empty()).unwrap();
+    assert!(check_overlaps().is_empty());
+    mem_unexport(disjoint, ObmmUnexportFlags::empty()).unwrap();
+}
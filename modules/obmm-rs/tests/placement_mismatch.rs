@@ -0,0 +1,55 @@
+#![cfg(feature = "hook")]
+
+use std::sync::Mutex;
+
+use obmm_rs::*;
+
+/// Logger keeping every warning for inspection
+struct Capture(Mutex<Vec<String>>);
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+
+#[test]
+fn test_import_placement_mismatch() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+    let desc = ObmmMemDesc::<UbPrivData>::builder().addr(0xffff_fc00_0000).length(4096).build().unwrap();
+    let base_dist = ImportPlacement::PreferNode(2).base_dist().unwrap();
+    set_hook_import_node(5);
+
+    let live = live_memids();
+    let err = mem_import_strict(&desc, ObmmImportFlags::empty(), base_dist).unwrap_err();
+    assert_eq!(err, ObmmError::PlacementMismatch { base_dist, node: 5 });
+    assert_eq!(live_memids(), live);
+
+    let (memid, node) = mem_import(&desc, ObmmImportFlags::empty(), base_dist).unwrap();
+    assert_eq!(node, 5);
+    let warnings = LOGGER.0.lock().unwrap().clone();
+    assert!(warnings.iter().any(|warning| warning.contains(&format!("MemID {memid}")) && warning.contains("NUMA node 5")));
+
+    let (at, node, _) = mem_import_at(&desc, ObmmImportFlags::empty(), base_dist, 0x7f00_0000_0000).unwrap();
+    assert_eq!(node, 5);
+    let warnings = LOGGER.0.lock().unwrap().clone();
+    assert!(warnings.iter().any(|warning| warning.contains(&format!("MemID {at}")) && warning.contains("NUMA node 5")));
+    mem_unimport(at, ObmmUnimportFlags::empty()).unwrap();
+
+    set_hook_import_node(2);
+    let (strict, node) = mem_import_strict(&desc, ObmmImportFlags::empty(), base_dist).unwrap();
+    assert_eq!(node, 2);
+    mem_unimport(strict, ObmmUnimportFlags::empty()).unwrap();
+    mem_unimport(memid, ObmmUnimportFlags::empty()).unwrap();
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "hook")]
+
+use std::time::{Duration, Instant};
+
+use obmm_rs::*;
+
+/// Export and unexport `count` small regions, returning the time taken
+fn export_burst(count: usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..count {
+        let (memid, _) = mem_export::<UbPrivData>(&[4096; MAX_NUMA_NODES], ObmmExportFlags::empty()).unwrap();
+        mem_unexport(memid, ObmmUnexportFlags::empty()).unwrap();
+    }
+    start.elapsed()
+}
+
+// one test, as the limit is global to the process
+#[test]
+fn test_export_rate_limit() {
+    // 20 per second with a full bucket of 20: 10 more exports need 500ms
+    set_export_rate_limit(20);
+    assert!(export_burst(30) >= Duration::from_millis(450));
+
+    set_export_rate_limit_mode(RateLimitMode::Error);
+    set_export_rate_limit(5);
+    let _elapsed = export_burst(5);
+    let err = mem_export::<UbPrivData>(&[4096; MAX_NUMA_NODES], ObmmExportFlags::empty()).unwrap_err();
+    assert_eq!(err.downcast_ref::<ObmmError>(), Some(&ObmmError::RateLimited));
+    let err = mem_export_at::<UbPrivData>(0x6400_0000_0000, 4096, ObmmExportFlags::empty()).unwrap_err();
+    assert_eq!(err.downcast_ref::<ObmmError>(), Some(&ObmmError::RateLimited));
+
+    set_export_rate_limit(0);
+    assert!(export_burst(200) < Duration::from_millis(450));
+}
@@ -0,0 +1,12 @@
+#![cfg(feature = "hook")]
+
+use obmm_rs::*;
+
+#[test]
+fn test_selftest_passes() {
+    let report = run_selftest().unwrap();
+    assert!(report.passed(), "{report:?}");
+    let names: Vec<&str> = report.steps.iter().map(|step| step.name).collect();
+    assert_eq!(names, ["export", "json", "binary", "import", "verify", "unimport", "unexport"]);
+    assert!(live_memids().is_empty());
+}
@@ -0,0 +1,60 @@
+#![cfg(all(unix, feature = "uds"))]
+
+use std::thread;
+use obmm_rs::*;
+
+#[test]
+fn test_fetch_descriptor_over_uds() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("memlink.sock");
+    let desc = ObmmMemDesc::<UbPrivData>::builder()
+        .addr(0xffff_fc00_0000)
+        .length(1024 * 1024 * 128)
+        .tokenid(42)
+        .priv_data(UbPrivData::CACHEABLE)
+        .build()
+        .unwrap();
+
+    let server = DescServer::bind(&path).unwrap();
+    server.register(7, desc);
+    let handle = thread::spawn(move || {
+        server.serve_one().unwrap();
+        server.serve_one().unwrap();
+    });
+
+    match DescClient::fetch(&path, 7) {
+        Ok(fetched) => assert_eq!(fetched, desc),
+        Err(e) => panic!("fetch failed: {}", e),
+    }
+    assert!(DescClient::fetch(&path, 8).is_err());
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_validate_descriptor_over_uds() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("memlink.sock");
+    let desc = ObmmMemDesc::<UbPrivData>::builder()
+        .addr(0xffff_fc00_0000)
+        .length(1024 * 1024 * 128)
+        .tokenid(42)
+        .build()
+        .unwrap();
+    let mut moved = desc;
+    moved.addr = 0xffff_fd00_0000;
+    let mut expiring = desc;
+    expiring.expires_at = Some(std::time::SystemTime::UNIX_EPOCH);
+
+    let server = DescServer::bind(&path).unwrap();
+    server.register(7, desc);
+    let handle = thread::spawn(move || {
+        for _ in 0..3 {
+            server.serve_one().unwrap();
+        }
+    });
+
+    assert!(DescClient::validate(&path, &desc).unwrap());
+    assert!(!DescClient::validate(&path, &moved).unwrap());
+    assert!(!DescClient::validate(&path, &expiring).unwrap());
+    handle.join().unwrap();
+}
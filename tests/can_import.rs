@@ -0,0 +1,44 @@
+//! `memlink can-import` exit status for compatible and incompatible flags
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use obmm_rs::{ObmmExportFlags, ObmmMemDesc, UbPrivData};
+
+/// Write a descriptor exported with `export_flags` to a temporary file named after `name`
+fn write_desc(name: &str, export_flags: ObmmExportFlags) -> anyhow::Result<PathBuf> {
+    let desc = ObmmMemDesc::<UbPrivData>::builder()
+        .addr(0xffff_fc00_0000)
+        .length(1024 * 1024 * 128)
+        .priv_data(UbPrivData::OCHIP | UbPrivData::CACHEABLE)
+        .export_flags(export_flags)
+        .build()?;
+    let path = std::env::temp_dir().join(format!("memlink_can_import_{name}_{}.json", std::process::id()));
+    std::fs::write(&path, desc.to_json()?)?;
+    Ok(path)
+}
+
+/// Run `memlink can-import` on `desc` with import `flags`
+fn can_import(desc: &PathBuf, flags: &str) -> anyhow::Result<bool> {
+    let status = Command::new(env!("CARGO_BIN_EXE_memlink"))
+        .arg("can-import")
+        .arg("--desc")
+        .arg(desc)
+        .arg("--flags")
+        .arg(flags)
+        .status()?;
+    Ok(status.success())
+}
+
+#[test]
+fn test_can_import() -> anyhow::Result<()> {
+    let compatible = write_desc("compatible", ObmmExportFlags::ALLOWMMAP)?;
+    let incompatible = write_desc("incompatible", ObmmExportFlags::REMOTENUMA)?;
+    let passed = can_import(&compatible, "ALLOWMMAP")?;
+    let rejected = !can_import(&incompatible, "ALLOWMMAP")?;
+    std::fs::remove_file(compatible)?;
+    std::fs::remove_file(incompatible)?;
+    assert!(passed);
+    assert!(rejected);
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! `memlink compare` reporting divergence between two memlink directories
+
+use std::process::Command;
+
+use obmm_rs::{MemlinkDir, ObmmMemDesc, UbPrivData};
+
+#[test]
+fn test_compare_reports_divergence() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("memlink_compare_{}", std::process::id()));
+    let (a, b) = (MemlinkDir::open(root.join("a"))?, MemlinkDir::open(root.join("b"))?);
+    let desc = ObmmMemDesc::<UbPrivData>::example();
+    a.save(1, &desc)?;
+    b.save(1, &desc)?;
+    let compare = || Command::new(env!("CARGO_BIN_EXE_memlink")).arg("compare").arg("--a").arg(a.path()).arg("--b").arg(b.path()).output();
+
+    assert!(compare()?.status.success());
+    b.save(2, &desc)?;
+    let output = compare()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("MemID 2: only in"));
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! `memlink gc` removing descriptors left by dead processes and expired ones
+
+use std::process::Command;
+use std::time::Duration;
+
+use obmm_rs::{ENV_MEMLINK_DIR, MemlinkDir, MemlinkMeta, ObmmMemDesc, UbPrivData};
+
+/// Run `memlink gc` on `dir` with `extra` arguments
+fn gc(dir: &MemlinkDir, extra: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_memlink")).arg("gc").args(extra).env(ENV_MEMLINK_DIR, dir.path()).output()?;
+    anyhow::ensure!(output.status.success(), "gc failed: {}", String::from_utf8_lossy(&output.stderr));
+    Ok(())
+}
+
+#[test]
+fn test_gc_orphans() -> anyhow::Result<()> {
+    let dir = MemlinkDir::open(std::env::temp_dir().join(format!("memlink_gc_{}", std::process::id())))?;
+    let desc = ObmmMemDesc::<UbPrivData>::example();
+    for mem_id in [1, 2] {
+        dir.save(mem_id, &desc)?;
+    }
+    MemlinkMeta::current().to_file(dir.path(), 1)?;
+    let mut dead = MemlinkMeta::current();
+    dead.pid = u32::MAX;
+    dead.to_file(dir.path(), 2)?;
+    desc.to_json_file_with_ttl(dir.path(), 3, Duration::ZERO)?;
+
+    gc(&dir, &["--orphans-only"])?;
+    assert_eq!(dir.list()?, [1, 3]);
+    gc(&dir, &[])?;
+    assert_eq!(dir.list()?, [1]);
+    std::fs::remove_dir_all(dir.path())?;
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! `memlink export --stdout` piped into `memlink import -`
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use obmm_rs::MemId;
+
+/// Run `memlink export --stdout` with `extra` arguments and return its stdout
+fn export(extra: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = Command::new(env!("CARGO_BIN_EXE_memlink")).args(["export", "--stdout"]).args(extra).output()?;
+    anyhow::ensure!(output.status.success(), "export failed: {}", String::from_utf8_lossy(&output.stderr));
+    Ok(output.stdout)
+}
+
+/// Run `memlink import -` with `extra` arguments, feeding it `desc`, and return the printed MemID
+fn import(desc: &[u8], extra: &[&str]) -> anyhow::Result<MemId> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_memlink"))
+        .args(["import", "-", "--flags", "ALLOWMMAP"])
+        .args(extra)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().ok_or_else(|| anyhow::anyhow!("stdin not piped"))?.write_all(desc)?;
+    let output = child.wait_with_output()?;
+    anyhow::ensure!(output.status.success(), "import failed: {}", String::from_utf8_lossy(&output.stderr));
+    Ok(String::from_utf8(output.stdout)?.trim().parse()?)
+}
+
+#[test]
+fn test_pipe_json() -> anyhow::Result<()> {
+    let desc = export(&[])?;
+    assert!(desc.starts_with(b"{"));
+    let _memid = import(&desc, &[])?;
+    Ok(())
+}
+
+#[test]
+fn test_pipe_envelope() -> anyhow::Result<()> {
+    let key_file = std::env::temp_dir().join(format!("memlink_pipe_key_{}", std::process::id()));
+    std::fs::write(&key_file, "shared secret\n")?;
+    let key_arg = key_file.to_string_lossy().into_owned();
+    let desc = export(&["--key-file", &key_arg])?;
+    let signed = import(&desc, &["--key-file", &key_arg]);
+    let unsigned = import(&desc, &[]);
+    std::fs::remove_file(&key_file)?;
+    assert!(desc.starts_with(b"obmm:v1:"));
+    let _memid = signed?;
+    assert!(unsigned.is_err());
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! `memlink selftest` exit status under the hooked backend
+
+use std::process::Command;
+
+#[test]
+fn test_selftest_passes() -> anyhow::Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_memlink")).arg("selftest").output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "selftest failed: {stderr}");
+    assert!(stderr.contains("unexport: ok"));
+    Ok(())
+}